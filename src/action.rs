@@ -9,11 +9,15 @@
 //! `Choice` means actions made in execution.
 //! Example: I demand you transfer a 1 (make a choice) to my hand!
 
-use serde::Deserialize;
+use std::convert::Infallible;
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
 
 use crate::card::{Age, Card};
 use crate::game::Players;
 use crate::player::Player;
+use crate::utils::num_combinations;
 
 #[derive(Clone)]
 pub enum RefStep<'c> {
@@ -23,7 +27,7 @@ pub enum RefStep<'c> {
     Execute(&'c Card),
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum NoRefStep {
     Draw,
@@ -32,10 +36,10 @@ pub enum NoRefStep {
     Execute(String),
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
-pub enum NoRefChoice {
-    Card(Vec<String>),
+pub enum NoRefChoice<C = String> {
+    Card(Vec<C>),
     Opponent(usize),
     Yn(bool),
 }
@@ -50,13 +54,7 @@ pub enum RefChoice<'c, 'g> {
 impl<'c, 'g> RefChoice<'c, 'g> {
     pub fn card(self) -> Option<&'c Card> {
         match self {
-            RefChoice::Card(cards) => {
-                if cards.len() == 1 {
-                    Some(cards[0])
-                } else {
-                    panic!("Error when unwrapping Action to one card")
-                }
-            }
+            RefChoice::Card(cards) if cards.len() == 1 => Some(cards[0]),
             RefChoice::NoValidAction => None,
             _ => {
                 panic!("Error when unwrapping Action to one card")
@@ -96,32 +94,171 @@ pub enum RefAction<'c, 'g> {
     Executing(RefChoice<'c, 'g>),
 }
 
-#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 #[serde(untagged)]
-pub enum Action {
+pub enum Action<C = String> {
     Step(NoRefStep),
-    Executing(NoRefChoice),
+    Executing(NoRefChoice<C>),
 }
 
 impl Action {
     pub fn to_ref<'c, 'g>(self, game: &'g Players<'c>) -> RefAction<'c, 'g> {
         match self {
-            Action::Step(s) => RefAction::Step(match s {
-                NoRefStep::Draw => RefStep::Draw,
-                NoRefStep::Meld(name) => RefStep::Meld(game.find_card(&name)),
-                NoRefStep::Achieve(a) => RefStep::Achieve(a),
-                NoRefStep::Execute(name) => RefStep::Execute(game.find_card(&name)),
-            }),
-            Action::Executing(e) => RefAction::Executing(match e {
-                NoRefChoice::Card(names) => RefChoice::Card(
-                    names
-                        .into_iter()
-                        .map(|name| game.find_card(&name))
-                        .collect(),
-                ),
-                NoRefChoice::Opponent(id) => RefChoice::Opponent(game.player_at(id)),
-                NoRefChoice::Yn(yn) => RefChoice::Yn(yn),
-            }),
+            Action::Step(s) => RefAction::Step(s.to_ref(game)),
+            Action::Executing(e) => RefAction::Executing(e.to_ref(game)),
+        }
+    }
+}
+
+impl<C> Action<C> {
+    /// The action with its chosen cards mapped by `f`.
+    pub fn map<D>(&self, mut f: impl FnMut(&C) -> D) -> Action<D> {
+        match self.try_map(|card| Ok::<_, Infallible>(f(card))) {
+            Ok(action) => action,
+            Err(never) => match never {},
+        }
+    }
+
+    /// The action with its chosen cards mapped by `f`, or the first error of `f`.
+    pub fn try_map<D, E>(&self, f: impl FnMut(&C) -> Result<D, E>) -> Result<Action<D>, E> {
+        Ok(match self {
+            Action::Step(step) => Action::Step(step.clone()),
+            Action::Executing(NoRefChoice::Card(cards)) => Action::Executing(NoRefChoice::Card(
+                cards.iter().map(f).collect::<Result<_, _>>()?,
+            )),
+            Action::Executing(NoRefChoice::Opponent(id)) => {
+                Action::Executing(NoRefChoice::Opponent(*id))
+            }
+            Action::Executing(NoRefChoice::Yn(yn)) => Action::Executing(NoRefChoice::Yn(*yn)),
+        })
+    }
+}
+
+impl Action<&Card> {
+    /// The action with the names of its chosen cards.
+    pub fn no_ref(&self) -> Action {
+        self.map(|card| card.name().to_owned())
+    }
+}
+
+impl NoRefStep {
+    pub fn to_ref<'c>(self, game: &Players<'c>) -> RefStep<'c> {
+        match self {
+            NoRefStep::Draw => RefStep::Draw,
+            NoRefStep::Meld(name) => RefStep::Meld(game.find_card(&name)),
+            NoRefStep::Achieve(a) => RefStep::Achieve(a),
+            NoRefStep::Execute(name) => RefStep::Execute(game.find_card(&name)),
+        }
+    }
+}
+
+impl NoRefChoice {
+    pub fn to_ref<'c, 'g>(self, game: &'g Players<'c>) -> RefChoice<'c, 'g> {
+        match self {
+            NoRefChoice::Card(names) => RefChoice::Card(
+                names
+                    .into_iter()
+                    .map(|name| game.find_card(&name))
+                    .collect(),
+            ),
+            NoRefChoice::Opponent(id) => RefChoice::Opponent(game.player_at(id)),
+            NoRefChoice::Yn(yn) => RefChoice::Yn(yn),
+        }
+    }
+}
+
+/// Card choices with more valid subsets than this are not listed
+/// by `OuterGame::legal_actions`.
+pub const MAX_LISTED_CARD_CHOICES: usize = 256;
+
+/// The valid actions in a state, as returned by `OuterGame::legal_actions`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LegalActions {
+    Listed(Vec<Action>),
+    /// Choosing any `min_num` to `max_num` cards from `from` is valid,
+    /// but there are too many such subsets to list them all.
+    CardSubsets {
+        min_num: usize,
+        max_num: usize,
+        from: Vec<String>,
+    },
+}
+
+impl LegalActions {
+    pub fn contains(&self, action: &Action) -> bool {
+        match self {
+            LegalActions::Listed(actions) => actions.contains(action),
+            LegalActions::CardSubsets {
+                min_num,
+                max_num,
+                from,
+            } => match action {
+                Action::Executing(NoRefChoice::Card(cards)) => {
+                    (*min_num..=*max_num).contains(&cards.len())
+                        && cards.iter().all(|name| from.contains(name))
+                        && cards
+                            .iter()
+                            .enumerate()
+                            .all(|(i, name)| !cards[..i].contains(name))
+                }
+                _ => false,
+            },
+        }
+    }
+
+    /// The first valid action, taking the first `min_num` cards of card subsets.
+    pub fn first(&self) -> Option<Action> {
+        match self {
+            LegalActions::Listed(actions) => actions.first().cloned(),
+            LegalActions::CardSubsets { min_num, from, .. } => Some(Action::Executing(
+                NoRefChoice::Card(from[..*min_num].to_vec()),
+            )),
+        }
+    }
+
+    /// A valid action chosen uniformly at random, if any.
+    pub fn choose<R: Rng + ?Sized>(&self, rng: &mut R) -> Option<Action> {
+        match self {
+            LegalActions::Listed(actions) => actions.choose(rng).cloned(),
+            LegalActions::CardSubsets {
+                min_num,
+                max_num,
+                from,
+            } => {
+                // choose the size first, weighted by the number of subsets of each size
+                let weights: Vec<f64> = (*min_num..=*max_num)
+                    .map(|k| {
+                        (0..k)
+                            .map(|i| (from.len() - i) as f64 / (k - i) as f64)
+                            .product()
+                    })
+                    .collect();
+                let mut x = rng.gen::<f64>() * weights.iter().sum::<f64>();
+                let mut num = *max_num;
+                for (k, weight) in (*min_num..).zip(weights) {
+                    if x < weight {
+                        num = k;
+                        break;
+                    }
+                    x -= weight;
+                }
+                Some(Action::Executing(NoRefChoice::Card(
+                    from.choose_multiple(rng, num).cloned().collect(),
+                )))
+            }
+        }
+    }
+
+    /// The number of valid actions, saturating at `usize::MAX`.
+    pub fn count(&self) -> usize {
+        match self {
+            LegalActions::Listed(actions) => actions.len(),
+            LegalActions::CardSubsets {
+                min_num,
+                max_num,
+                from,
+            } => num_combinations(from.len(), *min_num, *max_num),
         }
     }
 }
@@ -129,11 +266,14 @@ impl Action {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use serde_json::from_str;
     use Action::*;
     use NoRefChoice::*;
     use NoRefStep::*;
 
+    fn from_str(s: &str) -> serde_json::Result<Action> {
+        serde_json::from_str(s)
+    }
+
     #[test]
     fn action_deserialization() {
         matches!(from_str("draw"), Ok(Step(Draw)));
@@ -0,0 +1,186 @@
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use strum::IntoEnumIterator;
+
+use crate::{
+    action::{Action, LegalActions, NoRefStep},
+    agent::Agent,
+    board::Board,
+    card::Icon,
+    observation::{ObsType, Observation},
+};
+
+/// The features of the observer's position which are evaluated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Position {
+    pub score: usize,
+    pub achievements: usize,
+    /// The number of icons of which the observer has more than every opponent.
+    pub icon_dominance: usize,
+}
+
+impl Position {
+    pub fn of(obs: &Observation) -> Position {
+        Position::with_board(obs, &obs.main_player.board)
+    }
+
+    fn with_board(obs: &Observation, board: &Board) -> Position {
+        let icons = board.regular_icon_count();
+        let opponent_icons: Vec<_> = obs
+            .other_players
+            .iter()
+            .map(|p| p.board.regular_icon_count())
+            .collect();
+        Position {
+            score: obs.main_player.score.iter().map(|c| c.age() as usize).sum(),
+            achievements: obs.main_player.achievements.len(),
+            icon_dominance: Icon::iter()
+                .filter(|icon| opponent_icons.iter().all(|o| icons[icon] > o[icon]))
+                .count(),
+        }
+    }
+
+    /// The position right after the step `action`, as far as can be foreseen.
+    ///
+    /// Draws and the effects of dogmas are unknown, so they don't change the position.
+    fn after(obs: &Observation, action: &Action) -> Position {
+        match action {
+            Action::Step(NoRefStep::Meld(name)) => {
+                let card = obs
+                    .main_player
+                    .hand
+                    .iter()
+                    .find(|c| c.name() == name)
+                    .expect("Melded card should be in hand.");
+                let mut board = (*obs.main_player.board).clone();
+                board.meld(card);
+                Position::with_board(obs, &board)
+            }
+            Action::Step(NoRefStep::Achieve(_)) => {
+                let mut position = Position::of(obs);
+                position.achievements += 1;
+                position
+            }
+            _ => Position::of(obs),
+        }
+    }
+}
+
+/// Weights of the features of a `Position`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Evaluation {
+    score: f64,
+    achievements: f64,
+    icon_dominance: f64,
+}
+
+impl Default for Evaluation {
+    fn default() -> Self {
+        Evaluation {
+            score: 1.0,
+            achievements: 5.0,
+            icon_dominance: 1.0,
+        }
+    }
+}
+
+impl Evaluation {
+    pub fn score(mut self, weight: f64) -> Evaluation {
+        self.score = weight;
+        self
+    }
+
+    pub fn achievements(mut self, weight: f64) -> Evaluation {
+        self.achievements = weight;
+        self
+    }
+
+    pub fn icon_dominance(mut self, weight: f64) -> Evaluation {
+        self.icon_dominance = weight;
+        self
+    }
+
+    pub fn evaluate(&self, position: &Position) -> f64 {
+        self.score * position.score as f64
+            + self.achievements * position.achievements as f64
+            + self.icon_dominance * position.icon_dominance as f64
+    }
+}
+
+/// Takes the step leading to the best evaluated position, breaking ties at
+/// random, and makes random choices during dogmas.
+pub struct GreedyAgent {
+    evaluation: Evaluation,
+    rng: StdRng,
+}
+
+impl GreedyAgent {
+    pub fn new(evaluation: Evaluation, seed: u64) -> GreedyAgent {
+        GreedyAgent {
+            evaluation,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Agent for GreedyAgent {
+    fn act(&mut self, obs: &Observation) -> Action {
+        let legal = obs.legal_actions();
+        if let (ObsType::Main, LegalActions::Listed(actions)) = (&obs.obstype, &legal) {
+            let values: Vec<f64> = actions
+                .iter()
+                .map(|action| self.evaluation.evaluate(&Position::after(obs, action)))
+                .collect();
+            let best = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let best_actions: Vec<&Action> = actions
+                .iter()
+                .zip(values)
+                .filter(|&(_, value)| value >= best)
+                .map(|(action, _)| action)
+                .collect();
+            if let Some(&action) = best_actions.choose(&mut self.rng) {
+                return action.clone();
+            }
+        }
+        legal
+            .choose(&mut self.rng)
+            .expect("The acting player has some valid action.")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{card::default_cards, game::GameConfig, player::PlayerBuilder};
+
+    #[test]
+    fn greedy_meld() {
+        let archery = default_cards::archery();
+        let oars = default_cards::oars();
+        let pottery = default_cards::pottery();
+        let agriculture = default_cards::agriculture();
+        let game = GameConfig::new(vec![&archery, &oars, &pottery, &agriculture])
+            .players(vec![
+                PlayerBuilder::default().hand(vec![&oars, &pottery]),
+                PlayerBuilder::default().board(vec![&agriculture]),
+            ])
+            .build();
+        let obs = game.observe(0);
+        assert_eq!(
+            Position::of(&obs),
+            Position {
+                score: 0,
+                achievements: 0,
+                icon_dominance: 0
+            }
+        );
+        // Oars gives castles and a crown, which the opponent has none of,
+        // while Pottery gives leaves, which is no more than Agriculture.
+        let meld = |name: &str| Action::Step(NoRefStep::Meld(name.to_owned()));
+        assert_eq!(Position::after(&obs, &meld("Oars")).icon_dominance, 2);
+        assert_eq!(Position::after(&obs, &meld("Pottery")).icon_dominance, 0);
+        let mut agent = GreedyAgent::new(Evaluation::default(), 0);
+        assert_eq!(agent.act(&obs), meld("Oars"));
+        let mut agent = GreedyAgent::new(Evaluation::default().icon_dominance(-1.0), 0);
+        assert_ne!(agent.act(&obs), meld("Oars"));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    action::{Action, NoRefChoice, NoRefStep},
+    agent::Agent,
+    card::{Card, Dogma},
+    observation::{ObsType, Observation},
+    state::Choose,
+};
+
+/// Follows a few fixed rules.
+///
+/// In the main phase, it achieves the highest age possible, or executes the
+/// highest dogma that no opponent can share and whose demands affect someone,
+/// or melds the highest card in hand if it's higher than the top card of its
+/// color, or draws. During dogmas, it says yes, chooses the opponent with the
+/// highest score, and chooses the first cards offered.
+#[derive(Default)]
+pub struct HeuristicAgent;
+
+impl HeuristicAgent {
+    pub fn new() -> HeuristicAgent {
+        HeuristicAgent
+    }
+
+    fn step(&self, obs: &Observation) -> NoRefStep {
+        let player = &obs.main_player;
+        if let Some(age) = (1..=10).rev().find(|&age| obs.can_achieve(age)) {
+            return NoRefStep::Achieve(age);
+        }
+        let best_dogma = player
+            .board
+            .top_cards()
+            .into_iter()
+            .filter(|card| is_exclusive(obs, card))
+            .max_by_key(|card| card.age());
+        if let Some(card) = best_dogma {
+            return NoRefStep::Execute(card.name().to_owned());
+        }
+        let best_meld = player
+            .hand
+            .iter()
+            .filter(|card| {
+                player
+                    .board
+                    .get_stack(card.color())
+                    .top_card()
+                    .is_none_or(|top| top.age() < card.age())
+            })
+            .max_by_key(|card| card.age());
+        if let Some(card) = best_meld {
+            return NoRefStep::Meld(card.name().to_owned());
+        }
+        NoRefStep::Draw
+    }
+
+    fn choice(&self, obs: &Observation, state: &Choose) -> NoRefChoice {
+        match state {
+            Choose::Card { from, .. } => {
+                let (min_num, _) = state.card_num_bounds().expect("Card choices have bounds.");
+                NoRefChoice::Card(
+                    from[..min_num]
+                        .iter()
+                        .map(|c| c.name().to_owned())
+                        .collect(),
+                )
+            }
+            Choose::Opponent => {
+                let num_players = obs.num_players();
+                let (offset, _) = obs
+                    .other_players
+                    .iter()
+                    .enumerate()
+                    .max_by_key(|(_, p)| p.score.iter().map(|&age| age as usize).sum::<usize>())
+                    .expect("There are opponents.");
+                NoRefChoice::Opponent((obs.main_player_id + offset + 1) % num_players)
+            }
+            Choose::Yn => NoRefChoice::Yn(true),
+        }
+    }
+}
+
+/// Checks if no opponent would share the dogmas of `card`, and its demands
+/// would affect some opponent.
+fn is_exclusive(obs: &Observation, card: &Card) -> bool {
+    let icon = card.main_icon();
+    let own = obs.main_player.board.regular_icon_count()[&icon];
+    let opponents: Vec<usize> = obs
+        .other_players
+        .iter()
+        .map(|p| p.board.regular_icon_count()[&icon])
+        .collect();
+    card.dogmas().iter().all(|dogma| match dogma {
+        Dogma::Share(_) => opponents.iter().all(|&n| n < own),
+        Dogma::Demand(_) => opponents.iter().any(|&n| n < own),
+    })
+}
+
+impl Agent for HeuristicAgent {
+    fn act(&mut self, obs: &Observation) -> Action {
+        match &obs.obstype {
+            ObsType::Main => Action::Step(self.step(obs)),
+            ObsType::Executing(execution) => Action::Executing(self.choice(obs, &execution.state)),
+        }
+    }
+}
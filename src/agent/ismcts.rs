@@ -0,0 +1,273 @@
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+use crate::{
+    action::{Action, LegalActions},
+    agent::{Agent, HeuristicAgent},
+    card::Card,
+    determinize::determinize,
+    error::InnResult,
+    game::{OuterGame, PlayerId},
+    observation::{ObsType, Observation},
+};
+
+#[derive(Clone, Debug)]
+pub struct IsmctsConfig {
+    iterations: usize,
+    time_limit: Option<Duration>,
+    exploration: f64,
+    max_playout_steps: usize,
+}
+
+impl Default for IsmctsConfig {
+    fn default() -> Self {
+        IsmctsConfig {
+            iterations: 1000,
+            time_limit: None,
+            exploration: 0.7,
+            max_playout_steps: 400,
+        }
+    }
+}
+
+impl IsmctsConfig {
+    /// The largest number of iterations of each search.
+    pub fn iterations(mut self, iterations: usize) -> IsmctsConfig {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Stops each search after `time_limit`, even with iterations left.
+    pub fn time_limit(mut self, time_limit: Duration) -> IsmctsConfig {
+        self.time_limit = Some(time_limit);
+        self
+    }
+
+    /// The exploration constant of UCB.
+    pub fn exploration(mut self, exploration: f64) -> IsmctsConfig {
+        self.exploration = exploration;
+        self
+    }
+
+    /// Playouts longer than this are stopped, and the players leading by
+    /// achievements then score are considered the winners.
+    pub fn max_playout_steps(mut self, max_playout_steps: usize) -> IsmctsConfig {
+        self.max_playout_steps = max_playout_steps;
+        self
+    }
+}
+
+struct Node {
+    /// The action leading to this node, and the player who took it.
+    action: Option<(Action, PlayerId)>,
+    children: Vec<usize>,
+    visits: usize,
+    /// How many times this node could have been chosen.
+    availability: usize,
+    reward: f64,
+}
+
+impl Node {
+    fn new(action: Option<(Action, PlayerId)>) -> Node {
+        Node {
+            action,
+            children: Vec::new(),
+            visits: 0,
+            availability: 0,
+            reward: 0.0,
+        }
+    }
+}
+
+/// Single-observer information set Monte Carlo tree search.
+///
+/// Each iteration samples the hidden information consistent with the
+/// observation, then searches the sampled game. Only steps of the main phase
+/// are searched, because states during dogmas can't be sampled; choices
+/// during dogmas are made by `HeuristicAgent`.
+pub struct IsmctsAgent<'c> {
+    cards: Vec<&'c Card>,
+    config: IsmctsConfig,
+    rng: StdRng,
+}
+
+impl<'c> IsmctsAgent<'c> {
+    /// `cards` should be the card list of the game, or another instance of it.
+    pub fn new(cards: Vec<&'c Card>, config: IsmctsConfig, seed: u64) -> IsmctsAgent<'c> {
+        IsmctsAgent {
+            cards,
+            config,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn search(&mut self, obs: &Observation) -> Option<Action> {
+        let start = Instant::now();
+        let mut tree = vec![Node::new(None)];
+        for _ in 0..self.config.iterations {
+            if let Some(limit) = self.config.time_limit {
+                if start.elapsed() >= limit {
+                    break;
+                }
+            }
+            let config = determinize(obs, &self.cards, &mut self.rng).ok()?;
+            let mut game = config.build();
+            // an error only stops this iteration
+            let _ = self.iterate(&mut tree, &mut game);
+        }
+        tree[0]
+            .children
+            .iter()
+            .max_by_key(|&&child| tree[child].visits)
+            .and_then(|&child| tree[child].action.clone())
+            .map(|(action, _)| action)
+    }
+
+    fn iterate(&mut self, tree: &mut Vec<Node>, game: &mut OuterGame) -> InnResult<()> {
+        // selection and expansion
+        let mut node = 0;
+        let mut path = Vec::new();
+        while !game.is_over() {
+            let legal = match game.legal_actions() {
+                LegalActions::Listed(actions) => actions,
+                LegalActions::CardSubsets { .. } => break,
+            };
+            let player = game.acting_player();
+            let mut available = Vec::new();
+            for &child in tree[node].children.iter() {
+                let (action, _) = tree[child].action.as_ref().unwrap();
+                if legal.contains(action) {
+                    available.push(child);
+                }
+            }
+            let untried: Vec<&Action> = legal
+                .iter()
+                .filter(|&action| {
+                    !available
+                        .iter()
+                        .any(|&child| &tree[child].action.as_ref().unwrap().0 == action)
+                })
+                .collect();
+            for &child in available.iter() {
+                tree[child].availability += 1;
+            }
+            if let Some(&action) = untried.choose(&mut self.rng) {
+                let child = tree.len();
+                tree.push(Node::new(Some((action.clone(), player))));
+                tree[child].availability += 1;
+                tree[node].children.push(child);
+                path.push(child);
+                game.step(action.clone())?;
+                break;
+            }
+            let exploration = self.config.exploration;
+            let ucb = |node: &Node| {
+                node.reward / node.visits as f64
+                    + exploration * ((node.availability as f64).ln() / node.visits as f64).sqrt()
+            };
+            let child = match available
+                .into_iter()
+                .max_by(|&a, &b| ucb(&tree[a]).total_cmp(&ucb(&tree[b])))
+            {
+                Some(child) => child,
+                None => break,
+            };
+            path.push(child);
+            game.step(tree[child].action.as_ref().unwrap().0.clone())?;
+            node = child;
+        }
+
+        // playout
+        for _ in 0..self.config.max_playout_steps {
+            if game.is_over() {
+                break;
+            }
+            let action = match game.legal_actions().choose(&mut self.rng) {
+                Some(action) => action,
+                None => break,
+            };
+            game.step(action)?;
+        }
+
+        // backpropagation
+        let rewards = rewards(game);
+        for child in path {
+            let node = &mut tree[child];
+            node.visits += 1;
+            node.reward += rewards[node.action.as_ref().unwrap().1];
+        }
+        Ok(())
+    }
+}
+
+/// Splits a reward of 1 among the winners, or the leading players if the
+/// game is not over.
+fn rewards(game: &OuterGame) -> Vec<f64> {
+    let players = game.players();
+    let winners: Vec<PlayerId> = match game.winners() {
+        Some(winners) => winners.to_vec(),
+        None => {
+            let standing = |id: PlayerId| {
+                let player = players.player_at(id);
+                (player.achievements().inner().len(), player.total_score())
+            };
+            let best = (0..players.num_players()).map(standing).max().unwrap();
+            (0..players.num_players())
+                .filter(|&id| standing(id) == best)
+                .collect()
+        }
+    };
+    (0..players.num_players())
+        .map(|id| {
+            if winners.contains(&id) {
+                1.0 / winners.len() as f64
+            } else {
+                0.0
+            }
+        })
+        .collect()
+}
+
+impl<'c> Agent for IsmctsAgent<'c> {
+    fn act(&mut self, obs: &Observation) -> Action {
+        let legal = obs.legal_actions();
+        if let (ObsType::Main, LegalActions::Listed(actions)) = (&obs.obstype, &legal) {
+            if actions.len() == 1 {
+                return actions[0].clone();
+            }
+            if let Some(action) = self.search(obs) {
+                return action;
+            }
+        }
+        HeuristicAgent::new().act(obs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{Match, RandomAgent},
+        card::default_cards,
+        game::GameConfig,
+    };
+
+    #[test]
+    fn ismcts_plays() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let config = GameConfig::standard(cards.clone(), 11).default_players(2);
+        // another instance of the card list
+        let own_cards = default_cards::default_cards();
+        let agent = IsmctsAgent::new(
+            own_cards.iter().collect(),
+            IsmctsConfig::default().iterations(30),
+            0,
+        );
+        let result = Match::new(vec![Box::new(agent), Box::new(RandomAgent::new(0))])
+            .play(config)
+            .unwrap();
+        assert!(!result.winners.is_empty());
+    }
+}
@@ -0,0 +1,422 @@
+//! Agents choosing actions from observations, and `Match`, which plays a
+//! game with one agent per seat.
+
+mod greedy;
+mod heuristic;
+mod ismcts;
+mod random;
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    action::Action,
+    error::{InnResult, InnovationError},
+    game::{GameConfig, OuterGame, PlayerId},
+    logger::{Logger, Observer},
+    observation::{GameState, Observation},
+    protocol::{redact, Event},
+    trajectory::TrajectoryRecorder,
+};
+
+pub use greedy::{Evaluation, GreedyAgent, Position};
+pub use heuristic::HeuristicAgent;
+pub use ismcts::{IsmctsAgent, IsmctsConfig};
+pub use random::RandomAgent;
+
+pub trait Agent {
+    /// Chooses the action of its seat, which is the acting player of `obs`.
+    fn act(&mut self, obs: &Observation) -> Action;
+
+    /// Called before the game starts, with the seat of the agent.
+    fn on_game_start(&mut self, _player: PlayerId, _num_players: usize) {}
+
+    /// Called for every event of the game, in order, with the cards hidden
+    /// from its seat.
+    fn on_event(&mut self, _event: &Event) {}
+
+    /// Called after the game ends.
+    fn on_game_end(&mut self, _winners: &[PlayerId]) {}
+}
+
+impl<A: Agent + ?Sized> Agent for Box<A> {
+    fn act(&mut self, obs: &Observation) -> Action {
+        (**self).act(obs)
+    }
+
+    fn on_game_start(&mut self, player: PlayerId, num_players: usize) {
+        (**self).on_game_start(player, num_players)
+    }
+
+    fn on_event(&mut self, event: &Event) {
+        (**self).on_event(event)
+    }
+
+    fn on_game_end(&mut self, winners: &[PlayerId]) {
+        (**self).on_game_end(winners)
+    }
+}
+
+impl<A: Agent + ?Sized> Agent for &mut A {
+    fn act(&mut self, obs: &Observation) -> Action {
+        (**self).act(obs)
+    }
+
+    fn on_game_start(&mut self, player: PlayerId, num_players: usize) {
+        (**self).on_game_start(player, num_players)
+    }
+
+    fn on_event(&mut self, event: &Event) {
+        (**self).on_event(event)
+    }
+
+    fn on_game_end(&mut self, winners: &[PlayerId]) {
+        (**self).on_game_end(winners)
+    }
+}
+
+/// What `Match` does when an agent chooses an invalid action.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InvalidActionPolicy {
+    /// Stops the match with `InnovationError::InvalidAction`.
+    Abort,
+    /// Asks the agent again, at most the given number of times, then aborts.
+    Retry(usize),
+    /// Plays the first legal action instead.
+    FirstLegal,
+    /// The agent loses, and all other players win.
+    Forfeit,
+}
+
+pub struct MatchResult<'c> {
+    pub winners: Vec<PlayerId>,
+    /// The player who forfeited by an invalid action, if any.
+    pub forfeited: Option<PlayerId>,
+    /// The total scores of the players at the end.
+    pub scores: Vec<usize>,
+    /// The log of the game, which is the only game in its history.
+    pub logger: Logger<'c>,
+}
+
+enum Next {
+    Act(PlayerId),
+    End(Vec<PlayerId>),
+    Forfeit(PlayerId),
+}
+
+impl From<GameState<'_>> for Next {
+    fn from(state: GameState) -> Self {
+        match state {
+            GameState::Normal(player) => Next::Act(player),
+            GameState::End(end) => Next::End(end.winners),
+        }
+    }
+}
+
+/// Plays games with one agent per seat, the `i`th agent being player `i`.
+pub struct Match<'a> {
+    agents: Vec<Box<dyn Agent + 'a>>,
+    policy: InvalidActionPolicy,
+    recorder: Option<Rc<RefCell<TrajectoryRecorder>>>,
+}
+
+impl<'a> Match<'a> {
+    pub fn new(agents: Vec<Box<dyn Agent + 'a>>) -> Match<'a> {
+        Match {
+            agents,
+            policy: InvalidActionPolicy::Abort,
+            recorder: None,
+        }
+    }
+
+    pub fn invalid_action_policy(mut self, policy: InvalidActionPolicy) -> Match<'a> {
+        self.policy = policy;
+        self
+    }
+
+    /// Records the decisions of all seats in the games played.
+    pub fn record(mut self, recorder: Rc<RefCell<TrajectoryRecorder>>) -> Match<'a> {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    pub fn agents_mut(&mut self) -> &mut [Box<dyn Agent + 'a>] {
+        &mut self.agents
+    }
+
+    pub fn into_agents(self) -> Vec<Box<dyn Agent + 'a>> {
+        self.agents
+    }
+
+    /// Builds a game from `config`, starts it and plays it to the end.
+    ///
+    /// `config` should have as many players as there are agents.
+    pub fn play<'c>(&mut self, config: GameConfig<'c>) -> InnResult<MatchResult<'c>> {
+        let logger = Rc::new(RefCell::new(Logger::new()));
+        let observer: Rc<RefCell<dyn Observer<'c> + 'c>> = logger.clone();
+        let mut config = config.observe(&observer);
+        drop(observer);
+        if let Some(recorder) = &self.recorder {
+            let observer: Rc<RefCell<dyn Observer<'c> + 'c>> = recorder.clone();
+            config = config.observe(&observer);
+        }
+        let mut game = config.build();
+        let num_players = game.players().num_players();
+        if num_players != self.agents.len() {
+            return Err(InnovationError::WrongPlayerNum);
+        }
+        logger.borrow_mut().start_game(game.players());
+        if let Some(recorder) = &self.recorder {
+            recorder.borrow_mut().begin_game();
+        }
+        for (id, agent) in self.agents.iter_mut().enumerate() {
+            agent.on_game_start(id, num_players);
+        }
+
+        let mut delivered = 0;
+        let mut next = Next::from(game.start()?);
+        let (winners, forfeited) = loop {
+            self.deliver_events(&logger, &mut delivered);
+            let player = match next {
+                Next::Act(player) => player,
+                Next::End(winners) => break (winners, None),
+                Next::Forfeit(player) => {
+                    break (
+                        (0..num_players).filter(|&id| id != player).collect(),
+                        Some(player),
+                    )
+                }
+            };
+            let mut retries = 0;
+            next = loop {
+                let action = self.agents[player].act(&game.observe(player));
+                self.propose(&game, player, &action);
+                match game.step(action).map(Next::from) {
+                    Ok(next) => break next,
+                    Err(InnovationError::InvalidAction) => match self.policy {
+                        InvalidActionPolicy::Retry(max_retries) if retries < max_retries => {
+                            retries += 1;
+                        }
+                        InvalidActionPolicy::Abort | InvalidActionPolicy::Retry(_) => {
+                            return Err(InnovationError::InvalidAction);
+                        }
+                        InvalidActionPolicy::FirstLegal => {
+                            let action = game
+                                .legal_actions()
+                                .first()
+                                .ok_or(InnovationError::InvalidAction)?;
+                            self.propose(&game, player, &action);
+                            break Next::from(game.step(action)?);
+                        }
+                        InvalidActionPolicy::Forfeit => break Next::Forfeit(player),
+                    },
+                    Err(e) => return Err(e),
+                }
+            };
+        };
+        for agent in self.agents.iter_mut() {
+            agent.on_game_end(&winners);
+        }
+
+        let scores: Vec<usize> = (0..num_players)
+            .map(|id| game.players().player_at(id).total_score())
+            .collect();
+        if let Some(recorder) = &self.recorder {
+            recorder.borrow_mut().end_game(&winners, &scores);
+        }
+        drop(game);
+        logger.borrow_mut().finish(winners.clone());
+        let logger = Rc::try_unwrap(logger)
+            .ok()
+            .expect("The game holds only a weak reference to the logger.")
+            .into_inner();
+        Ok(MatchResult {
+            winners,
+            forfeited,
+            scores,
+            logger,
+        })
+    }
+
+    fn propose(&self, game: &OuterGame, player: PlayerId, action: &Action) {
+        if let Some(recorder) = &self.recorder {
+            recorder.borrow_mut().propose(game, player, action);
+        }
+    }
+
+    fn deliver_events(&mut self, logger: &RefCell<Logger>, delivered: &mut usize) {
+        let logger = logger.borrow();
+        let items = &logger
+            .current_game()
+            .expect("The logger is started with the game.")
+            .items[*delivered..];
+        for item in items {
+            for (seat, agent) in self.agents.iter_mut().enumerate() {
+                agent.on_event(&redact(item, Some(seat)));
+            }
+        }
+        *delivered += items.len();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{NoRefChoice, NoRefStep},
+        card::default_cards,
+        card_pile::MainCardPile,
+        logger::SimpleOp,
+        observation::ObsType,
+        protocol::SeenCard,
+        record::{ItemData, OperationData},
+        state::Choose,
+    };
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Draws whenever possible, and makes the first valid choice otherwise.
+    /// Chooses invalid actions first, if asked to.
+    #[derive(Default)]
+    struct Drawer {
+        invalid_actions: usize,
+        events: usize,
+        draws: usize,
+        seat: Option<PlayerId>,
+        winners: Option<Vec<PlayerId>>,
+    }
+
+    impl Agent for Drawer {
+        fn act(&mut self, obs: &Observation) -> Action {
+            if self.invalid_actions > 0 {
+                self.invalid_actions -= 1;
+                return Action::Step(NoRefStep::Meld("No Such Card".to_owned()));
+            }
+            match &obs.obstype {
+                ObsType::Main => Action::Step(NoRefStep::Draw),
+                ObsType::Executing(execution) => Action::Executing(match &execution.state {
+                    Choose::Card { min_num, from, .. } => NoRefChoice::Card(
+                        from[..*min_num]
+                            .iter()
+                            .map(|c| c.name().to_owned())
+                            .collect(),
+                    ),
+                    Choose::Opponent => NoRefChoice::Opponent(1 - obs.main_player_id),
+                    Choose::Yn => NoRefChoice::Yn(false),
+                }),
+            }
+        }
+
+        fn on_game_start(&mut self, player: PlayerId, num_players: usize) {
+            assert_eq!(num_players, 2);
+            self.seat = Some(player);
+        }
+
+        fn on_event(&mut self, event: &Event) {
+            self.events += 1;
+            // only the drawer sees the card drawn
+            if let ItemData::Operation(OperationData::SimpleOp(SimpleOp::Draw, id, card, _)) = event
+            {
+                assert_eq!(matches!(card, SeenCard::Name(_)), self.seat == Some(*id));
+                self.draws += 1;
+            }
+        }
+
+        fn on_game_end(&mut self, winners: &[PlayerId]) {
+            self.winners = Some(winners.to_vec());
+        }
+    }
+
+    fn play(
+        drawers: &mut [Drawer; 2],
+        policy: InvalidActionPolicy,
+    ) -> InnResult<(Vec<PlayerId>, Option<PlayerId>, usize)> {
+        let cards = [
+            default_cards::archery(),
+            default_cards::pottery(),
+            default_cards::agriculture(),
+            default_cards::oars(),
+        ];
+        let cards: Vec<_> = cards.iter().collect();
+        let config = GameConfig::new(cards.clone())
+            .main_pile(MainCardPile::builder().draw_deck(cards).build());
+        let [first, second] = drawers;
+        let result = Match::new(vec![Box::new(first), Box::new(second)])
+            .invalid_action_policy(policy)
+            .play(config)?;
+        let history = result.logger.history();
+        assert_eq!(history.len(), 1);
+        Ok((result.winners, result.forfeited, history[0].items.len()))
+    }
+
+    #[test]
+    fn play_to_end() {
+        let mut drawers: [Drawer; 2] = Default::default();
+        let (winners, forfeited, num_items) =
+            play(&mut drawers, InvalidActionPolicy::Abort).unwrap();
+        // after melding the first cards, drawing from the empty pile ends the game
+        assert_eq!(winners, vec![0, 1]);
+        assert_eq!(forfeited, None);
+        for (id, drawer) in drawers.iter().enumerate() {
+            assert_eq!(drawer.seat, Some(id));
+            assert_eq!(drawer.events, num_items);
+            assert!(drawer.draws > 0);
+            assert_eq!(drawer.winners.as_deref(), Some(&[0, 1][..]));
+        }
+    }
+
+    #[test]
+    fn invalid_actions() {
+        let mut drawers: [Drawer; 2] = Default::default();
+        drawers[1].invalid_actions = 2;
+        assert!(matches!(
+            play(&mut drawers, InvalidActionPolicy::Abort),
+            Err(InnovationError::InvalidAction)
+        ));
+
+        let mut drawers: [Drawer; 2] = Default::default();
+        drawers[1].invalid_actions = 2;
+        assert!(play(&mut drawers, InvalidActionPolicy::Retry(1)).is_err());
+
+        let mut drawers: [Drawer; 2] = Default::default();
+        drawers[1].invalid_actions = 2;
+        let (winners, _, _) = play(&mut drawers, InvalidActionPolicy::Retry(2)).unwrap();
+        assert_eq!(winners, vec![0, 1]);
+
+        let mut drawers: [Drawer; 2] = Default::default();
+        drawers[1].invalid_actions = 1;
+        let (winners, _, _) = play(&mut drawers, InvalidActionPolicy::FirstLegal).unwrap();
+        assert_eq!(winners, vec![0, 1]);
+
+        let mut drawers: [Drawer; 2] = Default::default();
+        drawers[1].invalid_actions = 1;
+        let (winners, forfeited, _) = play(&mut drawers, InvalidActionPolicy::Forfeit).unwrap();
+        assert_eq!(winners, vec![0]);
+        assert_eq!(forfeited, Some(1));
+        assert_eq!(drawers[0].winners.as_deref(), Some(&[0][..]));
+    }
+
+    #[test]
+    fn baseline_bots() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let mut rng = StdRng::seed_from_u64(5);
+        for num_players in 2..=4 {
+            for seed in 0..5 {
+                let agents: Vec<Box<dyn Agent>> = (0..num_players)
+                    .map(|id| -> Box<dyn Agent> {
+                        match (id + seed as usize) % 3 {
+                            0 => Box::new(RandomAgent::new(seed)),
+                            1 => Box::new(GreedyAgent::new(Evaluation::default(), seed)),
+                            _ => Box::new(HeuristicAgent::new()),
+                        }
+                    })
+                    .collect();
+                let game_seed = rng.gen();
+                let config =
+                    GameConfig::standard(cards.clone(), game_seed).default_players(num_players);
+                let result = Match::new(agents).play(config).unwrap();
+                assert!(!result.winners.is_empty());
+                assert_eq!(result.logger.history()[0].seed, Some(game_seed));
+            }
+        }
+    }
+}
@@ -0,0 +1,24 @@
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::{action::Action, agent::Agent, observation::Observation};
+
+/// Chooses uniformly at random among the valid actions.
+pub struct RandomAgent {
+    rng: StdRng,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> RandomAgent {
+        RandomAgent {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn act(&mut self, obs: &Observation) -> Action {
+        obs.legal_actions()
+            .choose(&mut self.rng)
+            .expect("The acting player has some valid action.")
+    }
+}
@@ -1,5 +1,7 @@
 use std::cell::RefCell;
 
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use strum::IntoEnumIterator;
 
 use crate::{
@@ -12,9 +14,14 @@ use crate::{
     structure::{Place, PlayerPlace},
 };
 
+#[derive(Clone)]
 pub struct WinByAchievementChecker;
 
 impl<'c> InternalObserver<'c> for WinByAchievementChecker {
+    fn fork(&self) -> Box<dyn InternalObserver<'c> + 'c> {
+        Box::new(self.clone())
+    }
+
     fn update(&mut self, event: &Item<'c>, game: &Players<'c>) -> InnResult<()> {
         let win_num = match game.players().len() {
             2 => 6,
@@ -46,16 +53,7 @@ impl<'c> AchievementManager<'c> {
             available_achievements: RefCell::new(
                 special_achievements
                     .into_iter()
-                    .map(|sa| {
-                        let condition: Box<dyn Achievement> = match &sa {
-                            SpecialAchievement::Monument => Box::new(Monument::new(first_player)),
-                            SpecialAchievement::Empire => Box::new(Empire),
-                            SpecialAchievement::World => Box::new(World),
-                            SpecialAchievement::Wonder => Box::new(Wonder),
-                            SpecialAchievement::Universe => Box::new(Universe),
-                        };
-                        (sa, RefCell::new(condition))
-                    })
+                    .map(|sa| (sa, RefCell::new(condition(sa, first_player))))
                     .collect(),
             ),
             acting_player: first_player,
@@ -63,7 +61,63 @@ impl<'c> AchievementManager<'c> {
     }
 }
 
+fn condition<'c>(sa: SpecialAchievement, first_player: PlayerId) -> Box<dyn Achievement<'c>> {
+    match sa {
+        SpecialAchievement::Monument => Box::new(Monument::new(first_player)),
+        SpecialAchievement::Empire => Box::new(Empire),
+        SpecialAchievement::World => Box::new(World),
+        SpecialAchievement::Wonder => Box::new(Wonder),
+        SpecialAchievement::Universe => Box::new(Universe),
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManagerState {
+    acting_player: PlayerId,
+    available_achievements: Vec<(SpecialAchievement, Value)>,
+}
+
 impl<'c> InternalObserver<'c> for AchievementManager<'c> {
+    fn fork(&self) -> Box<dyn InternalObserver<'c> + 'c> {
+        Box::new(AchievementManager {
+            available_achievements: RefCell::new(
+                self.available_achievements
+                    .borrow()
+                    .iter()
+                    .map(|(sa, condition)| (*sa, RefCell::new(condition.borrow().box_clone())))
+                    .collect(),
+            ),
+            acting_player: self.acting_player,
+        })
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(ManagerState {
+            acting_player: self.acting_player,
+            available_achievements: self
+                .available_achievements
+                .borrow()
+                .iter()
+                .map(|(sa, condition)| (*sa, condition.borrow().save()))
+                .collect(),
+        })
+        .expect("The state of achievements should be serializable.")
+    }
+
+    fn load(&mut self, state: &Value) -> InnResult<()> {
+        let state: ManagerState =
+            serde_json::from_value(state.clone()).map_err(|_| InnovationError::InvalidSave)?;
+        let mut available = Vec::new();
+        for (sa, saved) in state.available_achievements {
+            let mut condition = condition(sa, state.acting_player);
+            condition.load(&saved)?;
+            available.push((sa, RefCell::new(condition)));
+        }
+        self.available_achievements = RefCell::new(available);
+        self.acting_player = state.acting_player;
+        Ok(())
+    }
+
     fn update(&mut self, event: &Item<'c>, game: &Players<'c>) -> InnResult<()> {
         if let Item::ChangeTurn(_prev, next) = event {
             self.acting_player = *next;
@@ -97,6 +151,14 @@ impl<'c> InternalObserver<'c> for AchievementManager<'c> {
 trait Achievement<'c> {
     fn update_interested(&mut self, event: &Item<'c>) -> Vec<PlayerId>;
     fn further_check(&self, game: &Players<'c>, player: &Player<'c>) -> bool;
+    fn box_clone(&self) -> Box<dyn Achievement<'c>>;
+    /// The progress towards the achievement to save with the game, if any.
+    fn save(&self) -> Value {
+        Value::Null
+    }
+    fn load(&mut self, _state: &Value) -> InnResult<()> {
+        Ok(())
+    }
 }
 
 fn check_board(event: &Item) -> Vec<PlayerId> {
@@ -115,6 +177,7 @@ fn check_board(event: &Item) -> Vec<PlayerId> {
         Vec::new()
     }
 }
+#[derive(Clone, Serialize, Deserialize)]
 struct Monument {
     scored: usize,
     tucked: usize,
@@ -163,8 +226,22 @@ impl<'c> Achievement<'c> for Monument {
     fn further_check(&self, _game: &Players<'c>, _player: &Player<'c>) -> bool {
         true
     }
+
+    fn box_clone(&self) -> Box<dyn Achievement<'c>> {
+        Box::new(self.clone())
+    }
+
+    fn save(&self) -> Value {
+        serde_json::to_value(self).expect("Monument should be serializable.")
+    }
+
+    fn load(&mut self, state: &Value) -> InnResult<()> {
+        *self = serde_json::from_value(state.clone()).map_err(|_| InnovationError::InvalidSave)?;
+        Ok(())
+    }
 }
 
+#[derive(Clone)]
 struct Empire;
 
 impl<'c> Achievement<'c> for Empire {
@@ -176,8 +253,13 @@ impl<'c> Achievement<'c> for Empire {
         let icons = player.board().icon_count();
         Icon::iter().all(|icon| icons[&icon] >= 3)
     }
+
+    fn box_clone(&self) -> Box<dyn Achievement<'c>> {
+        Box::new(self.clone())
+    }
 }
 
+#[derive(Clone)]
 struct World;
 
 impl<'c> Achievement<'c> for World {
@@ -188,8 +270,13 @@ impl<'c> Achievement<'c> for World {
     fn further_check(&self, _game: &Players<'c>, player: &Player<'c>) -> bool {
         player.board().icon_count()[&Icon::Clock] >= 12
     }
+
+    fn box_clone(&self) -> Box<dyn Achievement<'c>> {
+        Box::new(self.clone())
+    }
 }
 
+#[derive(Clone)]
 struct Wonder;
 
 impl<'c> Achievement<'c> for Wonder {
@@ -208,8 +295,13 @@ impl<'c> Achievement<'c> for Wonder {
             .map(|color| board.get_stack(color))
             .all(|stack| stack.is_splayed(Splay::Right) || stack.is_splayed(Splay::Up))
     }
+
+    fn box_clone(&self) -> Box<dyn Achievement<'c>> {
+        Box::new(self.clone())
+    }
 }
 
+#[derive(Clone)]
 struct Universe;
 
 impl<'c> Achievement<'c> for Universe {
@@ -221,4 +313,8 @@ impl<'c> Achievement<'c> for Universe {
         let top_cards = player.board().top_cards();
         top_cards.len() == 5 && top_cards.into_iter().all(|card| card.age() >= 8)
     }
+
+    fn box_clone(&self) -> Box<dyn Achievement<'c>> {
+        Box::new(self.clone())
+    }
 }
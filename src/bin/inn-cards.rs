@@ -0,0 +1,62 @@
+//! Writing and checking card catalogues of `inn_rust::card::catalogue`.
+//!
+//! ```text
+//! inn-cards export FILE
+//! inn-cards check FILE
+//! ```
+//!
+//! `export` writes the catalogue of the default cards, and `check` reads
+//! one and makes the cards of it with the default dogmas, telling what's
+//! wrong if they can't be made. A `FILE` ending in `.csv` is CSV, any other
+//! JSON.
+
+use std::{fs, process};
+
+use inn_rust::card::{catalogue::Catalogue, default_cards};
+
+const USAGE: &str = "usage: inn-cards export FILE
+       inn-cards check FILE
+where FILE is CSV if it ends in .csv and JSON otherwise";
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (command, path) = match args.as_slice() {
+        [command, path] => (command.as_str(), path),
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+    let csv = path.ends_with(".csv");
+    match command {
+        "export" => {
+            let cards = default_cards::default_cards();
+            let catalogue = Catalogue::from_cards(&cards.iter().collect::<Vec<_>>());
+            let text = if csv {
+                catalogue.to_csv()
+            } else {
+                catalogue.to_json()
+            };
+            fs::write(path, text).unwrap_or_else(|e| fail(e));
+        }
+        "check" => {
+            let text = fs::read_to_string(path).unwrap_or_else(|e| fail(e));
+            let catalogue = if csv {
+                Catalogue::from_csv(&text).unwrap_or_else(|e| fail(e))
+            } else {
+                Catalogue::from_json(&text).unwrap_or_else(|e| fail(e))
+            };
+            let cards = catalogue.build_default().unwrap_or_else(|e| fail(e));
+            println!("{} cards", cards.len());
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! The JSON line protocol of `inn_rust::protocol` on standard input and
+//! output.
+//!
+//! ```text
+//! inn-engine
+//! inn-engine match [--seed N] SEAT SEAT [SEAT [SEAT]]
+//! ```
+//!
+//! Without arguments, it answers engine commands. With `match`, it plays a
+//! match where each seat is `external`, `random`, `greedy` or `heuristic`;
+//! the external seat, of which there is at most one, is played through
+//! standard input and output. The winners are written to standard error.
+
+use std::{env, io, process};
+
+use inn_rust::{
+    agent::{Agent, Evaluation, GreedyAgent, HeuristicAgent, Match, RandomAgent},
+    card::default_cards,
+    game::GameConfig,
+    protocol::{Engine, ExternalAgent},
+};
+use rand::{thread_rng, Rng};
+
+const USAGE: &str = "usage: inn-engine
+       inn-engine match [--seed N] SEAT SEAT [SEAT [SEAT]]
+where each SEAT is external, random, greedy or heuristic, with one external at most";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2)
+}
+
+fn play_match(args: impl Iterator<Item = String>) {
+    let mut seed = None;
+    let mut seats = Vec::new();
+    let mut args = args;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => match args.next().map(|s| s.parse::<u64>()) {
+                Some(Ok(n)) => seed = Some(n),
+                _ => usage(),
+            },
+            "external" | "random" | "greedy" | "heuristic" => seats.push(arg),
+            _ => usage(),
+        }
+    }
+    if !(2..=4).contains(&seats.len()) || seats.iter().filter(|s| *s == "external").count() > 1 {
+        usage();
+    }
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+
+    let cards = default_cards::default_cards();
+    let cards: Vec<_> = cards.iter().collect();
+    let agents: Vec<Box<dyn Agent>> = seats
+        .iter()
+        .enumerate()
+        .map(|(id, seat)| -> Box<dyn Agent> {
+            let agent_seed = seed.wrapping_add(id as u64 + 1);
+            match seat.as_str() {
+                "external" => Box::new(ExternalAgent::new(io::stdin().lock(), io::stdout())),
+                "random" => Box::new(RandomAgent::new(agent_seed)),
+                "greedy" => Box::new(GreedyAgent::new(Evaluation::default(), agent_seed)),
+                _ => Box::new(HeuristicAgent::new()),
+            }
+        })
+        .collect();
+    let config = GameConfig::standard(cards.clone(), seed).default_players(seats.len());
+
+    match Match::new(agents).play(config) {
+        Ok(result) => eprintln!("seed {}, won by {:?}", seed, result.winners),
+        Err(e) => {
+            eprintln!("The match stopped: {:?}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        None => {
+            let cards = default_cards::default_cards();
+            let mut engine = Engine::new(cards.iter().collect());
+            if let Err(e) = engine.run(io::stdin().lock(), io::stdout().lock()) {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+        Some("match") => play_match(args),
+        Some(_) => usage(),
+    }
+}
@@ -0,0 +1,56 @@
+//! Exporting a game of a log file as an HTML replay.
+//!
+//! ```text
+//! inn-replay LOG OUT [GAME]
+//! ```
+//!
+//! `LOG` is a `LogFile` as JSON, written for the default cards; `GAME` is
+//! the number of the game in it, the first one by default. The page written
+//! to `OUT` needs nothing else to be viewed.
+
+use std::{env, fs, process};
+
+use inn_rust::{
+    card::default_cards, game::GameConfig, logger::Logger, record::LogFile, viewer::export_html,
+};
+
+const USAGE: &str = "usage: inn-replay LOG OUT [GAME]";
+
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !(2..=3).contains(&args.len()) {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+    let index = match args.get(2).map(|s| s.parse::<usize>()) {
+        None => 0,
+        Some(Ok(index)) => index,
+        Some(Err(_)) => {
+            eprintln!("{}", USAGE);
+            process::exit(2);
+        }
+    };
+
+    let json = fs::read_to_string(&args[0]).unwrap_or_else(|e| fail(e));
+    let file = LogFile::from_json(&json).unwrap_or_else(|e| fail(e));
+    let cards = default_cards::default_cards();
+    let cards: Vec<_> = cards.iter().collect();
+    let logger = Logger::load(&cards, &file)
+        .unwrap_or_else(|e| fail(format!("The log doesn't fit the cards: {:?}", e)));
+    let record = logger.history().get(index).unwrap_or_else(|| {
+        fail(format!(
+            "There is no game {}, the log has {}",
+            index,
+            logger.history().len()
+        ))
+    });
+    let config = GameConfig::new(cards.clone()).default_players(record.num_players);
+    let html = export_html(record, config)
+        .unwrap_or_else(|e| fail(format!("The game can't be replayed: {:?}", e)));
+    fs::write(&args[1], html).unwrap_or_else(|e| fail(e));
+}
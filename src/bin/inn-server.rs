@@ -0,0 +1,25 @@
+//! Serves games over TCP, as described in `inn_rust::server`.
+//!
+//! ```text
+//! inn-server [ADDRESS]
+//! ```
+//!
+//! The address defaults to `0.0.0.0:7878`.
+
+use std::{env, process};
+
+use inn_rust::server::Server;
+
+fn main() {
+    let addr = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "0.0.0.0:7878".to_owned());
+    let result = Server::bind(&addr).and_then(|server| {
+        eprintln!("serving on {}", server.local_addr()?);
+        server.run()
+    });
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
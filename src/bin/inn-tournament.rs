@@ -0,0 +1,104 @@
+//! Tournaments between the built-in agents.
+//!
+//! ```text
+//! inn-tournament [--seed N] [--table N] [--swiss ROUNDS] [--threads N]
+//!                [--json | --games] AGENT AGENT...
+//! ```
+//!
+//! Each agent is `random`, `greedy`, `heuristic` or `ismcts`, and may be
+//! given more than once. The standings are written as CSV, or all results
+//! as JSON with `--json`, or the games as CSV with `--games`.
+
+use std::{env, process, str::FromStr, thread};
+
+use inn_rust::{
+    agent::{Evaluation, GreedyAgent, HeuristicAgent, IsmctsAgent, IsmctsConfig, RandomAgent},
+    tournament::{Entrant, Pairing, Tournament},
+};
+
+const USAGE: &str = "usage: inn-tournament [--seed N] [--table N] [--swiss ROUNDS] [--threads N]
+                      [--json | --games] AGENT AGENT...
+where each AGENT is random, greedy, heuristic or ismcts";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2)
+}
+
+fn number<T: FromStr>(arg: Option<String>) -> T {
+    arg.and_then(|s| s.parse().ok()).unwrap_or_else(|| usage())
+}
+
+fn entrant(name: &str, count: usize) -> Entrant {
+    let label = if count > 1 {
+        format!("{} {}", name, count)
+    } else {
+        name.to_owned()
+    };
+    match name {
+        "random" => Entrant::new(label, |_, seed| Box::new(RandomAgent::new(seed))),
+        "greedy" => Entrant::new(label, |_, seed| {
+            Box::new(GreedyAgent::new(Evaluation::default(), seed))
+        }),
+        "heuristic" => Entrant::new(label, |_, _| Box::new(HeuristicAgent::new())),
+        "ismcts" => Entrant::new(label, |cards, seed| {
+            let config = IsmctsConfig::default().iterations(200);
+            Box::new(IsmctsAgent::new(cards.to_vec(), config, seed))
+        }),
+        _ => usage(),
+    }
+}
+
+enum Output {
+    Standings,
+    Games,
+    Json,
+}
+
+fn main() {
+    let mut tournament =
+        Tournament::new(Vec::new()).threads(thread::available_parallelism().map_or(1, |n| n.get()));
+    let mut output = Output::Standings;
+    let mut names: Vec<String> = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        tournament = match arg.as_str() {
+            "--seed" => tournament.seed(number(args.next())),
+            "--table" => tournament.table_size(number(args.next())),
+            "--swiss" => tournament.pairing(Pairing::Swiss {
+                rounds: number(args.next()),
+            }),
+            "--threads" => tournament.threads(number(args.next())),
+            "--json" => {
+                output = Output::Json;
+                tournament
+            }
+            "--games" => {
+                output = Output::Games;
+                tournament
+            }
+            _ if arg.starts_with('-') => usage(),
+            _ => {
+                names.push(arg);
+                tournament
+            }
+        }
+    }
+    for (i, name) in names.iter().enumerate() {
+        let count = names[..=i].iter().filter(|n| *n == name).count();
+        tournament = tournament.entrant(entrant(name, count));
+    }
+
+    let result = tournament.run();
+    match result {
+        Ok(result) => match output {
+            Output::Standings => print!("{}", result.standings_csv()),
+            Output::Games => print!("{}", result.games_csv()),
+            Output::Json => println!("{}", result.to_json()),
+        },
+        Err(e) => {
+            eprintln!("The tournament stopped: {:?}", e);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+//! Hot-seat games in a terminal.
+//!
+//! ```text
+//! inn [--seed N] [--cards FILE] SEAT SEAT [SEAT [SEAT]]
+//! ```
+//!
+//! Each seat is `human`, `random`, `greedy` or `heuristic`. Humans type
+//! actions in the notation of `inn_rust::notation`, and the screen is
+//! cleared between the turns of different humans. The cards of `FILE`, a
+//! script of `inn_rust::card::script`, are played with the default ones,
+//! replacing those of the same names.
+
+use std::{
+    cell::Cell,
+    env, fs,
+    io::{self, BufRead, Write},
+    process,
+    rc::Rc,
+};
+
+use inn_rust::{
+    action::{Action, LegalActions},
+    agent::{Agent, Evaluation, GreedyAgent, HeuristicAgent, Match, RandomAgent},
+    card::{default_cards, script::parse_cards, Card},
+    game::{GameConfig, PlayerId},
+    narrator::Narrator,
+    notation::{format_action, parse_action},
+    observation::Observation,
+    protocol::Event,
+    render::render_observation,
+};
+use rand::{thread_rng, Rng};
+
+const USAGE: &str = "usage: inn [--seed N] [--cards FILE] SEAT SEAT [SEAT [SEAT]]
+where each SEAT is human, random, greedy or heuristic";
+
+const HELP: &str = "commands:
+  draw | meld CARD | achieve AGE | dogma CARD
+  pick CARD, CARD, ... | opp PLAYER | yes | no
+  help     lists the valid actions
+  show     shows the game again
+  quit     ends the program";
+
+struct Human<'a> {
+    id: PlayerId,
+    cards: &'a [&'a Card],
+    // the human whose private cards are on the screen
+    screen: Rc<Cell<Option<PlayerId>>>,
+    narrator: Narrator<Vec<u8>>,
+}
+
+impl<'a> Human<'a> {
+    fn new(id: PlayerId, cards: &'a [&'a Card], screen: Rc<Cell<Option<PlayerId>>>) -> Human<'a> {
+        Human {
+            id,
+            cards,
+            screen,
+            narrator: Narrator::new(Vec::new()).viewer(id),
+        }
+    }
+
+    // what happened since this human last looked
+    fn print_news(&mut self) {
+        self.narrator
+            .flush()
+            .expect("Writing to a vector can't fail.");
+        let news = std::mem::take(self.narrator.get_mut());
+        print!("{}", String::from_utf8_lossy(&news));
+    }
+
+    fn take_screen(&mut self) {
+        if self.screen.get() != Some(self.id) {
+            if self.screen.get().is_some() {
+                clear_screen();
+                print!("Pass to Player {} and press Enter. ", self.id + 1);
+                read_line();
+            }
+            clear_screen();
+            self.screen.set(Some(self.id));
+        }
+    }
+}
+
+impl Agent for Human<'_> {
+    fn act(&mut self, obs: &Observation) -> Action {
+        self.take_screen();
+        self.print_news();
+        println!("\n{}", render_observation(obs));
+        loop {
+            print!("Player {}> ", self.id + 1);
+            let line = read_line();
+            let action = match line.trim() {
+                "help" => {
+                    println!("{}\n{}", HELP, legal_actions(&obs.legal_actions()));
+                    continue;
+                }
+                "show" => {
+                    println!("{}", render_observation(obs));
+                    continue;
+                }
+                "quit" => process::exit(0),
+                text => match parse_action(text, self.cards) {
+                    Ok(action) => action,
+                    Err(e) => {
+                        println!("{}", e);
+                        continue;
+                    }
+                },
+            };
+            if obs.legal_actions().contains(&action) {
+                return action;
+            }
+            println!("'{}' is not valid now; type help", format_action(&action));
+        }
+    }
+
+    fn on_event(&mut self, event: &Event) {
+        self.narrator.tell(event);
+    }
+
+    fn on_game_end(&mut self, _winners: &[PlayerId]) {
+        self.take_screen();
+        self.print_news();
+    }
+}
+
+fn clear_screen() {
+    print!("\x1b[2J\x1b[H");
+}
+
+// a line of standard input, ending the program at its end
+fn read_line() -> String {
+    io::stdout()
+        .flush()
+        .expect("Failed to write to standard output.");
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => process::exit(0),
+        Ok(_) => line,
+    }
+}
+
+fn legal_actions(actions: &LegalActions) -> String {
+    match actions {
+        LegalActions::Listed(actions) => format!(
+            "valid actions: {}",
+            actions
+                .iter()
+                .map(format_action)
+                .collect::<Vec<_>>()
+                .join(" | ")
+        ),
+        LegalActions::CardSubsets {
+            min_num,
+            max_num,
+            from,
+        } => format!(
+            "valid actions: pick {} to {} of {}",
+            min_num,
+            max_num,
+            from.join(", ")
+        ),
+    }
+}
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2)
+}
+
+fn main() {
+    let mut seed = None;
+    let mut script = None;
+    let mut seats = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--seed" => match args.next().map(|s| s.parse::<u64>()) {
+                Some(Ok(n)) => seed = Some(n),
+                _ => usage(),
+            },
+            "--cards" => match args.next() {
+                Some(path) => script = Some(path),
+                None => usage(),
+            },
+            "human" | "random" | "greedy" | "heuristic" => seats.push(arg),
+            _ => usage(),
+        }
+    }
+    if !(2..=4).contains(&seats.len()) {
+        usage();
+    }
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+
+    let mut cards = default_cards::default_cards();
+    if let Some(path) = script {
+        let source = fs::read_to_string(&path).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        });
+        let scripted = parse_cards(&source).unwrap_or_else(|e| {
+            eprintln!("{}: {}", path, e);
+            process::exit(1);
+        });
+        cards.retain(|card| scripted.iter().all(|new| new.name() != card.name()));
+        cards.extend(scripted);
+    }
+    let cards: Vec<_> = cards.iter().collect();
+    let screen = Rc::new(Cell::new(None));
+    let agents: Vec<Box<dyn Agent + '_>> = seats
+        .iter()
+        .enumerate()
+        .map(|(id, seat)| -> Box<dyn Agent + '_> {
+            let agent_seed = seed.wrapping_add(id as u64 + 1);
+            match seat.as_str() {
+                "human" => Box::new(Human::new(id, &cards, screen.clone())),
+                "random" => Box::new(RandomAgent::new(agent_seed)),
+                "greedy" => Box::new(GreedyAgent::new(Evaluation::default(), agent_seed)),
+                _ => Box::new(HeuristicAgent::new()),
+            }
+        })
+        .collect();
+    let config = GameConfig::standard(cards.clone(), seed).default_players(seats.len());
+
+    println!("Game seed: {}", seed);
+    let result = match Match::new(agents).play(config) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("The game stopped: {:?}", e);
+            process::exit(1);
+        }
+    };
+    let winners: Vec<String> = result
+        .winners
+        .iter()
+        .map(|id| format!("Player {}", id + 1))
+        .collect();
+    println!("\nGame over; won by {}", winners.join(" and "));
+}
@@ -57,10 +57,25 @@ impl<'a> Stack<'a> {
         self.splay = direction;
     }
 
+    /// Sets the splay direction without checking the stack, e.g. when
+    /// building a board.
+    pub fn set_splay(&mut self, direction: Splay) {
+        self.splay = direction;
+    }
+
     pub fn is_splayed(&self, direction: Splay) -> bool {
         self.splay == direction
     }
 
+    pub fn splay_direction(&self) -> Splay {
+        self.splay
+    }
+
+    /// Iterates the cards from top to bottom.
+    pub fn iter(&self) -> impl Iterator<Item = &'a Card> + '_ {
+        self.cards.iter().copied()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.cards.is_empty()
     }
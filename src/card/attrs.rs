@@ -1,9 +1,9 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
 pub type Age = u8;
 
-#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Serialize)]
+#[derive(Copy, Clone, Debug, EnumIter, PartialEq, Serialize, Deserialize)]
 pub enum Color {
     Blue,
     Red,
@@ -24,7 +24,7 @@ impl Color {
     }
 }
 
-#[derive(PartialEq, Eq, Hash, Copy, Clone, EnumIter, Serialize)]
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone, EnumIter, Serialize, Deserialize)]
 pub enum Icon {
     Castle,
     Factory,
@@ -36,7 +36,7 @@ pub enum Icon {
     Empty,
 }
 
-#[derive(Debug, PartialEq, Default, Clone, Copy, EnumIter, Serialize)]
+#[derive(Debug, PartialEq, Default, Clone, Copy, EnumIter, Serialize, Deserialize)]
 pub enum Splay {
     #[default]
     NoSplay,
@@ -3,7 +3,7 @@ use crate::{
     observation::SingleAchievementView,
 };
 use counter::Counter;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, hash::Hash};
 use strum_macros::EnumIter;
 
@@ -54,6 +54,16 @@ impl Card {
         }
     }
 
+    /// Sets the main icon, which is the most common icon by default.
+    pub fn with_main_icon(mut self, main_icon: Icon) -> Card {
+        self.main_icon = main_icon;
+        self
+    }
+
+    pub(crate) fn into_dogmas(self) -> Vec<Dogma> {
+        self.dogmas
+    }
+
     pub fn age(&self) -> Age {
         self.age
     }
@@ -107,7 +117,7 @@ impl Debug for Card {
     }
 }
 
-#[derive(PartialEq, Debug, Serialize, EnumIter, Clone, Copy)]
+#[derive(PartialEq, Debug, Serialize, Deserialize, EnumIter, Clone, Copy)]
 pub enum SpecialAchievement {
     Monument,
     Empire,
@@ -0,0 +1,369 @@
+//! The attributes of cards as data, for editing them outside the code.
+//!
+//! A `Catalogue` has an entry for each card with everything but what its
+//! dogmas do: the name, age, color, icons, main icon, the kinds of the
+//! dogmas and their text. It's written and read as JSON or CSV, the latter
+//! with a header line
+//!
+//! ```text
+//! name,age,color,icon_1,icon_2,icon_3,icon_4,main_icon,dogma_count,dogmas,doc
+//! Pottery,1,Blue,Empty,Leaf,Leaf,Leaf,Leaf,2,share share,"You may ..."
+//! ```
+//!
+//! where `dogmas` are the kinds separated by spaces, and an empty icon may
+//! be written as `Empty` or left blank.
+//!
+//! `Catalogue::build` makes cards from the entries, taking the dogmas from
+//! the cards of the same names, such as `default_cards::default_cards()`.
+
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    card::{default_cards, Age, Card, Color, Dogma, Icon},
+    utils::{csv_line, csv_records},
+};
+
+const CSV_HEADER: [&str; 11] = [
+    "name",
+    "age",
+    "color",
+    "icon_1",
+    "icon_2",
+    "icon_3",
+    "icon_4",
+    "main_icon",
+    "dogma_count",
+    "dogmas",
+    "doc",
+];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DogmaKind {
+    Share,
+    Demand,
+}
+
+impl From<&Dogma> for DogmaKind {
+    fn from(dogma: &Dogma) -> DogmaKind {
+        match dogma {
+            Dogma::Share(_) => DogmaKind::Share,
+            Dogma::Demand(_) => DogmaKind::Demand,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CardEntry {
+    pub name: String,
+    pub age: Age,
+    pub color: Color,
+    pub icons: [Icon; 4],
+    pub main_icon: Icon,
+    /// The number of dogmas, which should be the length of `dogmas`.
+    pub dogma_count: usize,
+    pub dogmas: Vec<DogmaKind>,
+    pub doc: String,
+}
+
+impl CardEntry {
+    pub fn from_card(card: &Card) -> CardEntry {
+        CardEntry {
+            name: card.name().to_owned(),
+            age: card.age(),
+            color: card.color(),
+            icons: card.icons(),
+            main_icon: card.main_icon(),
+            dogma_count: card.dogmas().len(),
+            dogmas: card.dogmas().iter().map(DogmaKind::from).collect(),
+            doc: card.doc().clone(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CatalogueError {
+    /// A quote on this line of a CSV file is never closed.
+    UnclosedQuote(usize),
+    /// A record of a CSV file, the header being record 1, is malformed.
+    Csv { record: usize, message: String },
+    /// The entry of a card doesn't make a card.
+    Card { name: String, message: String },
+}
+
+impl fmt::Display for CatalogueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogueError::UnclosedQuote(line) => {
+                write!(f, "line {}: the quote is never closed", line)
+            }
+            CatalogueError::Csv { record, message } => write!(f, "record {}: {}", record, message),
+            CatalogueError::Card { name, message } => write!(f, "{}: {}", name, message),
+        }
+    }
+}
+
+impl std::error::Error for CatalogueError {}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Catalogue {
+    pub cards: Vec<CardEntry>,
+}
+
+// an enum value from its name, as serde reads it
+fn parse_name<T: DeserializeOwned>(field: &str) -> Option<T> {
+    serde_json::from_value(Value::String(field.trim().to_owned())).ok()
+}
+
+impl Catalogue {
+    pub fn from_cards(cards: &[&Card]) -> Catalogue {
+        Catalogue {
+            cards: cards
+                .iter()
+                .map(|card| CardEntry::from_card(card))
+                .collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("A catalogue should be serializable.")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<Catalogue> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = csv_line(&CSV_HEADER) + "\n";
+        for entry in &self.cards {
+            let mut fields = vec![
+                entry.name.clone(),
+                entry.age.to_string(),
+                format!("{:?}", entry.color),
+            ];
+            fields.extend(entry.icons.iter().map(|icon| format!("{:?}", icon)));
+            fields.push(format!("{:?}", entry.main_icon));
+            fields.push(entry.dogma_count.to_string());
+            fields.push(
+                entry
+                    .dogmas
+                    .iter()
+                    .map(|kind| match kind {
+                        DogmaKind::Share => "share",
+                        DogmaKind::Demand => "demand",
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            );
+            fields.push(entry.doc.clone());
+            csv += &csv_line(&fields);
+            csv += "\n";
+        }
+        csv
+    }
+
+    /// Reads a catalogue written by `to_csv`, possibly edited.
+    ///
+    /// The columns may be in any order, and columns not in the header of
+    /// `to_csv` are ignored.
+    pub fn from_csv(csv: &str) -> Result<Catalogue, CatalogueError> {
+        let records = csv_records(csv).map_err(CatalogueError::UnclosedQuote)?;
+        let mut records = records
+            .into_iter()
+            .enumerate()
+            .map(|(i, record)| (i + 1, record))
+            .filter(|(_, record)| record.iter().any(|field| !field.trim().is_empty()));
+        let header = match records.next() {
+            Some((_, header)) => header,
+            None => return Ok(Catalogue::default()),
+        };
+        let columns = CSV_HEADER
+            .iter()
+            .map(|name| {
+                header
+                    .iter()
+                    .position(|column| column.trim() == *name)
+                    .ok_or_else(|| CatalogueError::Csv {
+                        record: 1,
+                        message: format!("there's no column {}", name),
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut cards = Vec::new();
+        for (number, record) in records {
+            let error = |message: String| CatalogueError::Csv {
+                record: number,
+                message,
+            };
+            let field = |column: usize| {
+                record
+                    .get(columns[column])
+                    .map(String::as_str)
+                    .unwrap_or("")
+            };
+            let icon = |column: usize| match field(column).trim() {
+                "" => Ok(Icon::Empty),
+                name => parse_name(name).ok_or_else(|| error(format!("{} is not an icon", name))),
+            };
+            let dogmas = field(9)
+                .split_whitespace()
+                .map(|kind| {
+                    parse_name(kind).ok_or_else(|| error(format!("{} is not a dogma kind", kind)))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            cards.push(CardEntry {
+                name: field(0).trim().to_owned(),
+                age: field(1)
+                    .trim()
+                    .parse()
+                    .map_err(|_| error(format!("{} is not an age", field(1))))?,
+                color: parse_name(field(2))
+                    .ok_or_else(|| error(format!("{} is not a color", field(2))))?,
+                icons: [icon(3)?, icon(4)?, icon(5)?, icon(6)?],
+                main_icon: icon(7)?,
+                dogma_count: field(8)
+                    .trim()
+                    .parse()
+                    .map_err(|_| error(format!("{} is not a dogma count", field(8))))?,
+                dogmas,
+                doc: field(10).to_owned(),
+            });
+        }
+        Ok(Catalogue { cards })
+    }
+
+    /// Makes the cards of the entries, in order, with the dogmas of the
+    /// cards in `behaviours` of the same names.
+    ///
+    /// The dogmas of an entry must be of the kinds of the dogmas it gets.
+    /// Cards of `behaviours` without entries are left out.
+    pub fn build(&self, behaviours: Vec<Card>) -> Result<Vec<Card>, CatalogueError> {
+        let mut behaviours: Vec<Option<Card>> = behaviours.into_iter().map(Some).collect();
+        let mut cards = Vec::with_capacity(self.cards.len());
+        for entry in &self.cards {
+            let error = |message: &str| CatalogueError::Card {
+                name: entry.name.clone(),
+                message: message.to_owned(),
+            };
+            if !(1..=10).contains(&entry.age) {
+                return Err(error("the age is not from 1 to 10"));
+            }
+            if entry.main_icon == Icon::Empty {
+                return Err(error("the main icon is empty"));
+            }
+            if entry.dogma_count != entry.dogmas.len() {
+                return Err(error("the dogma count is not the number of dogmas"));
+            }
+            let behaviour = behaviours
+                .iter_mut()
+                .find(|card| card.as_ref().is_some_and(|c| c.name() == entry.name))
+                .and_then(Option::take);
+            let behaviour = match behaviour {
+                Some(behaviour) => behaviour,
+                None if cards.iter().any(|card: &Card| card.name() == entry.name) => {
+                    return Err(error("the card is listed twice"))
+                }
+                None => return Err(error("there are no dogmas for the card")),
+            };
+            if behaviour
+                .dogmas()
+                .iter()
+                .map(DogmaKind::from)
+                .ne(entry.dogmas.iter().copied())
+            {
+                return Err(error("the dogmas are not of the kinds of the card's"));
+            }
+            cards.push(
+                Card::new(
+                    entry.name.clone(),
+                    entry.age,
+                    entry.color,
+                    entry.icons,
+                    behaviour.into_dogmas(),
+                    entry.doc.clone(),
+                )
+                .with_main_icon(entry.main_icon),
+            );
+        }
+        Ok(cards)
+    }
+
+    /// `build` with the dogmas of the default cards.
+    pub fn build_default(&self) -> Result<Vec<Card>, CatalogueError> {
+        self.build(default_cards::default_cards())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let catalogue = Catalogue::from_cards(&cards);
+        assert_eq!(catalogue.cards.len(), cards.len());
+        assert_eq!(catalogue.cards[0].name, "Pottery");
+        assert_eq!(catalogue.cards[0].dogmas, vec![DogmaKind::Share; 2]);
+
+        let csv = catalogue.to_csv();
+        assert!(csv.starts_with("name,age,color,icon_1,"));
+        assert_eq!(Catalogue::from_csv(&csv).unwrap(), catalogue);
+        assert_eq!(
+            Catalogue::from_json(&catalogue.to_json()).unwrap(),
+            catalogue
+        );
+
+        let built = catalogue.build_default().unwrap();
+        for (card, original) in built.iter().zip(&cards) {
+            assert_eq!(CardEntry::from_card(card), CardEntry::from_card(original));
+        }
+
+        // edited in a spreadsheet
+        let edited = csv
+            .replacen("Pottery,1,Blue,Empty,", "Pottery,2,Blue,,", 1)
+            .replace('\n', "\r\n");
+        let edited = Catalogue::from_csv(&edited)
+            .unwrap()
+            .build_default()
+            .unwrap();
+        assert_eq!(edited[0].age(), 2);
+        assert_eq!(edited[0].icons()[0], Icon::Empty);
+        assert_eq!(edited[0].dogmas().len(), 2);
+    }
+
+    #[test]
+    fn invalid() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let csv = Catalogue::from_cards(&cards).to_csv();
+        let pottery = |from: &str, to: &str| {
+            Catalogue::from_csv(&csv.replacen(from, to, 1))
+                .and_then(|catalogue| catalogue.build_default())
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(pottery(",Blue,", ",Pink,"), "record 2: Pink is not a color");
+        assert_eq!(
+            pottery("Leaf,2,share share", "Leaf,2,share demand"),
+            "Pottery: the dogmas are not of the kinds of the card's"
+        );
+        assert_eq!(
+            pottery("Leaf,2,share share", "Leaf,1,share share"),
+            "Pottery: the dogma count is not the number of dogmas"
+        );
+        assert_eq!(
+            pottery("Pottery,", "Pot,"),
+            "Pot: there are no dogmas for the card"
+        );
+        assert_eq!(
+            pottery("Tools,", "Pottery,"),
+            "Pottery: the card is listed twice"
+        );
+    }
+}
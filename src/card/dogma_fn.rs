@@ -1,11 +1,10 @@
-use std::{cell::RefCell, cmp::min, convert::TryInto, rc::Rc};
+use std::{cmp::min, convert::TryInto};
 
-use generator::{done, Gn, Scope};
 use strum::IntoEnumIterator;
 
 use crate::{
+    action::{NoRefChoice, RefChoice},
     card::{
-        flow::{FlowState, GenResume, GenYield},
         Card,
         Color::{self, *},
         Dogma,
@@ -13,42 +12,103 @@ use crate::{
         SpecialAchievement,
         Splay::{self, *},
     },
-    error::InnResult,
-    game::{Players, RcCell},
+    error::{InnResult, InnovationError},
+    game::Players,
     player::Player,
-    state::{Choose, ExecutionState},
+    state::{ActionCheckResult, Choose, ExecutionState},
     structure::{Board, Hand, Score},
 };
 
-// wrapper of Scope
-// which lifetime in Scope???
-pub struct Context<'a, 'c, 'g> {
-    s: Scope<'a, GenResume<'c, 'g>, GenYield<'c, 'g>>,
+/// A card in execution.
+struct Frame<'c> {
+    card: &'c Card,
+    demand_complied: bool,
 }
 
-impl<'a, 'c, 'g> Context<'a, 'c, 'g> {
-    pub fn new(s: Scope<'a, GenResume<'c, 'g>, GenYield<'c, 'g>>) -> Context<'a, 'c, 'g> {
-        Context { s }
+/// The choices of an execution.
+///
+/// An execution is a plain function, which is run from the beginning every
+/// time a choice is made. Choices are answered by the choices made so far,
+/// in order, and choices with at most one valid action are answered
+/// automatically. A choice not made yet is kept as the pending one, and the
+/// execution is stopped with `InnovationError::Suspend`, which should be
+/// propagated with `?`.
+pub struct Context<'c, 'g> {
+    game: &'g Players<'c>,
+    choices: Vec<NoRefChoice>,
+    cursor: usize,
+    frames: Vec<Frame<'c>>,
+    pending: Option<ExecutionState<'c, 'g>>,
+}
+
+impl<'c, 'g> Context<'c, 'g> {
+    pub fn new(game: &'g Players<'c>, choices: Vec<NoRefChoice>) -> Context<'c, 'g> {
+        Context {
+            game,
+            choices,
+            cursor: 0,
+            frames: Vec::new(),
+            pending: None,
+        }
     }
 
-    pub fn into_raw(self) -> Scope<'a, GenResume<'c, 'g>, GenYield<'c, 'g>> {
-        self.s
+    /// The choice the execution is suspended at.
+    pub fn into_pending(self) -> Option<ExecutionState<'c, 'g>> {
+        self.pending
     }
 
-    pub fn yield_(&mut self, player: &'g Player<'c>, choose: Choose<'c>) -> GenResume<'c, 'g> {
-        self.s
-            .yield_(Ok(ExecutionState::new(player, choose)))
-            .expect("Generator got None")
+    /// Runs `f` as the execution of `card`.
+    pub fn executing<T>(&mut self, card: &'c Card, f: impl FnOnce(&mut Self) -> T) -> T {
+        self.frames.push(Frame {
+            card,
+            demand_complied: false,
+        });
+        let result = f(self);
+        self.frames.pop();
+        result
     }
 
-    // TODO: implementation? use is_done or resume or send or ...?
-    /// Manual yield from a (local) generator.
-    pub fn yield_from(&mut self, mut gen: FlowState<'c, 'g>) {
-        let mut res = gen.resume();
-        while let Some(request) = res {
-            let choice = self.s.yield_(request).expect("Generator got None");
-            gen.set_para(choice);
-            res = gen.resume();
+    /// Records that an opponent complied with a demand of the card in execution.
+    pub fn mark_demand_complied(&mut self) {
+        if let Some(frame) = self.frames.last_mut() {
+            frame.demand_complied = true;
+        }
+    }
+
+    /// Whether any opponent has complied with a demand of the card in execution.
+    pub fn demand_complied(&self) -> bool {
+        self.frames
+            .last()
+            .is_some_and(|frame| frame.demand_complied)
+    }
+
+    pub fn choose(
+        &mut self,
+        player: &'g Player<'c>,
+        choose: Choose<'c>,
+    ) -> InnResult<RefChoice<'c, 'g>> {
+        let mut state = ExecutionState::new(player, choose);
+        if let Some(frame) = self.frames.last() {
+            state = state.or(frame.card);
+        }
+        match state.check_valid_actions(self.game) {
+            ActionCheckResult::Zero => return Ok(RefChoice::NoValidAction),
+            ActionCheckResult::One(choice) => return Ok(choice),
+            ActionCheckResult::Many => {}
+        }
+        match self.choices.get(self.cursor) {
+            // recorded choices may come from a save, so check them first
+            Some(choice) if !state.accepts(choice, self.game.num_players()) => {
+                Err(InnovationError::InvalidAction)
+            }
+            Some(choice) => {
+                self.cursor += 1;
+                Ok(choice.clone().to_ref(self.game))
+            }
+            None => {
+                self.pending = Some(state);
+                Err(InnovationError::Suspend)
+            }
         }
     }
 
@@ -56,37 +116,40 @@ impl<'a, 'c, 'g> Context<'a, 'c, 'g> {
         &mut self,
         player: &'g Player<'c>,
         from: Vec<&'c Card>,
-    ) -> Option<&'c Card> {
+    ) -> InnResult<Option<&'c Card>> {
         // MAYFIXED: TODO: cards not enough, etc?
-        self.yield_(
-            player,
-            Choose::Card {
-                min_num: 1,
-                max_num: Some(1),
-                from,
-            },
-        )
-        .card()
+        Ok(self
+            .choose(
+                player,
+                Choose::Card {
+                    min_num: 1,
+                    max_num: Some(1),
+                    from,
+                },
+            )?
+            .card())
     }
 
     pub fn may_choose_one_card(
         &mut self,
         player: &'g Player<'c>,
         from: Vec<&'c Card>,
-    ) -> Option<&'c Card> {
+    ) -> InnResult<Option<&'c Card>> {
         // TODO: use what form? min_num = 0 or yn.then(1 card) or other?
         let cards = self
-            .yield_(
+            .choose(
                 player,
                 Choose::Card {
                     min_num: 0,
                     max_num: Some(1),
                     from,
                 },
-            )
-            .cards()?;
-        debug_assert!(cards.len() <= 1);
-        cards.first().copied()
+            )?
+            .cards();
+        Ok(cards.and_then(|cards| {
+            debug_assert!(cards.len() <= 1);
+            cards.first().copied()
+        }))
     }
 
     pub fn choose_card_at_most(
@@ -94,17 +157,18 @@ impl<'a, 'c, 'g> Context<'a, 'c, 'g> {
         player: &'g Player<'c>,
         from: Vec<&'c Card>,
         max_num: Option<usize>,
-    ) -> Option<Vec<&'c Card>> {
+    ) -> InnResult<Option<Vec<&'c Card>>> {
         // choose at least one if possible
-        self.yield_(
-            player,
-            Choose::Card {
-                min_num: 1,
-                max_num,
-                from,
-            },
-        )
-        .cards()
+        Ok(self
+            .choose(
+                player,
+                Choose::Card {
+                    min_num: 1,
+                    max_num,
+                    from,
+                },
+            )?
+            .cards())
     }
 
     pub fn choose_any_cards_up_to(
@@ -112,18 +176,19 @@ impl<'a, 'c, 'g> Context<'a, 'c, 'g> {
         player: &'g Player<'c>,
         from: Vec<&'c Card>,
         max_num: Option<usize>,
-    ) -> Vec<&'c Card> {
+    ) -> InnResult<Vec<&'c Card>> {
         // can choose 0 to max_num cards
-        self.yield_(
-            player,
-            Choose::Card {
-                min_num: 0,
-                max_num,
-                from,
-            },
-        )
-        .cards()
-        .expect("The actor can choose 0 cards, so there should always be valid action.")
+        Ok(self
+            .choose(
+                player,
+                Choose::Card {
+                    min_num: 0,
+                    max_num,
+                    from,
+                },
+            )?
+            .cards()
+            .expect("The actor can choose 0 cards, so there should always be valid action."))
     }
 
     pub fn choose_cards_exact(
@@ -131,29 +196,35 @@ impl<'a, 'c, 'g> Context<'a, 'c, 'g> {
         player: &'g Player<'c>,
         from: Vec<&'c Card>,
         num: usize,
-    ) -> Option<Vec<&'c Card>> {
-        self.yield_(
-            player,
-            Choose::Card {
-                min_num: num,
-                max_num: Some(num),
-                from,
-            },
-        )
-        .cards()
+    ) -> InnResult<Option<Vec<&'c Card>>> {
+        Ok(self
+            .choose(
+                player,
+                Choose::Card {
+                    min_num: num,
+                    max_num: Some(num),
+                    from,
+                },
+            )?
+            .cards())
+    }
+
+    pub fn choose_opponent(&mut self, player: &'g Player<'c>) -> InnResult<Option<&'g Player<'c>>> {
+        Ok(self.choose(player, Choose::Opponent)?.player())
     }
 
-    pub fn choose_yn(&mut self, player: &'g Player<'c>) -> bool {
-        self.yield_(player, Choose::Yn)
+    pub fn choose_yn(&mut self, player: &'g Player<'c>) -> InnResult<bool> {
+        Ok(self
+            .choose(player, Choose::Yn)?
             .yn()
-            .expect("Actors should always have valid actions when choosing yes or no.")
+            .expect("Actors should always have valid actions when choosing yes or no."))
     }
 
     pub fn may<T, F>(&mut self, player: &'g Player<'c>, f: F) -> InnResult<Option<T>>
     where
-        F: FnOnce(&mut Context<'a, 'c, 'g>) -> InnResult<T>,
+        F: FnOnce(&mut Context<'c, 'g>) -> InnResult<T>,
     {
-        self.choose_yn(player).then(|| f(self)).transpose()
+        self.choose_yn(player)?.then(|| f(self)).transpose()
     }
 
     pub fn may_splay(
@@ -190,7 +261,7 @@ impl<'a, 'c, 'g> Context<'a, 'c, 'g> {
         Ok(self
             .may(player, |ctx| {
                 let color = ctx
-                    .choose_one_card(player, available_top_cards)
+                    .choose_one_card(player, available_top_cards)?
                     .unwrap()
                     .color();
                 game.splay(player, color, direction)
@@ -199,66 +270,31 @@ impl<'a, 'c, 'g> Context<'a, 'c, 'g> {
     }
 }
 
-pub fn mk_execution<'c, 'g, F>(f: F) -> FlowState<'c, 'g>
-where
-    F: for<'a> FnOnce(&mut Context<'a, 'c, 'g>) -> InnResult<()> + 'g,
-{
-    Gn::new_scoped_local(|s| {
-        let mut ctx = Context::new(s);
-        if let Err(e) = f(&mut ctx) {
-            ctx.into_raw().yield_(Err(e));
-            panic!(
-                "Error happened but an progress of execution has been made!\n\
-                So the same execution process can't be touched.\n\
-                This can be caused by unknown internal error OR winning."
-            )
-        };
-        done!()
-    })
-}
-
 fn shared<F>(f: F) -> Dogma
 where
-    F: for<'a, 'c, 'g> Fn(
-            &'g Player<'c>,
-            &'g Players<'c>,
-            &mut Context<'a, 'c, 'g>,
-        ) -> InnResult<()>
+    F: for<'c, 'g> Fn(&'g Player<'c>, &'g Players<'c>, &mut Context<'c, 'g>) -> InnResult<()>
         + 'static,
 {
-    // convert a ctx-based dogma draft into a real ((player, game) -> generator) dogma
-    // but several generators may exist at one time, each has a reference to f
-    // meanwhile, the real dogma may have ended, so they can't refer to the dogma
-    // so Rc is used
-    // TODO: check if there's some relationship between Rc and Box here
-    let rcf = Rc::new(f);
-    Dogma::Share(Box::new(move |player, game| {
-        let cloned = Rc::clone(&rcf);
-        mk_execution(move |ctx| cloned(player, game, ctx))
-    }))
+    Dogma::Share(Box::new(f))
 }
 
 fn demand<F>(f: F) -> Dogma
 where
-    F: for<'a, 'c, 'g> Fn(
+    F: for<'c, 'g> Fn(
             &'g Player<'c>,
             &'g Player<'c>,
             &'g Players<'c>,
-            &mut Context<'a, 'c, 'g>,
+            &mut Context<'c, 'g>,
         ) -> InnResult<()>
         + 'static,
 {
-    let rcf = Rc::new(f);
-    Dogma::Demand(Box::new(move |player, opponent, game| {
-        let cloned = Rc::clone(&rcf);
-        mk_execution(move |ctx| cloned(player, opponent, game, ctx))
-    }))
+    Dogma::Demand(Box::new(f))
 }
 
 pub fn pottery() -> Vec<Dogma> {
     vec![
         shared(|player, game, ctx| {
-            let cards = ctx.choose_any_cards_up_to(player, player.hand().to_vec(), Some(3));
+            let cards = ctx.choose_any_cards_up_to(player, player.hand().to_vec(), Some(3))?;
             if !cards.is_empty() {
                 let n = cards.len();
                 for card in cards {
@@ -281,7 +317,7 @@ pub fn tools() -> Vec<Dogma> {
             // need confirmation of rule, any or exact 3 cards?
             let cards = ctx
                 .may(player, |ctx| {
-                    Ok(ctx.choose_cards_exact(player, player.hand().to_vec(), 3))
+                    ctx.choose_cards_exact(player, player.hand().to_vec(), 3)
                 })?
                 .flatten();
             if let Some(cards) = cards {
@@ -295,7 +331,7 @@ pub fn tools() -> Vec<Dogma> {
         shared(|player, game, ctx| {
             let card = ctx
                 .may(player, |ctx| {
-                    Ok(ctx.choose_one_card(player, player.hand().filtered_vec(|&c| c.age() == 3)))
+                    ctx.choose_one_card(player, player.hand().filtered_vec(|&c| c.age() == 3))
                 })?
                 .flatten();
             if let Some(card) = card {
@@ -319,7 +355,7 @@ pub fn archery() -> Vec<Dogma> {
             .expect("After drawn a 1, opponent should have at least one card.")
             .age();
         let card = ctx
-            .choose_one_card(opponent, opponent.hand().filtered_vec(|c| c.age() == age))
+            .choose_one_card(opponent, opponent.hand().filtered_vec(|c| c.age() == age))?
             .expect("After drawn a 1, opponent should have at least one card.");
         game.transfer_card(&opponent.with_id(Hand), &player.with_id(Hand), card)?;
         Ok(())
@@ -340,29 +376,20 @@ pub fn metalworking() -> Vec<Dogma> {
     })]
 }
 
-// inner state
-// but need to ensure the execution order, or avoid multiple mutable borrows
-// i.e., ensure the first is dropped before the second is created
-// also, one dogma will be executed many times by different players
-// whether the state is shared is a question
-// whether call it only once (lazy???) or once for every execution is a question
-// I guess both can work
 pub fn oars() -> Vec<Dogma> {
-    let transferred: RcCell<bool> = Rc::new(RefCell::new(false));
-    let view = Rc::clone(&transferred);
     vec![
-        demand(move |player, opponent, game, ctx| {
-            let card = ctx.choose_one_card(opponent, opponent.hand().has_icon(Crown));
+        demand(|player, opponent, game, ctx| {
+            let card = ctx.choose_one_card(opponent, opponent.hand().has_icon(Crown))?;
             if let Some(card) = card {
                 // MAYFIXED: TODO: handle the Result
                 game.transfer_card(&opponent.with_id(Hand), &player.with_id(Score), card)?;
                 game.draw(opponent, 1)?;
-                *transferred.borrow_mut() = true;
+                ctx.mark_demand_complied();
             }
             Ok(())
         }),
-        shared(move |player, game, _ctx| {
-            if !*view.borrow() {
+        shared(|player, game, ctx| {
+            if !ctx.demand_complied() {
                 game.draw(player, 1)?;
             }
             Ok(())
@@ -378,7 +405,7 @@ pub fn clothing() -> Vec<Dogma> {
                 player
                     .hand()
                     .filtered_vec(|c| player.stack(c.color()).is_empty()),
-            ); // make this a separate statement to avoid hand borrowing issue
+            )?; // make this a separate statement to avoid hand borrowing issue
             if let Some(card) = card {
                 game.meld(player, card)?;
             }
@@ -404,7 +431,7 @@ pub fn clothing() -> Vec<Dogma> {
 pub fn agriculture() -> Vec<Dogma> {
     vec![shared(|player, game, ctx| {
         let card = ctx.may(player, |ctx| {
-            Ok(ctx.choose_one_card(player, player.hand().to_vec()))
+            ctx.choose_one_card(player, player.hand().to_vec())
         })?;
         if let Some(card) = card.flatten() {
             game.r#return(player, card)?;
@@ -419,7 +446,7 @@ pub fn domestication() -> Vec<Dogma> {
         let min_age = player.hand().iter().map(|c| c.age()).min();
         if let Some(min_age) = min_age {
             let card = ctx
-                .choose_one_card(player, player.hand().filtered_vec(|c| c.age() == min_age))
+                .choose_one_card(player, player.hand().filtered_vec(|c| c.age() == min_age))?
                 .expect(
                     "There's a min age in player's hand, so there should be \
                     a card that can be chosen.",
@@ -433,7 +460,7 @@ pub fn domestication() -> Vec<Dogma> {
 
 pub fn masonry() -> Vec<Dogma> {
     vec![shared(|player, game, ctx| {
-        let to_melds = ctx.choose_any_cards_up_to(player, player.hand().has_icon(Castle), None);
+        let to_melds = ctx.choose_any_cards_up_to(player, player.hand().has_icon(Castle), None)?;
         let len = to_melds.len();
         for card in to_melds {
             game.meld(player, card)?;
@@ -448,15 +475,15 @@ pub fn masonry() -> Vec<Dogma> {
 pub fn city_states() -> Vec<Dogma> {
     vec![demand(|player, opponent, game, ctx| {
         if opponent.board().icon_count()[&Castle] >= 4 {
+            let top_cards = opponent.board().top_cards();
+            // make this a separate statement to avoid board borrowing issue
             if let Some(card) = ctx.choose_one_card(
                 opponent,
-                opponent
-                    .board()
-                    .top_cards()
+                top_cards
                     .into_iter()
                     .filter(|c| c.contains(Castle))
                     .collect(),
-            ) {
+            )? {
                 game.transfer(&opponent.with_id(Board), &player.with_id(Board), card, true)?;
                 game.draw(opponent, 1)?;
             }
@@ -472,14 +499,14 @@ pub fn code_of_laws() -> Vec<Dogma> {
             player
                 .hand()
                 .filtered_vec(|card| !player.stack(card.color()).is_empty()),
-        );
+        )?;
         let card = match opt_card {
             Some(c) => c,
             None => return Ok(()),
         };
         game.tuck(player, card)?;
         // TODO: use may_splay, and/or implement may_splay use this method?
-        if player.can_splay(card.color(), Left) && ctx.choose_yn(player) {
+        if player.can_splay(card.color(), Left) && ctx.choose_yn(player)? {
             game.splay(player, card.color(), Left)?;
         }
         Ok(())
@@ -508,7 +535,7 @@ pub fn monotheism() -> Vec<Dogma> {
                 .collect();
             // you must transfer a top card in available_cards
             // from your board to my score pile! If you do, draw and tuck a 1!
-            let chosen = ctx.choose_one_card(opponent, available_cards);
+            let chosen = ctx.choose_one_card(opponent, available_cards)?;
             if let Some(card) = chosen {
                 game.transfer(
                     &opponent.with_id(Board),
@@ -535,9 +562,9 @@ pub fn philosophy() -> Vec<Dogma> {
             Ok(())
         }),
         shared(|player, game, ctx| {
-            if !player.score_pile().to_vec().is_empty() && ctx.choose_yn(player) {
+            if !player.hand().to_vec().is_empty() && ctx.choose_yn(player)? {
                 let card = ctx
-                    .choose_one_card(player, player.score_pile().to_vec())
+                    .choose_one_card(player, player.hand().to_vec())?
                     .unwrap();
                 game.score(player, card)?;
             }
@@ -553,12 +580,12 @@ pub fn optics() -> Vec<Dogma> {
             game.draw_and_score(player, 4)?;
             Ok(())
         } else {
-            let card = match ctx.choose_one_card(player, player.score_pile().to_vec()) {
+            let card = match ctx.choose_one_card(player, player.score_pile().to_vec())? {
                 Some(card) => card,
                 None => return Ok(()),
             };
             // TODO: can only choose players that have lower score than you
-            let opponent = ctx.yield_(player, Choose::Opponent).player().expect(
+            let opponent = ctx.choose_opponent(player)?.expect(
                 "Actors should always have valid actions when choosing an opponent currently, \
                 as choose_players_from() has not yet been implemented.",
             );
@@ -570,17 +597,17 @@ pub fn optics() -> Vec<Dogma> {
 
 pub fn anatomy() -> Vec<Dogma> {
     vec![demand(|_player, opponent, game, ctx| {
-        if let Some(score_card) = ctx.choose_one_card(opponent, opponent.hand().to_vec()) {
+        let score_pile = opponent.score_pile().to_vec();
+        if let Some(score_card) = ctx.choose_one_card(opponent, score_pile)? {
             game.return_from(opponent, score_card, &opponent.with_id(Score))?;
+            let top_cards = opponent.board().top_cards();
             if let Some(board_card) = ctx.choose_one_card(
                 opponent,
-                opponent
-                    .board()
-                    .top_cards()
+                top_cards
                     .into_iter()
                     .filter(|c| c.age() == score_card.age())
                     .collect(),
-            ) {
+            )? {
                 game.return_from(opponent, board_card, &opponent.with_id(Board))?;
             }
         }
@@ -591,15 +618,14 @@ pub fn anatomy() -> Vec<Dogma> {
 pub fn enterprise() -> Vec<Dogma> {
     vec![
         demand(|player, opponent, game, ctx| {
+            let top_cards = opponent.board().top_cards();
             if let Some(card) = ctx.choose_one_card(
                 opponent,
-                opponent
-                    .board()
-                    .top_cards()
+                top_cards
                     .into_iter()
                     .filter(|c| c.color() != Purple && c.contains(Crown))
                     .collect(),
-            ) {
+            )? {
                 game.transfer(&opponent.with_id(Board), &player.with_id(Board), card, true)?;
                 game.draw_and_meld(opponent, 4)?;
             }
@@ -617,9 +643,9 @@ pub fn reformation() -> Vec<Dogma> {
         shared(|player, game, ctx| {
             let num_leaves = player.board().icon_count()[&Leaf];
             let num_cards = min(num_leaves % 2, player.hand().to_vec().len());
-            if num_cards >= 1 && ctx.choose_yn(player) {
+            if num_cards >= 1 && ctx.choose_yn(player)? {
                 let cards = ctx
-                    .choose_cards_exact(player, player.hand().to_vec(), num_cards)
+                    .choose_cards_exact(player, player.hand().to_vec(), num_cards)?
                     .expect("Player should be able to choose cards of computed number.");
                 for card in cards {
                     game.tuck(player, card)?;
@@ -642,7 +668,7 @@ pub fn computers() -> Vec<Dogma> {
         }),
         shared(|player, game, ctx| {
             let card = game.draw_and_meld(player, 10)?;
-            ctx.yield_from(game.execute_shared_alone(player, card));
+            game.execute_shared_alone(player, card, ctx)?;
             Ok(())
         }),
     ]
@@ -653,12 +679,13 @@ mod tests {
     use std::{cell::RefCell, rc::Rc};
 
     use crate::{
-        action::{Action, NoRefStep},
+        action::{Action, NoRefChoice, NoRefStep},
         card::default_cards,
         card_pile::MainCardPile,
         game::GameConfig,
         logger::{Logger, Observer},
         player::PlayerBuilder,
+        utils::vec_eq_unordered,
     };
 
     #[test]
@@ -718,4 +745,126 @@ mod tests {
         game.step(Action::Step(NoRefStep::Execute("Enterprise".to_owned())))
             .unwrap();
     }
+
+    #[test]
+    fn philosophy_scores_from_hand() {
+        let philosophy = default_cards::philosophy();
+        let archery = default_cards::archery();
+        let pottery = default_cards::pottery();
+        let mut game = GameConfig::new(vec![&philosophy, &archery, &pottery])
+            .players(vec![
+                PlayerBuilder::default()
+                    .board(vec![&philosophy])
+                    .hand(vec![&archery])
+                    .score(vec![&pottery]),
+                PlayerBuilder::default(),
+            ])
+            .build();
+        game.step(Action::Step(NoRefStep::Execute("Philosophy".to_owned())))
+            .unwrap();
+        game.step(Action::Executing(NoRefChoice::Yn(true))).unwrap();
+        let player = game.players().player_at(0);
+        assert!(player.hand().to_vec().is_empty());
+        assert!(vec_eq_unordered(
+            &player.score_pile().to_vec(),
+            [&archery, &pottery]
+        ));
+    }
+
+    #[test]
+    fn anatomy_returns_from_score_pile() {
+        let anatomy = default_cards::anatomy();
+        let archery = default_cards::archery();
+        let pottery = default_cards::pottery();
+        let clothing = default_cards::clothing();
+        let mut game = GameConfig::new(vec![&anatomy, &archery, &pottery, &clothing])
+            .players(vec![
+                PlayerBuilder::default().board(vec![&anatomy]),
+                PlayerBuilder::default()
+                    .board(vec![&archery])
+                    .hand(vec![&clothing])
+                    .score(vec![&pottery]),
+            ])
+            .build();
+        // the opponent returns Pottery, and then Archery of the same age
+        game.step(Action::Step(NoRefStep::Execute("Anatomy".to_owned())))
+            .unwrap();
+        let opponent = game.players().player_at(1);
+        assert!(opponent.score_pile().to_vec().is_empty());
+        assert!(opponent.board().top_cards().is_empty());
+        assert_eq!(opponent.hand().to_vec(), vec![&clothing]);
+    }
+
+    #[test]
+    fn city_states_borrowing() {
+        let city_states = default_cards::city_states();
+        let metalworking = default_cards::metalworking();
+        let masonry = default_cards::masonry();
+        let pottery = default_cards::pottery();
+        let mut game = GameConfig::new(vec![&city_states, &metalworking, &masonry, &pottery])
+            .main_pile(MainCardPile::builder().draw_deck(vec![&pottery]).build())
+            .players(vec![
+                PlayerBuilder::default().board(vec![&city_states]),
+                PlayerBuilder::default().board(vec![&metalworking, &masonry]),
+            ])
+            .build();
+        game.step(Action::Step(NoRefStep::Execute("City States".to_owned())))
+            .unwrap();
+        game.step(Action::Executing(NoRefChoice::Card(vec![
+            "Metalworking".to_owned()
+        ])))
+        .unwrap();
+        assert!(game.players().player_at(0).board().contains(&metalworking));
+        assert_eq!(game.players().player_at(1).hand().to_vec(), vec![&pottery]);
+    }
+
+    #[test]
+    fn enterprise_transfer_borrowing() {
+        let enterprise = default_cards::enterprise();
+        let oars = default_cards::oars();
+        let anatomy = default_cards::anatomy();
+        let mut game = GameConfig::new(vec![&enterprise, &oars, &anatomy])
+            .main_pile(MainCardPile::builder().draw_deck(vec![&anatomy]).build())
+            .players(vec![
+                PlayerBuilder::default().board(vec![&enterprise]),
+                PlayerBuilder::default().board(vec![&oars]),
+            ])
+            .build();
+        // the opponent transfers Oars, and draws and melds Anatomy
+        game.step(Action::Step(NoRefStep::Execute("Enterprise".to_owned())))
+            .unwrap();
+        assert!(game.players().player_at(0).board().contains(&oars));
+        assert_eq!(
+            game.players().player_at(1).board().top_cards(),
+            vec![&anatomy]
+        );
+    }
+
+    #[test]
+    fn oars_state_per_execution() {
+        let oars = default_cards::oars();
+        let clothing = default_cards::clothing();
+        let pottery = default_cards::pottery();
+        let archery = default_cards::archery();
+        let mut game = GameConfig::new(vec![&oars, &clothing, &pottery, &archery])
+            .main_pile(
+                MainCardPile::builder()
+                    .draw_deck(vec![&pottery, &archery])
+                    .build(),
+            )
+            .players(vec![
+                PlayerBuilder::default().board(vec![&oars]),
+                PlayerBuilder::default().hand(vec![&clothing]),
+            ])
+            .second_step(false)
+            .build();
+        // the opponent transfers Clothing and draws Pottery
+        game.step(Action::Step(NoRefStep::Execute("Oars".to_owned())))
+            .unwrap();
+        assert!(game.players().player_at(0).hand().to_vec().is_empty());
+        // no card with a crown is transferred this time, so draw Archery
+        game.step(Action::Step(NoRefStep::Execute("Oars".to_owned())))
+            .unwrap();
+        assert_eq!(game.players().player_at(0).hand().to_vec(), vec![&archery]);
+    }
 }
@@ -1,16 +1,16 @@
-use crate::{
-    action::RefChoice, error::InnResult, game::Players, player::Player, state::ExecutionState,
-};
-use generator::LocalGenerator;
+use crate::{card::dogma_fn::Context, error::InnResult, game::Players, player::Player};
 use serde::Serialize;
 
-pub type GenYield<'c, 'g> = InnResult<ExecutionState<'c, 'g>>;
-pub type GenResume<'c, 'g> = RefChoice<'c, 'g>;
-pub type FlowState<'c, 'g> = LocalGenerator<'g, GenResume<'c, 'g>, GenYield<'c, 'g>>;
-
-pub type ShareFlow = Box<dyn for<'c, 'g> Fn(&'g Player<'c>, &'g Players<'c>) -> FlowState<'c, 'g>>;
-pub type DemandFlow =
-    Box<dyn for<'c, 'g> Fn(&'g Player<'c>, &'g Player<'c>, &'g Players<'c>) -> FlowState<'c, 'g>>;
+pub type ShareFlow =
+    Box<dyn for<'c, 'g> Fn(&'g Player<'c>, &'g Players<'c>, &mut Context<'c, 'g>) -> InnResult<()>>;
+pub type DemandFlow = Box<
+    dyn for<'c, 'g> Fn(
+        &'g Player<'c>,
+        &'g Player<'c>,
+        &'g Players<'c>,
+        &mut Context<'c, 'g>,
+    ) -> InnResult<()>,
+>;
 
 pub enum Dogma {
     Share(ShareFlow),
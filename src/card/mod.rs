@@ -1,10 +1,13 @@
 mod attrs;
+#[allow(clippy::module_inception)]
 mod card;
+pub mod catalogue;
 pub mod default_cards;
 pub mod dogma_fn;
 pub mod flow;
+pub mod script;
 
 pub use attrs::{Age, Color, Icon, Splay};
 pub use card::{Achievement, Card, SpecialAchievement};
-pub use dogma_fn::mk_execution;
+pub use dogma_fn::Context;
 pub use flow::Dogma;
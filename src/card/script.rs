@@ -0,0 +1,1046 @@
+//! Cards whose dogmas are written in a small language instead of Rust.
+//!
+//! A script is a list of cards in parentheses, each being
+//!
+//! ```text
+//! ; a comment
+//! (card "Pottery" 1 Blue (Empty Leaf Leaf Leaf)
+//!   "You may return up to three cards from your hand. ..."
+//!   (share
+//!     (choose-up-to 3 hand)
+//!     (return chosen)
+//!     (if-you-do (draw-and-score (count chosen))))
+//!   (share (draw 1)))
+//! ```
+//!
+//! with the name, age, color, icons, text and dogmas. A dogma is `share` or
+//! `demand` with effects run in order. `you` is the player executing the
+//! dogma, or the opponent being demanded, and `me` is the player executing
+//! the card, who is `you` in shared dogmas. Choices are made by `you`.
+//!
+//! Effects:
+//!
+//! - `(draw AGE)`, `(draw-and-meld AGE)`, `(draw-and-score AGE)`,
+//!   `(draw-and-tuck AGE)`: the card is kept as `drawn`;
+//! - `(meld CARDS)`, `(score CARDS)`, `(tuck CARDS)`, `(return CARDS)`;
+//! - `(transfer CARDS PLACE)`, with `(hand WHO)`, `(score-pile WHO)` or
+//!   `(board WHO)` as the place, melding to a board;
+//! - `(splay DIRECTION COLOR...)` splays one of the colors, or of all if
+//!   none is given, and `(may-splay DIRECTION COLOR...)` asks first;
+//! - `(choose N CARDS)`, `(choose-up-to N CARDS)`, `(choose-any CARDS)`:
+//!   exactly N cards, or as many as there are, up to N cards, or any
+//!   number of cards, kept as `chosen`;
+//! - `(may EFFECT...)` asks whether to do the effects;
+//! - `(if-you-do EFFECT...)` does the effects if the last effect did
+//!   something, i.e. moved or chose a card or splayed;
+//! - `(if CONDITION EFFECT...)`;
+//! - `(repeat EFFECT...)` does the effects again while the last of them
+//!   does something;
+//! - `(for-each-color EFFECT...)` does the effects with `this-color` being
+//!   each color.
+//!
+//! Cards are `hand`, `score-pile`, `board` (the top cards), of `you`, or
+//! `(hand WHO)`, `(score-pile WHO)`, `(board WHO)`, or `chosen` or `drawn`,
+//! filtered by `(with-icon ICON CARDS)`, `(without-icon ICON CARDS)`,
+//! `(of-color COLOR CARDS)`, `(not-of-color COLOR CARDS)`,
+//! `(of-age N CARDS)`, `(highest CARDS)` and `(lowest CARDS)`.
+//!
+//! Numbers are integers, `(count CARDS)`, `(age-of CARDS)` (the highest
+//! age), `(icons ICON WHO)` (the icons on a board) and `(+ N N)`, `(- N N)`,
+//! `(* N N)`, `(/ N N)`. Conditions are `(= N N)`, `(< N N)`, `(<= N N)`,
+//! `(> N N)`, `(>= N N)`, `(any CARDS)`, `(not CONDITION)` and
+//! `demand-complied`, which tells whether an opponent moved cards or
+//! splayed due to a demand of the card.
+
+use std::{convert::TryInto, fmt};
+
+use strum::IntoEnumIterator;
+
+use crate::{
+    card::{Age, Card, Color, Context, Dogma, Icon, Splay},
+    error::InnResult,
+    game::{PlayerId, Players},
+    player::Player,
+    structure::{Board, Hand, PlayerPlace, Score},
+};
+
+/// The most times the effects of `repeat` are done.
+const REPEAT_LIMIT: usize = 100;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+type ParseResult<T> = Result<T, ScriptError>;
+
+enum Expr {
+    Atom(String, usize),
+    Str(String, usize),
+    List(Vec<Expr>, usize),
+}
+
+impl Expr {
+    fn line(&self) -> usize {
+        match self {
+            Expr::Atom(_, line) | Expr::Str(_, line) | Expr::List(_, line) => *line,
+        }
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> ParseResult<T> {
+        Err(ScriptError {
+            line: self.line(),
+            message: message.into(),
+        })
+    }
+
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Expr::Atom(atom, _) => Some(atom),
+            _ => None,
+        }
+    }
+
+    // the head and arguments of a list
+    fn form(&self) -> Option<(&str, &[Expr])> {
+        match self {
+            Expr::List(items, _) => match items.split_first() {
+                Some((Expr::Atom(head, _), args)) => Some((head, args)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+fn read(source: &str) -> ParseResult<Vec<Expr>> {
+    let mut stack: Vec<(Vec<Expr>, usize)> = vec![(Vec::new(), 0)];
+    let mut chars = source.chars().peekable();
+    let mut line = 1;
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => line += 1,
+            ';' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '(' => stack.push((Vec::new(), line)),
+            ')' => {
+                let (items, start) = stack.pop().unwrap();
+                match stack.last_mut() {
+                    Some((parent, _)) => parent.push(Expr::List(items, start)),
+                    None => {
+                        return Err(ScriptError {
+                            line,
+                            message: "there's no ( for this )".to_owned(),
+                        })
+                    }
+                }
+            }
+            '"' => {
+                let start = line;
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => text.push('\n'),
+                            Some(c @ ('"' | '\\')) => text.push(c),
+                            Some('\n') => {
+                                // a line continued, without the indentation
+                                line += 1;
+                                while chars.next_if(|c| c.is_whitespace()).is_some() {}
+                            }
+                            _ => {
+                                return Err(ScriptError {
+                                    line,
+                                    message: "unknown escape in a string".to_owned(),
+                                })
+                            }
+                        },
+                        Some(c) => {
+                            if c == '\n' {
+                                line += 1;
+                            }
+                            text.push(c);
+                        }
+                        None => {
+                            return Err(ScriptError {
+                                line: start,
+                                message: "the string is never closed".to_owned(),
+                            })
+                        }
+                    }
+                }
+                stack.last_mut().unwrap().0.push(Expr::Str(text, start));
+            }
+            c if c.is_whitespace() => {}
+            c => {
+                let mut atom = c.to_string();
+                while let Some(c) = chars.next_if(|&c| !c.is_whitespace() && !"();\"".contains(c)) {
+                    atom.push(c);
+                }
+                stack.last_mut().unwrap().0.push(Expr::Atom(atom, line));
+            }
+        }
+    }
+    match stack.len() {
+        1 => Ok(stack.pop().unwrap().0),
+        _ => Err(ScriptError {
+            line: stack.last().unwrap().1,
+            message: "the ( is never closed".to_owned(),
+        }),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Who {
+    You,
+    Me,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ColorRef {
+    Is(Color),
+    ThisColor,
+}
+
+#[derive(Debug, Clone)]
+enum Cards {
+    Place(Who, PlayerPlace),
+    Chosen,
+    Drawn,
+    WithIcon(Icon, Box<Cards>),
+    WithoutIcon(Icon, Box<Cards>),
+    OfColor(ColorRef, Box<Cards>),
+    NotOfColor(ColorRef, Box<Cards>),
+    OfAge(Box<Num>, Box<Cards>),
+    Highest(Box<Cards>),
+    Lowest(Box<Cards>),
+}
+
+#[derive(Debug, Clone)]
+enum Num {
+    Int(i64),
+    Count(Cards),
+    AgeOf(Cards),
+    Icons(Icon, Who),
+    Add(Box<Num>, Box<Num>),
+    Sub(Box<Num>, Box<Num>),
+    Mul(Box<Num>, Box<Num>),
+    Div(Box<Num>, Box<Num>),
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    Compare(fn(&i64, &i64) -> bool, Num, Num),
+    Any(Cards),
+    Not(Box<Condition>),
+    DemandComplied,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DrawTo {
+    Hand,
+    Meld,
+    Score,
+    Tuck,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Move {
+    Meld,
+    Score,
+    Tuck,
+    Return,
+}
+
+#[derive(Debug, Clone)]
+enum Effect {
+    Draw(DrawTo, Num),
+    Move(Move, Cards),
+    Transfer(Cards, Who, PlayerPlace),
+    Splay(Splay, Vec<Color>, bool),
+    Choose(Option<Num>, bool, Cards),
+    May(Vec<Effect>),
+    IfYouDo(Vec<Effect>),
+    If(Condition, Vec<Effect>),
+    Repeat(Vec<Effect>),
+    ForEachColor(Vec<Effect>),
+}
+
+fn name_of<T: fmt::Debug + Copy>(expr: &Expr, values: impl Iterator<Item = T>) -> Option<T> {
+    let atom = expr.atom()?;
+    values
+        .into_iter()
+        .find(|value| format!("{:?}", value) == atom)
+}
+
+fn icon(expr: &Expr) -> ParseResult<Icon> {
+    name_of(expr, Icon::iter().chain([Icon::Empty]))
+        .map_or_else(|| expr.error("expected an icon"), Ok)
+}
+
+fn color(expr: &Expr) -> ParseResult<Color> {
+    name_of(expr, Color::iter()).map_or_else(|| expr.error("expected a color"), Ok)
+}
+
+fn color_ref(expr: &Expr) -> ParseResult<ColorRef> {
+    match expr.atom() {
+        Some("this-color") => Ok(ColorRef::ThisColor),
+        _ => color(expr).map(ColorRef::Is),
+    }
+}
+
+fn who(expr: &Expr) -> ParseResult<Who> {
+    match expr.atom() {
+        Some("you") => Ok(Who::You),
+        Some("me") => Ok(Who::Me),
+        _ => expr.error("expected you or me"),
+    }
+}
+
+fn place(expr: &Expr) -> ParseResult<(Who, PlayerPlace)> {
+    let zone = |name: &str| match name {
+        "hand" => Some(PlayerPlace::Hand),
+        "score-pile" => Some(PlayerPlace::Score),
+        "board" => Some(PlayerPlace::Board),
+        _ => None,
+    };
+    match (expr.atom(), expr.form()) {
+        (Some(name), _) => zone(name).map(|zone| (Who::You, zone)),
+        (_, Some((name, [player]))) => match zone(name) {
+            Some(zone) => return Ok((who(player)?, zone)),
+            None => None,
+        },
+        _ => None,
+    }
+    .map_or_else(|| expr.error("expected a place"), Ok)
+}
+
+fn args<'e>(expr: &'e Expr, args: &'e [Expr], num: usize) -> ParseResult<&'e [Expr]> {
+    if args.len() == num {
+        Ok(args)
+    } else {
+        expr.error(format!(
+            "{} takes {} argument{}",
+            expr.form().unwrap().0,
+            num,
+            if num == 1 { "" } else { "s" }
+        ))
+    }
+}
+
+fn cards(expr: &Expr) -> ParseResult<Cards> {
+    match expr.atom() {
+        Some("chosen") => return Ok(Cards::Chosen),
+        Some("drawn") => return Ok(Cards::Drawn),
+        _ => {}
+    }
+    if let Ok((who, zone)) = place(expr) {
+        return Ok(Cards::Place(who, zone));
+    }
+    let (head, rest) = match expr.form() {
+        Some(form) => form,
+        None => return expr.error("expected cards"),
+    };
+    let of = |rest: &[Expr]| cards(&rest[1]).map(Box::new);
+    Ok(match head {
+        "with-icon" => {
+            let rest = args(expr, rest, 2)?;
+            Cards::WithIcon(icon(&rest[0])?, of(rest)?)
+        }
+        "without-icon" => {
+            let rest = args(expr, rest, 2)?;
+            Cards::WithoutIcon(icon(&rest[0])?, of(rest)?)
+        }
+        "of-color" => {
+            let rest = args(expr, rest, 2)?;
+            Cards::OfColor(color_ref(&rest[0])?, of(rest)?)
+        }
+        "not-of-color" => {
+            let rest = args(expr, rest, 2)?;
+            Cards::NotOfColor(color_ref(&rest[0])?, of(rest)?)
+        }
+        "of-age" => {
+            let rest = args(expr, rest, 2)?;
+            Cards::OfAge(Box::new(num(&rest[0])?), of(rest)?)
+        }
+        "highest" => Cards::Highest(Box::new(cards(&args(expr, rest, 1)?[0])?)),
+        "lowest" => Cards::Lowest(Box::new(cards(&args(expr, rest, 1)?[0])?)),
+        _ => return expr.error(format!("{} is not cards", head)),
+    })
+}
+
+fn num(expr: &Expr) -> ParseResult<Num> {
+    if let Some(atom) = expr.atom() {
+        return atom
+            .parse()
+            .map(Num::Int)
+            .or_else(|_| expr.error("expected a number"));
+    }
+    let (head, rest) = match expr.form() {
+        Some(form) => form,
+        None => return expr.error("expected a number"),
+    };
+    let binary = |f: fn(Box<Num>, Box<Num>) -> Num| {
+        let rest = args(expr, rest, 2)?;
+        Ok(f(Box::new(num(&rest[0])?), Box::new(num(&rest[1])?)))
+    };
+    match head {
+        "count" => Ok(Num::Count(cards(&args(expr, rest, 1)?[0])?)),
+        "age-of" => Ok(Num::AgeOf(cards(&args(expr, rest, 1)?[0])?)),
+        "icons" => {
+            let rest = args(expr, rest, 2)?;
+            Ok(Num::Icons(icon(&rest[0])?, who(&rest[1])?))
+        }
+        "+" => binary(Num::Add),
+        "-" => binary(Num::Sub),
+        "*" => binary(Num::Mul),
+        "/" => binary(Num::Div),
+        _ => expr.error(format!("{} is not a number", head)),
+    }
+}
+
+fn condition(expr: &Expr) -> ParseResult<Condition> {
+    if expr.atom() == Some("demand-complied") {
+        return Ok(Condition::DemandComplied);
+    }
+    let (head, rest) = match expr.form() {
+        Some(form) => form,
+        None => return expr.error("expected a condition"),
+    };
+    let compare = |f: fn(&i64, &i64) -> bool| {
+        let rest = args(expr, rest, 2)?;
+        Ok(Condition::Compare(f, num(&rest[0])?, num(&rest[1])?))
+    };
+    match head {
+        "=" => compare(i64::eq),
+        "<" => compare(i64::lt),
+        "<=" => compare(i64::le),
+        ">" => compare(i64::gt),
+        ">=" => compare(i64::ge),
+        "any" => Ok(Condition::Any(cards(&args(expr, rest, 1)?[0])?)),
+        "not" => Ok(Condition::Not(Box::new(condition(
+            &args(expr, rest, 1)?[0],
+        )?))),
+        _ => expr.error(format!("{} is not a condition", head)),
+    }
+}
+
+fn effects(exprs: &[Expr]) -> ParseResult<Vec<Effect>> {
+    exprs.iter().map(effect).collect()
+}
+
+fn effect(expr: &Expr) -> ParseResult<Effect> {
+    let (head, rest) = match expr.form() {
+        Some(form) => form,
+        None => return expr.error("expected an effect"),
+    };
+    let draw = |to| Ok(Effect::Draw(to, num(&args(expr, rest, 1)?[0])?));
+    let move_to = |to| Ok(Effect::Move(to, cards(&args(expr, rest, 1)?[0])?));
+    let splay = |may| match rest.split_first() {
+        Some((direction, colors)) => Ok(Effect::Splay(
+            name_of(direction, Splay::iter())
+                .filter(|&splay| splay != Splay::NoSplay)
+                .map_or_else(|| direction.error("expected Left, Right or Up"), Ok)?,
+            colors.iter().map(color).collect::<ParseResult<_>>()?,
+            may,
+        )),
+        None => expr.error("expected a direction"),
+    };
+    match head {
+        "draw" => draw(DrawTo::Hand),
+        "draw-and-meld" => draw(DrawTo::Meld),
+        "draw-and-score" => draw(DrawTo::Score),
+        "draw-and-tuck" => draw(DrawTo::Tuck),
+        "meld" => move_to(Move::Meld),
+        "score" => move_to(Move::Score),
+        "tuck" => move_to(Move::Tuck),
+        "return" => move_to(Move::Return),
+        "transfer" => {
+            let rest = args(expr, rest, 2)?;
+            let (who, zone) = place(&rest[1])?;
+            Ok(Effect::Transfer(cards(&rest[0])?, who, zone))
+        }
+        "splay" => splay(false),
+        "may-splay" => splay(true),
+        "choose" | "choose-up-to" => {
+            let rest = args(expr, rest, 2)?;
+            Ok(Effect::Choose(
+                Some(num(&rest[0])?),
+                head == "choose",
+                cards(&rest[1])?,
+            ))
+        }
+        "choose-any" => Ok(Effect::Choose(
+            None,
+            false,
+            cards(&args(expr, rest, 1)?[0])?,
+        )),
+        "may" => Ok(Effect::May(effects(rest)?)),
+        "if-you-do" => Ok(Effect::IfYouDo(effects(rest)?)),
+        "if" => match rest.split_first() {
+            Some((cond, body)) => Ok(Effect::If(condition(cond)?, effects(body)?)),
+            None => expr.error("expected a condition"),
+        },
+        "repeat" => Ok(Effect::Repeat(effects(rest)?)),
+        "for-each-color" => Ok(Effect::ForEachColor(effects(rest)?)),
+        _ => expr.error(format!("{} is not an effect", head)),
+    }
+}
+
+fn share(body: Vec<Effect>) -> Dogma {
+    Dogma::Share(Box::new(move |player, game, ctx| {
+        Run::new(game, player, player, ctx, false).effects(&body)
+    }))
+}
+
+fn demand(body: Vec<Effect>) -> Dogma {
+    Dogma::Demand(Box::new(move |player, opponent, game, ctx| {
+        Run::new(game, opponent, player, ctx, true).effects(&body)
+    }))
+}
+
+fn dogma(expr: &Expr) -> ParseResult<Dogma> {
+    match expr.form() {
+        Some(("share", body)) => Ok(share(effects(body)?)),
+        Some(("demand", body)) => Ok(demand(effects(body)?)),
+        _ => expr.error("expected a share or demand dogma"),
+    }
+}
+
+fn card(expr: &Expr) -> ParseResult<Card> {
+    let rest = match expr.form() {
+        Some(("card", rest)) if rest.len() >= 5 => rest,
+        _ => return expr.error("expected (card NAME AGE COLOR ICONS TEXT DOGMA...)"),
+    };
+    let name = match &rest[0] {
+        Expr::Str(name, _) => name.clone(),
+        other => return other.error("expected the name as a string"),
+    };
+    let age = match rest[1].atom().and_then(|age| age.parse::<Age>().ok()) {
+        Some(age) if (1..=10).contains(&age) => age,
+        _ => return rest[1].error("expected an age from 1 to 10"),
+    };
+    let icons = match &rest[3] {
+        Expr::List(icons, _) if icons.len() == 4 => [
+            icon(&icons[0])?,
+            icon(&icons[1])?,
+            icon(&icons[2])?,
+            icon(&icons[3])?,
+        ],
+        other => return other.error("expected four icons"),
+    };
+    let doc = match &rest[4] {
+        Expr::Str(doc, _) => doc.clone(),
+        other => return other.error("expected the text as a string"),
+    };
+    let dogmas = rest[5..].iter().map(dogma).collect::<ParseResult<_>>()?;
+    Ok(Card::new(name, age, color(&rest[2])?, icons, dogmas, doc))
+}
+
+/// Reads the cards of a script.
+pub fn parse_cards(source: &str) -> Result<Vec<Card>, ScriptError> {
+    read(source)?.iter().map(card).collect()
+}
+
+/// Reads dogmas alone, i.e. `share` and `demand` forms, such as for the
+/// entries of a `card::catalogue::Catalogue`.
+pub fn parse_dogmas(source: &str) -> Result<Vec<Dogma>, ScriptError> {
+    read(source)?.iter().map(dogma).collect()
+}
+
+// a card and where it is, if not in the draw piles
+#[derive(Clone, Copy)]
+struct Held<'c> {
+    card: &'c Card,
+    place: Option<(PlayerId, PlayerPlace)>,
+}
+
+// an execution of a dogma
+struct Run<'c, 'g, 'x> {
+    game: &'g Players<'c>,
+    you: &'g Player<'c>,
+    me: &'g Player<'c>,
+    ctx: &'x mut Context<'c, 'g>,
+    is_demand: bool,
+    chosen: Vec<Held<'c>>,
+    drawn: Vec<Held<'c>>,
+    // whether the last effect did something
+    did: bool,
+    color: Option<Color>,
+}
+
+// runs `$body` with `$from` being the place of a player, as a value of its
+// type in `structure`
+macro_rules! with_place {
+    ($owner:expr, $zone:expr, |$from:ident| $body:expr) => {
+        match $zone {
+            PlayerPlace::Hand => {
+                let $from = ($owner, Hand);
+                $body
+            }
+            PlayerPlace::Score => {
+                let $from = ($owner, Score);
+                $body
+            }
+            PlayerPlace::Board => {
+                let $from = ($owner, Board);
+                $body
+            }
+        }
+    };
+}
+
+impl<'c, 'g, 'x> Run<'c, 'g, 'x> {
+    fn new(
+        game: &'g Players<'c>,
+        you: &'g Player<'c>,
+        me: &'g Player<'c>,
+        ctx: &'x mut Context<'c, 'g>,
+        is_demand: bool,
+    ) -> Self {
+        Run {
+            game,
+            you,
+            me,
+            ctx,
+            is_demand,
+            chosen: Vec::new(),
+            drawn: Vec::new(),
+            did: false,
+            color: None,
+        }
+    }
+
+    fn player(&self, who: Who) -> &'g Player<'c> {
+        match who {
+            Who::You => self.you,
+            Who::Me => self.me,
+        }
+    }
+
+    fn color(&self, color: ColorRef) -> Option<Color> {
+        match color {
+            ColorRef::Is(color) => Some(color),
+            ColorRef::ThisColor => self.color,
+        }
+    }
+
+    fn cards(&self, cards: &Cards) -> Vec<Held<'c>> {
+        let filter = |cards: &Cards, f: &dyn Fn(&Card) -> bool| {
+            let mut held = self.cards(cards);
+            held.retain(|held| f(held.card));
+            held
+        };
+        match cards {
+            Cards::Place(who, zone) => {
+                let player = self.player(*who);
+                let cards = match zone {
+                    PlayerPlace::Hand => player.hand().to_vec(),
+                    PlayerPlace::Score => player.score_pile().to_vec(),
+                    PlayerPlace::Board => player.board().top_cards(),
+                };
+                cards
+                    .into_iter()
+                    .map(|card| Held {
+                        card,
+                        place: Some((player.id(), *zone)),
+                    })
+                    .collect()
+            }
+            Cards::Chosen => self.chosen.clone(),
+            Cards::Drawn => self.drawn.clone(),
+            Cards::WithIcon(icon, cards) => filter(cards, &|card| card.contains(*icon)),
+            Cards::WithoutIcon(icon, cards) => filter(cards, &|card| !card.contains(*icon)),
+            Cards::OfColor(color, cards) => {
+                let color = self.color(*color);
+                filter(cards, &|card| Some(card.color()) == color)
+            }
+            Cards::NotOfColor(color, cards) => {
+                let color = self.color(*color);
+                filter(cards, &|card| Some(card.color()) != color)
+            }
+            Cards::OfAge(age, cards) => {
+                let age = self.num(age);
+                filter(cards, &|card| i64::from(card.age()) == age)
+            }
+            Cards::Highest(cards) => {
+                let mut held = self.cards(cards);
+                let age = held.iter().map(|held| held.card.age()).max();
+                held.retain(|held| Some(held.card.age()) == age);
+                held
+            }
+            Cards::Lowest(cards) => {
+                let mut held = self.cards(cards);
+                let age = held.iter().map(|held| held.card.age()).min();
+                held.retain(|held| Some(held.card.age()) == age);
+                held
+            }
+        }
+    }
+
+    fn num(&self, num: &Num) -> i64 {
+        match num {
+            Num::Int(n) => *n,
+            Num::Count(cards) => self.cards(cards).len() as i64,
+            Num::AgeOf(cards) => self
+                .cards(cards)
+                .iter()
+                .map(|held| i64::from(held.card.age()))
+                .max()
+                .unwrap_or(0),
+            Num::Icons(icon, who) => self.player(*who).board().icon_count()[icon] as i64,
+            Num::Add(a, b) => self.num(a).saturating_add(self.num(b)),
+            Num::Sub(a, b) => self.num(a).saturating_sub(self.num(b)),
+            Num::Mul(a, b) => self.num(a).saturating_mul(self.num(b)),
+            Num::Div(a, b) => match self.num(b) {
+                0 => 0,
+                b => self.num(a).div_euclid(b),
+            },
+        }
+    }
+
+    fn condition(&self, condition: &Condition) -> bool {
+        match condition {
+            Condition::Compare(f, a, b) => f(&self.num(a), &self.num(b)),
+            Condition::Any(cards) => !self.cards(cards).is_empty(),
+            Condition::Not(condition) => !self.condition(condition),
+            Condition::DemandComplied => self.ctx.demand_complied(),
+        }
+    }
+
+    // the age to draw, where ages above 10 end the game
+    fn age(&self, age: &Num) -> Age {
+        self.num(age)
+            .clamp(1, 11)
+            .try_into()
+            .expect("The age is clamped.")
+    }
+
+    // records that `card` is at `place` now
+    fn moved(&mut self, card: &'c Card, place: Option<(PlayerId, PlayerPlace)>) {
+        for held in self.chosen.iter_mut().chain(self.drawn.iter_mut()) {
+            if held.card == card {
+                held.place = place;
+            }
+        }
+        if self.is_demand {
+            self.ctx.mark_demand_complied();
+        }
+    }
+
+    fn effects(&mut self, effects: &[Effect]) -> InnResult<()> {
+        self.did = false;
+        for effect in effects {
+            self.effect(effect)?;
+        }
+        Ok(())
+    }
+
+    fn effect(&mut self, effect: &Effect) -> InnResult<()> {
+        let (game, you) = (self.game, self.you);
+        match effect {
+            Effect::Draw(to, age) => {
+                let age = self.age(age);
+                let (card, zone) = match to {
+                    DrawTo::Hand => (game.draw(you, age)?, PlayerPlace::Hand),
+                    DrawTo::Meld => (game.draw_and_meld(you, age)?, PlayerPlace::Board),
+                    DrawTo::Score => (game.draw_and_score(you, age)?, PlayerPlace::Score),
+                    DrawTo::Tuck => (game.draw_and_tuck(you, age)?, PlayerPlace::Board),
+                };
+                self.drawn = vec![Held {
+                    card,
+                    place: Some((you.id(), zone)),
+                }];
+                self.did = true;
+            }
+            Effect::Move(to, cards) => {
+                self.did = false;
+                for held in self.cards(cards) {
+                    let (owner, zone) = match held.place {
+                        Some(place) => place,
+                        None => continue,
+                    };
+                    let card = held.card;
+                    with_place!(owner, zone, |from| match to {
+                        Move::Meld => game.meld_from(you, card, &from)?,
+                        Move::Score => game.score_from(you, card, &from)?,
+                        Move::Tuck => game.tuck_from(you, card, &from)?,
+                        Move::Return => game.return_from(you, card, &from)?,
+                    });
+                    self.moved(
+                        card,
+                        match to {
+                            Move::Meld | Move::Tuck => Some((you.id(), PlayerPlace::Board)),
+                            Move::Score => Some((you.id(), PlayerPlace::Score)),
+                            Move::Return => None,
+                        },
+                    );
+                    self.did = true;
+                }
+            }
+            Effect::Transfer(cards, who, to) => {
+                self.did = false;
+                let receiver = self.player(*who).id();
+                for held in self.cards(cards) {
+                    let (owner, zone) = match held.place {
+                        Some(place) => place,
+                        None => continue,
+                    };
+                    let card = held.card;
+                    with_place!(owner, zone, |from| match to {
+                        PlayerPlace::Hand => game.transfer_card(&from, &(receiver, Hand), card)?,
+                        PlayerPlace::Score => {
+                            game.transfer_card(&from, &(receiver, Score), card)?
+                        }
+                        PlayerPlace::Board => {
+                            game.transfer(&from, &(receiver, Board), card, true)?;
+                        }
+                    });
+                    self.moved(card, Some((receiver, *to)));
+                    self.did = true;
+                }
+            }
+            Effect::Splay(direction, colors, may) => {
+                let colors = if colors.is_empty() {
+                    Color::iter().collect()
+                } else {
+                    colors.clone()
+                };
+                self.did = if *may {
+                    self.ctx.may_splays(you, game, colors, *direction)?
+                } else {
+                    let top_cards: Vec<_> = colors
+                        .into_iter()
+                        .filter(|&color| you.can_splay(color, *direction))
+                        .map(|color| you.stack(color).top_card().unwrap())
+                        .collect();
+                    match self.ctx.choose_one_card(you, top_cards)? {
+                        Some(card) => {
+                            game.splay(you, card.color(), *direction)?;
+                            true
+                        }
+                        None => false,
+                    }
+                };
+                if self.did && self.is_demand {
+                    self.ctx.mark_demand_complied();
+                }
+            }
+            Effect::Choose(num, exact, cards) => {
+                let held = self.cards(cards);
+                let from: Vec<_> = held.iter().map(|held| held.card).collect();
+                let num = num
+                    .as_ref()
+                    .map(|num| self.num(num).clamp(0, from.len() as i64) as usize);
+                let chosen = match (num, exact) {
+                    (Some(0), _) => Vec::new(),
+                    (Some(num), true) => self
+                        .ctx
+                        .choose_cards_exact(you, from, num)?
+                        .unwrap_or_default(),
+                    (num, _) => self.ctx.choose_any_cards_up_to(you, from, num)?,
+                };
+                self.chosen = chosen
+                    .into_iter()
+                    .filter_map(|card| held.iter().find(|held| held.card == card).copied())
+                    .collect();
+                self.did = !self.chosen.is_empty();
+            }
+            Effect::May(effects) => {
+                if self.ctx.choose_yn(you)? {
+                    self.effects(effects)?;
+                } else {
+                    self.did = false;
+                }
+            }
+            Effect::IfYouDo(effects) => {
+                if self.did {
+                    self.effects(effects)?;
+                }
+            }
+            Effect::If(condition, effects) => {
+                if self.condition(condition) {
+                    self.effects(effects)?;
+                } else {
+                    self.did = false;
+                }
+            }
+            Effect::Repeat(effects) => {
+                for _ in 0..REPEAT_LIMIT {
+                    self.effects(effects)?;
+                    if !self.did {
+                        break;
+                    }
+                }
+            }
+            Effect::ForEachColor(effects) => {
+                let outer = self.color;
+                let mut did = false;
+                for color in Color::iter() {
+                    self.color = Some(color);
+                    self.effects(effects)?;
+                    did |= self.did;
+                }
+                self.color = outer;
+                self.did = did;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{Action, NoRefStep},
+        card::default_cards,
+        card_pile::MainCardPile,
+        game::GameConfig,
+        player::PlayerBuilder,
+    };
+
+    const SCRIPT: &str = r#"
+        (card "Oars" 1 Red (Castle Crown Empty Castle)
+          "I demand you transfer a card with a [Crown] from your hand to my score pile! \
+          If you do, draw a 1.\n\
+          If no cards were transferred due to this demand, draw a 1."
+          (demand
+            (choose 1 (with-icon Crown hand))
+            (transfer chosen (score-pile me))
+            (if-you-do (draw 1)))
+          (share (if (not demand-complied) (draw 1))))
+
+        ; scores until a card without a castle is drawn
+        (card "Metalworking" 1 Red (Castle Castle Empty Castle)
+          "Draw and reveal a 1. If it has a [Castle], score it and repeat this dogma effect. \
+          Otherwise, keep it."
+          (share (repeat (draw 1) (score (with-icon Castle drawn)))))
+
+        (card "Census" 2 Purple (Crown Empty Leaf Crown)
+          "Draw a 1 for each color on your board. Tuck your highest card in hand."
+          (share
+            (for-each-color (if (any (of-color this-color board)) (draw 1)))
+            (choose 1 (highest hand))
+            (tuck chosen)))
+    "#;
+
+    #[test]
+    fn parse() {
+        let cards = parse_cards(SCRIPT).unwrap();
+        let names: Vec<_> = cards.iter().map(Card::name).collect();
+        assert_eq!(names, vec!["Oars", "Metalworking", "Census"]);
+        let oars = default_cards::oars();
+        assert_eq!(cards[0].age(), oars.age());
+        assert_eq!(cards[0].icons(), oars.icons());
+        assert_eq!(cards[0].main_icon(), Icon::Castle);
+        assert_eq!(cards[0].doc(), oars.doc());
+        assert!(matches!(
+            cards[0].dogmas(),
+            [Dogma::Demand(_), Dogma::Share(_)]
+        ));
+        assert_eq!(parse_dogmas("(share (draw 1)) (demand)").unwrap().len(), 2);
+
+        let error = |source| parse_cards(source).unwrap_err().to_string();
+        assert_eq!(
+            error("(card \"X\" 1 Red (Leaf Leaf Leaf Leaf) \"\"\n  (share (drow 1)))"),
+            "line 2: drow is not an effect"
+        );
+        assert_eq!(
+            error("(card \"X\" 11 Red (Leaf Leaf Leaf Leaf) \"\")"),
+            "line 1: expected an age from 1 to 10"
+        );
+        assert_eq!(
+            error("(card \"X\" 1 Red (Leaf Leaf Leaf Leaf) \"\"\n  (share (draw 1 2)))"),
+            "line 2: draw takes 1 argument"
+        );
+        assert_eq!(error("(card \"X\"\n"), "line 1: the ( is never closed");
+    }
+
+    #[test]
+    fn scripted_oars() {
+        let cards = parse_cards(SCRIPT).unwrap();
+        let oars = &cards[0];
+        let clothing = default_cards::clothing();
+        let pottery = default_cards::pottery();
+        let archery = default_cards::archery();
+        let mut game = GameConfig::new(vec![oars, &clothing, &pottery, &archery])
+            .main_pile(
+                MainCardPile::builder()
+                    .draw_deck(vec![&pottery, &archery])
+                    .build(),
+            )
+            .players(vec![
+                PlayerBuilder::default().board(vec![oars]),
+                PlayerBuilder::default().hand(vec![&clothing]),
+            ])
+            .second_step(false)
+            .build();
+        // the opponent transfers Clothing and draws Pottery
+        game.step(Action::Step(NoRefStep::Execute("Oars".to_owned())))
+            .unwrap();
+        assert_eq!(
+            game.players().player_at(0).score_pile().to_vec(),
+            vec![&clothing]
+        );
+        assert!(game.players().player_at(0).hand().to_vec().is_empty());
+        assert_eq!(game.players().player_at(1).hand().to_vec(), vec![&pottery]);
+        // no card with a crown is transferred this time, so draw Archery
+        game.step(Action::Step(NoRefStep::Execute("Oars".to_owned())))
+            .unwrap();
+        assert_eq!(game.players().player_at(0).hand().to_vec(), vec![&archery]);
+    }
+
+    #[test]
+    fn scripted_metalworking_and_census() {
+        let cards = parse_cards(SCRIPT).unwrap();
+        let (metalworking, census) = (&cards[1], &cards[2]);
+        let archery = default_cards::archery();
+        let pottery = default_cards::pottery();
+        let tools = default_cards::tools();
+        let optics = default_cards::optics();
+        let all_cards = vec![metalworking, census, &archery, &pottery, &tools, &optics];
+        let build = |hand| {
+            GameConfig::new(all_cards.clone())
+                .main_pile(
+                    MainCardPile::builder()
+                        .draw_deck(vec![&archery, &pottery, &tools])
+                        .build(),
+                )
+                .players(vec![
+                    PlayerBuilder::default()
+                        .board(vec![metalworking, census])
+                        .hand(hand),
+                    PlayerBuilder::default(),
+                ])
+                .second_step(false)
+                .build()
+        };
+
+        // Archery has a castle and is scored, Pottery doesn't
+        let mut game = build(Vec::new());
+        game.step(Action::Step(NoRefStep::Execute("Metalworking".to_owned())))
+            .unwrap();
+        let player = game.players().player_at(0);
+        assert_eq!(player.score_pile().to_vec(), vec![&archery]);
+        assert_eq!(player.hand().to_vec(), vec![&pottery]);
+
+        // a card for each of red and purple, then Optics is tucked
+        let mut game = build(vec![&optics]);
+        game.step(Action::Step(NoRefStep::Execute("Census".to_owned())))
+            .unwrap();
+        let player = game.players().player_at(0);
+        assert_eq!(player.hand().to_vec(), vec![&archery, &pottery]);
+        assert_eq!(player.stack(Color::Red).top_card(), Some(metalworking));
+        assert!(game.observe(0).main_player.board.contains(&optics));
+    }
+}
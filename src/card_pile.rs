@@ -1,4 +1,4 @@
-use rand::{seq::SliceRandom, thread_rng};
+use rand::{seq::SliceRandom, thread_rng, Rng};
 
 use crate::{
     card::{Achievement, Age, Card, SpecialAchievement},
@@ -38,6 +38,7 @@ impl<'a> Removeable<&'a Card, ()> for CardPile<'a> {
     }
 }
 
+#[derive(Clone)]
 pub struct MainCardPile<'a> {
     piles: [CardPile<'a>; 10],
     achievements: VecSet<Achievement<'a>>,
@@ -127,6 +128,16 @@ impl<'a> MainCardPile<'a> {
     pub fn has_achievement(&self, view: &SingleAchievementView) -> bool {
         self.achievements.inner().iter().any(|a| a == view)
     }
+
+    /// The achievements which are not claimed yet.
+    pub fn achievements(&self) -> &[Achievement<'a>] {
+        self.achievements.inner()
+    }
+
+    /// The achievements which are not claimed yet.
+    pub fn achievement_views(&self) -> Vec<SingleAchievementView> {
+        self.achievements.inner().iter().map(|a| a.view()).collect()
+    }
 }
 
 impl<'a> Addable<&'a Card> for MainCardPile<'a> {
@@ -152,6 +163,7 @@ impl<'a> Removeable<Achievement<'a>, SingleAchievementView> for MainCardPile<'a>
 pub struct MainCardPileBuilder<'a> {
     piles: Vec<&'a Card>,
     achievements: Vec<Achievement<'a>>,
+    shuffle: bool,
     pick_normal_after_init: bool,
 }
 
@@ -176,8 +188,9 @@ impl<'a> MainCardPileBuilder<'a> {
         self
     }
 
+    /// Shuffle the deck when building; see `build_with`.
     pub fn shuffled(mut self) -> Self {
-        self.piles.shuffle(&mut thread_rng());
+        self.shuffle = true;
         self
     }
 
@@ -187,7 +200,16 @@ impl<'a> MainCardPileBuilder<'a> {
         self
     }
 
+    /// Builds the pile, shuffling the deck with `thread_rng()` if `shuffled`.
     pub fn build(self) -> MainCardPile<'a> {
+        self.build_with(&mut thread_rng())
+    }
+
+    /// Builds the pile, shuffling the deck with `rng` if `shuffled`.
+    pub fn build_with<R: Rng + ?Sized>(mut self, rng: &mut R) -> MainCardPile<'a> {
+        if self.shuffle {
+            self.piles.shuffle(rng);
+        }
         let mut pile = MainCardPile::new(self.piles, self.achievements);
         if self.pick_normal_after_init {
             // pick one (if exists) card of each of the first 9 ages as achievement
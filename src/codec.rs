@@ -0,0 +1,387 @@
+//! A fixed discrete action space for learning agents.
+//!
+//! Every action gets an index, computed from the card list of the game:
+//!
+//! | index                     | action                        |
+//! |---------------------------|-------------------------------|
+//! | `0`                       | draw                          |
+//! | `1 + i`                   | meld the `i`th card           |
+//! | `1 + n + (a - 1)`         | achieve age `a`, `1 <= a <= 10` |
+//! | `11 + n + i`              | execute the `i`th card        |
+//! | `11 + 2n + i`             | pick the `i`th card           |
+//! | `11 + 3n`                 | done picking                  |
+//! | `12 + 3n + p`             | choose player `p` as opponent |
+//! | `12 + 3n + MAX_PLAYERS`   | yes                           |
+//! | `13 + 3n + MAX_PLAYERS`   | no                            |
+//!
+//! where `n` is the number of cards.
+//!
+//! A `Choose::Card` choice is made by picking cards one by one, then choosing
+//! "done". Picking is finished automatically when no more cards can be picked.
+
+use crate::{
+    action::{Action, LegalActions, NoRefChoice, NoRefStep},
+    card::{Age, Card},
+    error::{InnResult, InnovationError},
+    game::OuterGame,
+    observation::ObsType,
+    state::Choose,
+};
+
+/// The largest number of players supported by the action space.
+pub const MAX_PLAYERS: usize = 4;
+
+const NUM_AGES: usize = 10;
+
+/// One entry of the discrete action space.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DiscreteAction {
+    /// A complete action.
+    Action(Action),
+    /// Adds a card to the card choice being made.
+    Pick(String),
+    /// Finishes the card choice with the cards picked so far.
+    Done,
+}
+
+pub struct ActionCodec {
+    card_names: Vec<String>,
+}
+
+impl ActionCodec {
+    pub fn new(cards: &[&Card]) -> ActionCodec {
+        ActionCodec {
+            card_names: cards.iter().map(|c| c.name().to_owned()).collect(),
+        }
+    }
+
+    pub fn from_game(game: &OuterGame) -> ActionCodec {
+        ActionCodec::new(game.players().cards())
+    }
+
+    fn num_cards(&self) -> usize {
+        self.card_names.len()
+    }
+
+    fn meld_offset(&self) -> usize {
+        1
+    }
+
+    fn achieve_offset(&self) -> usize {
+        self.meld_offset() + self.num_cards()
+    }
+
+    fn execute_offset(&self) -> usize {
+        self.achieve_offset() + NUM_AGES
+    }
+
+    fn pick_offset(&self) -> usize {
+        self.execute_offset() + self.num_cards()
+    }
+
+    fn done_index(&self) -> usize {
+        self.pick_offset() + self.num_cards()
+    }
+
+    fn opponent_offset(&self) -> usize {
+        self.done_index() + 1
+    }
+
+    fn yn_offset(&self) -> usize {
+        self.opponent_offset() + MAX_PLAYERS
+    }
+
+    /// The number of indices in the action space.
+    pub fn size(&self) -> usize {
+        self.yn_offset() + 2
+    }
+
+    pub fn card_names(&self) -> &[String] {
+        &self.card_names
+    }
+
+    pub fn card_index(&self, name: &str) -> Option<usize> {
+        self.card_names.iter().position(|n| n == name)
+    }
+
+    pub fn to_index(&self, action: &DiscreteAction) -> Option<usize> {
+        match action {
+            DiscreteAction::Action(Action::Step(step)) => match step {
+                NoRefStep::Draw => Some(0),
+                NoRefStep::Meld(name) => Some(self.meld_offset() + self.card_index(name)?),
+                NoRefStep::Achieve(age) => {
+                    if (1..=NUM_AGES).contains(&(*age as usize)) {
+                        Some(self.achieve_offset() + *age as usize - 1)
+                    } else {
+                        None
+                    }
+                }
+                NoRefStep::Execute(name) => Some(self.execute_offset() + self.card_index(name)?),
+            },
+            DiscreteAction::Action(Action::Executing(choice)) => match choice {
+                NoRefChoice::Card(names) => match names.as_slice() {
+                    [name] => Some(self.pick_offset() + self.card_index(name)?),
+                    _ => None,
+                },
+                NoRefChoice::Opponent(id) => {
+                    if *id < MAX_PLAYERS {
+                        Some(self.opponent_offset() + id)
+                    } else {
+                        None
+                    }
+                }
+                NoRefChoice::Yn(yn) => Some(self.yn_offset() + if *yn { 0 } else { 1 }),
+            },
+            DiscreteAction::Pick(name) => Some(self.pick_offset() + self.card_index(name)?),
+            DiscreteAction::Done => Some(self.done_index()),
+        }
+    }
+
+    pub fn from_index(&self, index: usize) -> Option<DiscreteAction> {
+        let card = |offset: usize| self.card_names[index - offset].clone();
+        let action = if index == 0 {
+            DiscreteAction::Action(Action::Step(NoRefStep::Draw))
+        } else if index < self.achieve_offset() {
+            DiscreteAction::Action(Action::Step(NoRefStep::Meld(card(self.meld_offset()))))
+        } else if index < self.execute_offset() {
+            let age = (index - self.achieve_offset() + 1) as Age;
+            DiscreteAction::Action(Action::Step(NoRefStep::Achieve(age)))
+        } else if index < self.pick_offset() {
+            DiscreteAction::Action(Action::Step(NoRefStep::Execute(card(
+                self.execute_offset(),
+            ))))
+        } else if index < self.done_index() {
+            DiscreteAction::Pick(card(self.pick_offset()))
+        } else if index == self.done_index() {
+            DiscreteAction::Done
+        } else if index < self.yn_offset() {
+            DiscreteAction::Action(Action::Executing(NoRefChoice::Opponent(
+                index - self.opponent_offset(),
+            )))
+        } else if index < self.size() {
+            DiscreteAction::Action(Action::Executing(NoRefChoice::Yn(
+                index == self.yn_offset(),
+            )))
+        } else {
+            return None;
+        };
+        Some(action)
+    }
+
+    /// Converts a complete action into the indices that make it,
+    /// given the current state of `game`.
+    pub fn encode(&self, action: &Action, game: &OuterGame) -> Option<Vec<usize>> {
+        match (action, game.next_action_type()) {
+            (Action::Executing(NoRefChoice::Card(names)), ObsType::Executing(obs)) => {
+                let (_, max_num) = obs.state.card_num_bounds()?;
+                self.encode_picks(names, max_num)
+            }
+            _ => Some(vec![self.to_index(&DiscreteAction::Action(action.clone()))?]),
+        }
+    }
+
+    // the picks of a card choice of at most `max_num` cards, ended by done
+    // unless the last pick ends it
+    fn encode_picks(&self, names: &[String], max_num: usize) -> Option<Vec<usize>> {
+        let mut indices = names
+            .iter()
+            .map(|name| Some(self.pick_offset() + self.card_index(name)?))
+            .collect::<Option<Vec<_>>>()?;
+        if names.len() < max_num || names.is_empty() {
+            indices.push(self.done_index());
+        }
+        Some(indices)
+    }
+
+    /// Marks the legal indices in the current state of `game`,
+    /// with `picked` being the cards picked so far in a card choice.
+    pub fn mask(&self, game: &OuterGame, picked: &[String]) -> Vec<bool> {
+        let mut mask = vec![false; self.size()];
+        if let ObsType::Executing(obs) = game.next_action_type() {
+            if let (Some((min_num, max_num)), Choose::Card { from, .. }) =
+                (obs.state.card_num_bounds(), &obs.state)
+            {
+                if picked.len() < max_num {
+                    for card in from.iter() {
+                        if !picked.iter().any(|name| name == card.name()) {
+                            if let Some(i) = self.card_index(card.name()) {
+                                mask[self.pick_offset() + i] = true;
+                            }
+                        }
+                    }
+                }
+                mask[self.done_index()] = picked.len() >= min_num;
+                return mask;
+            }
+        }
+        if let LegalActions::Listed(actions) = game.legal_actions() {
+            for action in actions {
+                if let Some(i) = self.to_index(&DiscreteAction::Action(action)) {
+                    mask[i] = true;
+                }
+            }
+        }
+        mask
+    }
+}
+
+/// Turns indices into complete actions, collecting picked cards in between.
+pub struct SequentialDecoder {
+    codec: ActionCodec,
+    picked: Vec<String>,
+}
+
+impl SequentialDecoder {
+    pub fn new(codec: ActionCodec) -> SequentialDecoder {
+        SequentialDecoder {
+            codec,
+            picked: Vec::new(),
+        }
+    }
+
+    pub fn codec(&self) -> &ActionCodec {
+        &self.codec
+    }
+
+    pub fn picked(&self) -> &[String] {
+        &self.picked
+    }
+
+    pub fn mask(&self, game: &OuterGame) -> Vec<bool> {
+        self.codec.mask(game, &self.picked)
+    }
+
+    /// Forgets the cards picked so far.
+    pub fn reset(&mut self) {
+        self.picked.clear();
+    }
+
+    /// Applies the action at `index`.
+    ///
+    /// Returns the complete action to be passed to `OuterGame::step`,
+    /// or `None` if a card choice is still being made.
+    pub fn decode(&mut self, index: usize, game: &OuterGame) -> InnResult<Option<Action>> {
+        if !self.mask(game).get(index).copied().unwrap_or(false) {
+            return Err(InnovationError::InvalidAction);
+        }
+        match self
+            .codec
+            .from_index(index)
+            .expect("Index is in range, as it's legal.")
+        {
+            DiscreteAction::Action(action) => Ok(Some(action)),
+            DiscreteAction::Pick(name) => {
+                self.picked.push(name);
+                let max_num = match game.next_action_type() {
+                    ObsType::Executing(obs) => obs.state.card_num_bounds().map(|(_, max)| max),
+                    ObsType::Main => None,
+                }
+                .expect("Picking is legal only in card choices.");
+                if self.picked.len() == max_num {
+                    Ok(Some(self.finish()))
+                } else {
+                    Ok(None)
+                }
+            }
+            DiscreteAction::Done => Ok(Some(self.finish())),
+        }
+    }
+
+    fn finish(&mut self) -> Action {
+        Action::Executing(NoRefChoice::Card(std::mem::take(&mut self.picked)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{card::default_cards, game::GameConfig, player::PlayerBuilder};
+
+    #[test]
+    fn index_round_trip() {
+        let cards = default_cards::default_cards();
+        let codec = ActionCodec::new(&cards.iter().collect::<Vec<_>>());
+        assert_eq!(codec.size(), 14 + 3 * cards.len() + MAX_PLAYERS);
+        for index in 0..codec.size() {
+            let action = codec.from_index(index).unwrap();
+            assert_eq!(codec.to_index(&action), Some(index));
+        }
+        assert_eq!(codec.from_index(codec.size()), None);
+    }
+
+    #[test]
+    fn encode_zero_cards() {
+        let cards = default_cards::default_cards();
+        let codec = ActionCodec::new(&cards.iter().collect::<Vec<_>>());
+        let done = codec.done_index();
+        // choosing no card is a decision too
+        assert_eq!(codec.encode_picks(&[], 0), Some(vec![done]));
+        assert_eq!(codec.encode_picks(&[], 2), Some(vec![done]));
+        let pottery = codec.pick_offset() + codec.card_index("Pottery").unwrap();
+        assert_eq!(
+            codec.encode_picks(&["Pottery".to_owned()], 1),
+            Some(vec![pottery])
+        );
+        assert_eq!(
+            codec.encode_picks(&["Pottery".to_owned()], 2),
+            Some(vec![pottery, done])
+        );
+    }
+
+    #[test]
+    fn sequential_picks() {
+        let masonry = default_cards::masonry();
+        let archery = default_cards::archery();
+        let metalworking = default_cards::metalworking();
+        let oars = default_cards::oars();
+        let mut game = GameConfig::new(vec![&masonry, &archery, &metalworking, &oars])
+            .player(
+                0,
+                PlayerBuilder::default().board(vec![&masonry]).hand(vec![
+                    &archery,
+                    &metalworking,
+                    &oars,
+                ]),
+            )
+            .build();
+        let mut decoder = SequentialDecoder::new(ActionCodec::from_game(&game));
+        let index = |action| decoder.codec().to_index(&action).unwrap();
+        let execute = index(DiscreteAction::Action(Action::Step(NoRefStep::Execute(
+            "Masonry".to_owned(),
+        ))));
+        let pick_archery = index(DiscreteAction::Pick("Archery".to_owned()));
+        let pick_oars = index(DiscreteAction::Pick("Oars".to_owned()));
+        let done = index(DiscreteAction::Done);
+
+        let action = decoder.decode(execute, &game).unwrap().unwrap();
+        game.step(action).unwrap();
+        let mask = decoder.mask(&game);
+        assert_eq!(mask.iter().filter(|&&legal| legal).count(), 4);
+        assert!(mask[pick_archery] && mask[pick_oars] && mask[done]);
+
+        assert_eq!(
+            decoder.codec().encode(
+                &Action::Executing(NoRefChoice::Card(vec![
+                    "Archery".to_owned(),
+                    "Oars".to_owned()
+                ])),
+                &game
+            ),
+            Some(vec![pick_archery, pick_oars, done])
+        );
+        assert!(decoder.decode(pick_archery, &game).unwrap().is_none());
+        assert!(!decoder.mask(&game)[pick_archery]);
+        assert!(decoder.decode(pick_archery, &game).is_err());
+        assert!(decoder.decode(pick_oars, &game).unwrap().is_none());
+        let action = decoder.decode(done, &game).unwrap().unwrap();
+        assert_eq!(
+            action,
+            Action::Executing(NoRefChoice::Card(vec![
+                "Archery".to_owned(),
+                "Oars".to_owned()
+            ]))
+        );
+        assert!(decoder.picked().is_empty());
+        game.step(action).unwrap();
+    }
+}
@@ -10,6 +10,7 @@ pub trait Removeable<T, P> {
 
 pub trait CardSet<'a, T: 'a>: Addable<&'a T> + Removeable<&'a T, T> {
     fn to_vec(&self) -> Vec<&'a T>;
+    fn box_clone(&self) -> Box<dyn CardSet<'a, T> + 'a>;
     fn iter(&self) -> Box<dyn Iterator<Item = &'a T> + 'a> {
         Box::new(self.to_vec().into_iter())
     }
@@ -42,6 +43,7 @@ impl<'a, 'b> dyn CardSet<'a, Card> + 'b {
     }
 }
 
+#[derive(Clone)]
 pub struct VecSet<T> {
     v: Vec<T>,
 }
@@ -95,6 +97,10 @@ impl<'a, T: PartialEq> CardSet<'a, T> for VecSet<&'a T> {
     fn to_vec(&self) -> Vec<&'a T> {
         self.clone_inner()
     }
+
+    fn box_clone(&self) -> Box<dyn CardSet<'a, T> + 'a> {
+        Box::new(self.clone())
+    }
 }
 
 pub type BoxCardSet<'a> = Box<dyn CardSet<'a, Card> + 'a>;
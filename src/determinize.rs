@@ -0,0 +1,228 @@
+//! Sampling the hidden information of an observation.
+//!
+//! An observer sees the boards of all players and its own hand and score
+//! pile, but only the ages of the other cards: those in the hands and score
+//! piles of the opponents, in the main pile and among the achievements.
+//! `determinize` fills these with cards the observer hasn't seen, producing a
+//! complete game that could be the one being observed.
+
+use rand::{seq::SliceRandom, Rng};
+use strum::IntoEnumIterator;
+
+use crate::{
+    board::Board,
+    card::{Achievement, Age, Card, Color, Splay},
+    card_pile::{split_cards, CardOrder, MainCardPile},
+    error::{InnResult, InnovationError},
+    game::GameConfig,
+    observation::{ObsType, Observation, SingleAchievementView},
+    player::PlayerBuilder,
+};
+
+/// Samples a game consistent with `obs`, in the main phase of the observed turn.
+///
+/// `cards` is the card list of the game, or another instance of it, as cards
+/// are matched by name. The hidden cards are drawn uniformly at random from
+/// the cards not seen by the observer, according to their ages, and the
+/// draw piles are shuffled.
+///
+/// Returns `InnovationError::InvalidAction` if `obs` is of the execution of
+/// a dogma, which isn't sampled, and `InnovationError::CardNotFound` if `obs`
+/// can't be made of `cards`, i.e. some seen card is not in `cards`, or the
+/// unseen cards of some age are more or fewer than the hidden ones.
+///
+/// The progress of special achievements within the turn is not observed,
+/// so it starts over in the sampled game.
+pub fn determinize<'c, R: Rng + ?Sized>(
+    obs: &Observation,
+    cards: &[&'c Card],
+    rng: &mut R,
+) -> InnResult<GameConfig<'c>> {
+    if !matches!(obs.obstype, ObsType::Main) {
+        return Err(InnovationError::InvalidAction);
+    }
+    let find = |card: &Card| {
+        cards
+            .iter()
+            .find(|c| c.name() == card.name())
+            .copied()
+            .ok_or(InnovationError::CardNotFound)
+    };
+    let main = &obs.main_player;
+    let seen: Vec<&str> = main
+        .hand
+        .iter()
+        .chain(main.score.iter())
+        .copied()
+        .chain(board_cards(&main.board))
+        .chain(obs.other_players.iter().flat_map(|p| board_cards(&p.board)))
+        .map(|c| c.name())
+        .collect();
+    let mut unseen = split_cards(cards.iter().copied().filter(|c| !seen.contains(&c.name())));
+    for pile in unseen.iter_mut() {
+        pile.shuffle(rng);
+    }
+
+    let num_players = obs.num_players();
+    let mut players: Vec<PlayerBuilder<'c>> = Vec::with_capacity(num_players);
+    players.resize_with(num_players, Default::default);
+    players[obs.main_player_id] = with_board(PlayerBuilder::default(), &main.board, find)?
+        .hand(
+            main.hand
+                .iter()
+                .map(|&c| find(c))
+                .collect::<InnResult<_>>()?,
+        )
+        .score(
+            main.score
+                .iter()
+                .map(|&c| find(c))
+                .collect::<InnResult<_>>()?,
+        )
+        .achievements(achievements(&mut unseen, &main.achievements)?);
+    for (i, other) in obs.other_players.iter().enumerate() {
+        players[(obs.main_player_id + 1 + i) % num_players] =
+            with_board(PlayerBuilder::default(), &other.board, find)?
+                .hand(
+                    other
+                        .hand
+                        .iter()
+                        .map(|&age| take(&mut unseen, age))
+                        .collect::<InnResult<_>>()?,
+                )
+                .score(
+                    other
+                        .score
+                        .iter()
+                        .map(|&age| take(&mut unseen, age))
+                        .collect::<InnResult<_>>()?,
+                )
+                .achievements(achievements(&mut unseen, &other.achievements)?);
+    }
+
+    let available = achievements(&mut unseen, &obs.achievements)?;
+    let mut deck = Vec::new();
+    for (age, &num) in (1..=10).zip(obs.main_pile.iter()) {
+        for _ in 0..num {
+            deck.push(take(&mut unseen, age)?);
+        }
+    }
+    if unseen.iter().any(|pile| !pile.is_empty()) {
+        return Err(InnovationError::CardNotFound);
+    }
+
+    Ok(GameConfig::new(cards.to_vec())
+        .main_pile(
+            MainCardPile::builder()
+                .draw_deck(deck)
+                .achievements(available)
+                .build(),
+        )
+        .players(players)
+        .first_player(obs.turn.current_player())
+        .second_step(obs.turn.is_second_step()))
+}
+
+fn board_cards<'a, 'c>(board: &'a Board<'c>) -> impl Iterator<Item = &'c Card> + 'a {
+    Color::iter().flat_map(move |color| board.get_stack(color).iter())
+}
+
+fn with_board<'c>(
+    mut builder: PlayerBuilder<'c>,
+    board: &Board,
+    find: impl Fn(&Card) -> InnResult<&'c Card>,
+) -> InnResult<PlayerBuilder<'c>> {
+    for color in Color::iter() {
+        let stack = board.get_stack(color);
+        builder = builder.board(stack.iter().map(&find).collect::<InnResult<_>>()?);
+        if stack.splay_direction() != Splay::NoSplay {
+            builder = builder.splay(color, stack.splay_direction());
+        }
+    }
+    Ok(builder)
+}
+
+fn take<'c>(unseen: &mut CardOrder<'c>, age: Age) -> InnResult<&'c Card> {
+    unseen[age as usize - 1]
+        .pop()
+        .ok_or(InnovationError::CardNotFound)
+}
+
+fn achievements<'c>(
+    unseen: &mut CardOrder<'c>,
+    views: &[SingleAchievementView],
+) -> InnResult<Vec<Achievement<'c>>> {
+    views
+        .iter()
+        .map(|view| match view {
+            SingleAchievementView::Normal(age) => Ok(Achievement::Normal(take(unseen, *age)?)),
+            SingleAchievementView::Special(special) => Ok(Achievement::Special(*special)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::default_cards;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn sampled_game_is_consistent() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut game = GameConfig::standard(cards.clone(), 3)
+            .default_players(3)
+            .build();
+        game.start().unwrap();
+        // play until player 1 has two top cards, in the main phase
+        while game.players().player_at(1).board().top_cards().len() < 2
+            || !matches!(game.next_action_type(), ObsType::Main)
+        {
+            let action = game.legal_actions().choose(&mut rng).unwrap();
+            game.step(action).unwrap();
+        }
+        let obs = game.observe(1);
+
+        // another instance of the card list
+        let own_cards = default_cards::default_cards();
+        let own_cards: Vec<_> = own_cards.iter().collect();
+        for _ in 0..5 {
+            let sampled = determinize(&obs, &own_cards, &mut rng).unwrap().build();
+            let sampled_obs = sampled.observe(1);
+            assert_eq!(sampled.acting_player(), game.acting_player());
+            assert_eq!(sampled_obs.main_player.hand, obs.main_player.hand);
+            assert_eq!(sampled_obs.main_player.score, obs.main_player.score);
+            assert_eq!(
+                sampled_obs.main_player.board.top_cards(),
+                obs.main_player.board.top_cards()
+            );
+            for (sampled, other) in sampled_obs.other_players.iter().zip(&obs.other_players) {
+                let mut hand = sampled.hand.clone();
+                let mut expected = other.hand.clone();
+                hand.sort();
+                expected.sort();
+                assert_eq!(hand, expected);
+                assert_eq!(sampled.board.top_cards(), other.board.top_cards());
+            }
+            assert_eq!(sampled_obs.main_pile, obs.main_pile);
+            assert_eq!(sampled_obs.achievements.len(), obs.achievements.len());
+        }
+
+        // the card list lacks some unseen card
+        assert!(determinize(&obs, &own_cards[1..], &mut rng).is_err());
+        drop(obs);
+
+        // nor during an execution
+        while matches!(game.next_action_type(), ObsType::Main) {
+            let action = game.legal_actions().choose(&mut rng).unwrap();
+            game.step(action).unwrap();
+        }
+        let obs = game.observe(game.acting_player());
+        assert!(matches!(
+            determinize(&obs, &own_cards, &mut rng),
+            Err(InnovationError::InvalidAction)
+        ));
+    }
+}
@@ -0,0 +1,312 @@
+//! Fixed-size numeric encoding of `Observation`s for learning agents.
+//!
+//! The layout depends only on the card list. With `n` cards, it is made of
+//! `MAX_PLAYERS` player sections, the observer's private section and the
+//! shared section, in this order.
+//!
+//! Player sections start from the observer and follow the playing order.
+//! Sections of missing players are all zero.
+//!
+//! | player section entry       | size     |
+//! |----------------------------|----------|
+//! | present flag               | 1        |
+//! | cards on board             | `n`      |
+//! | stack size of each color   | 5        |
+//! | splay of each color        | 5 × 4    |
+//! | icon count                 | 6        |
+//! | hand size of each age      | 10       |
+//! | score pile size of each age| 10       |
+//! | total score                | 1        |
+//! | normal achievements by age | 10       |
+//! | special achievements       | 5        |
+//!
+//! | observer's private section | size     |
+//! |----------------------------|----------|
+//! | cards in hand              | `n`      |
+//! | cards in score pile        | `n`      |
+//!
+//! | shared section             | size     |
+//! |----------------------------|----------|
+//! | main pile size of each age | 10       |
+//! | current player             | `MAX_PLAYERS` |
+//! | is the second step         | 1        |
+//! | acting player              | `MAX_PLAYERS` |
+//! | pending choice type (main, card, opponent, yes or no) | 4 |
+//! | min and max number of cards to choose | 2 |
+//! | cards to choose from       | `n`      |
+//! | card being executed        | `n`      |
+//!
+//! Cards, colors, splays, icons and special achievements are ordered by
+//! the card list and the declaration order of their enums respectively.
+//! Players in the shared section are counted from the observer.
+//! One-hot and multi-hot entries are 0 or 1; other entries are counts,
+//! saturating at `u8::MAX`.
+
+use std::collections::HashMap;
+
+use strum::IntoEnumIterator;
+
+use crate::{
+    board::Board,
+    card::{Age, Card, Color, Icon, SpecialAchievement, Splay},
+    codec::MAX_PLAYERS,
+    game::PlayerId,
+    observation::{ObsType, Observation, SingleAchievementView},
+    state::Choose,
+};
+
+const NUM_AGES: usize = 10;
+const NUM_COLORS: usize = 5;
+const NUM_SPLAYS: usize = 4;
+const NUM_ICONS: usize = 6;
+const NUM_SPECIAL_ACHIEVEMENTS: usize = 5;
+const NUM_CHOICE_TYPES: usize = 4;
+
+pub struct ObservationEncoder {
+    card_indices: HashMap<String, usize>,
+}
+
+impl ObservationEncoder {
+    pub fn new(cards: &[&Card]) -> ObservationEncoder {
+        ObservationEncoder {
+            card_indices: cards
+                .iter()
+                .enumerate()
+                .map(|(i, c)| (c.name().to_owned(), i))
+                .collect(),
+        }
+    }
+
+    fn num_cards(&self) -> usize {
+        self.card_indices.len()
+    }
+
+    fn player_section_size(&self) -> usize {
+        1 + self.num_cards()
+            + NUM_COLORS
+            + NUM_COLORS * NUM_SPLAYS
+            + NUM_ICONS
+            + NUM_AGES * 2
+            + 1
+            + NUM_AGES
+            + NUM_SPECIAL_ACHIEVEMENTS
+    }
+
+    /// The length of every encoded observation.
+    pub fn size(&self) -> usize {
+        MAX_PLAYERS * self.player_section_size()
+            + 2 * self.num_cards()
+            + NUM_AGES
+            + 2 * MAX_PLAYERS
+            + 1
+            + NUM_CHOICE_TYPES
+            + 2
+            + 2 * self.num_cards()
+    }
+
+    pub fn encode(&self, obs: &Observation) -> Vec<f32> {
+        self.encode_u8(obs).into_iter().map(f32::from).collect()
+    }
+
+    pub fn encode_u8(&self, obs: &Observation) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(self.size());
+        let main = &obs.main_player;
+        self.write_player(
+            &mut buf,
+            &main.board,
+            main.hand.iter().map(|c| c.age()),
+            main.score.iter().map(|c| c.age()),
+            &main.achievements,
+        );
+        for other in obs.other_players.iter().take(MAX_PLAYERS - 1) {
+            self.write_player(
+                &mut buf,
+                &other.board,
+                other.hand.iter().copied(),
+                other.score.iter().copied(),
+                &other.achievements,
+            );
+        }
+        let num_players = obs.other_players.len() + 1;
+        for _ in num_players..MAX_PLAYERS {
+            buf.extend(std::iter::repeat_n(0, self.player_section_size()));
+        }
+
+        self.write_cards(&mut buf, main.hand.iter().copied());
+        self.write_cards(&mut buf, main.score.iter().copied());
+
+        buf.extend(obs.main_pile.iter().map(|&n| saturate(n)));
+        let relative = |id: PlayerId| (id + num_players - obs.main_player_id) % num_players;
+        write_one_hot(
+            &mut buf,
+            MAX_PLAYERS,
+            Some(relative(obs.turn.current_player())),
+        );
+        buf.push(obs.turn.is_second_step() as u8);
+        write_one_hot(&mut buf, MAX_PLAYERS, Some(relative(obs.acting_player)));
+        match &obs.obstype {
+            ObsType::Main => {
+                write_one_hot(&mut buf, NUM_CHOICE_TYPES, Some(0));
+                buf.extend([0, 0]);
+                self.write_cards(&mut buf, std::iter::empty());
+                self.write_cards(&mut buf, std::iter::empty());
+            }
+            ObsType::Executing(execution) => {
+                let (choice_type, bounds, from) = match &execution.state {
+                    Choose::Card { from, .. } => {
+                        (1, execution.state.card_num_bounds(), from.as_slice())
+                    }
+                    Choose::Opponent => (2, None, &[][..]),
+                    Choose::Yn => (3, None, &[][..]),
+                };
+                write_one_hot(&mut buf, NUM_CHOICE_TYPES, Some(choice_type));
+                let (min_num, max_num) = bounds.unwrap_or((0, 0));
+                buf.extend([saturate(min_num), saturate(max_num)]);
+                self.write_cards(&mut buf, from.iter().copied());
+                self.write_cards(&mut buf, execution.card.into_iter());
+            }
+        }
+        debug_assert_eq!(buf.len(), self.size());
+        buf
+    }
+
+    fn write_player(
+        &self,
+        buf: &mut Vec<u8>,
+        board: &Board,
+        hand: impl Iterator<Item = Age>,
+        score: impl Iterator<Item = Age>,
+        achievements: &[SingleAchievementView],
+    ) {
+        buf.push(1);
+        self.write_cards(
+            buf,
+            Color::iter().flat_map(|color| board.get_stack(color).iter()),
+        );
+        for color in Color::iter() {
+            buf.push(saturate(board.get_stack(color).len()));
+        }
+        for color in Color::iter() {
+            let splay = board.get_stack(color).splay_direction();
+            write_one_hot(buf, NUM_SPLAYS, Splay::iter().position(|s| s == splay));
+        }
+        let icons = board.regular_icon_count();
+        for icon in Icon::iter() {
+            buf.push(saturate(icons[&icon]));
+        }
+        let mut total_score = 0;
+        write_age_counts(buf, hand);
+        write_age_counts(buf, score.inspect(|&age| total_score += age as usize));
+        buf.push(saturate(total_score));
+        write_age_counts(
+            buf,
+            achievements.iter().filter_map(|a| match a {
+                SingleAchievementView::Normal(age) => Some(*age),
+                SingleAchievementView::Special(_) => None,
+            }),
+        );
+        for special in SpecialAchievement::iter() {
+            buf.push(
+                achievements
+                    .iter()
+                    .any(|a| matches!(a, SingleAchievementView::Special(s) if *s == special))
+                    as u8,
+            );
+        }
+    }
+
+    fn write_cards<'c>(&self, buf: &mut Vec<u8>, cards: impl Iterator<Item = &'c Card>) {
+        let start = buf.len();
+        buf.extend(std::iter::repeat_n(0, self.num_cards()));
+        for card in cards {
+            let index = self.card_indices[card.name()];
+            buf[start + index] = 1;
+        }
+    }
+}
+
+fn saturate(n: usize) -> u8 {
+    n.min(u8::MAX as usize) as u8
+}
+
+fn write_one_hot(buf: &mut Vec<u8>, size: usize, index: Option<usize>) {
+    let start = buf.len();
+    buf.extend(std::iter::repeat_n(0, size));
+    if let Some(index) = index {
+        buf[start + index] = 1;
+    }
+}
+
+fn write_age_counts(buf: &mut Vec<u8>, ages: impl Iterator<Item = Age>) {
+    let mut counts = [0usize; NUM_AGES];
+    for age in ages {
+        counts[age as usize - 1] += 1;
+    }
+    buf.extend(counts.iter().map(|&n| saturate(n)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{Action, NoRefStep},
+        card::default_cards,
+        card_pile::MainCardPile,
+        game::GameConfig,
+        player::PlayerBuilder,
+    };
+
+    #[test]
+    fn encode_observation() {
+        let archery = default_cards::archery();
+        let pottery = default_cards::pottery();
+        let agriculture = default_cards::agriculture();
+        let optics = default_cards::optics();
+        let cards = vec![&archery, &pottery, &agriculture, &optics];
+        let encoder = ObservationEncoder::new(&cards);
+        let mut game = GameConfig::new(cards)
+            .main_pile(MainCardPile::builder().draw_deck(vec![&pottery]).build())
+            .players(vec![
+                PlayerBuilder::default()
+                    .board(vec![&archery])
+                    .score(vec![&optics]),
+                PlayerBuilder::default().hand(vec![&agriculture]),
+            ])
+            .build();
+        let player = game
+            .step(Action::Step(NoRefStep::Execute("Archery".to_owned())))
+            .unwrap()
+            .as_normal()
+            .unwrap();
+        let encoded = encoder.encode_u8(&game.observe(player));
+        assert_eq!(encoded.len(), encoder.size());
+        let section = encoder.player_section_size();
+
+        // the observer, player 1, has no card on board and two cards in hand
+        assert_eq!(encoded[0], 1);
+        assert_eq!(&encoded[1..5], &[0, 0, 0, 0]);
+        let hand_by_age = 1 + 4 + 5 + 20 + 6;
+        assert_eq!(encoded[hand_by_age], 2);
+        // the next player, player 0, has Archery on board and a 3 in score pile
+        assert_eq!(encoded[section], 1);
+        assert_eq!(&encoded[section + 1..section + 5], &[1, 0, 0, 0]);
+        assert_eq!(encoded[section + hand_by_age + 10 + 2], 1);
+        assert_eq!(encoded[section + hand_by_age + 20], 3);
+        // missing players
+        assert!(encoded[2 * section..4 * section].iter().all(|&x| x == 0));
+
+        let private = 4 * section;
+        assert_eq!(&encoded[private..private + 4], &[0, 1, 1, 0]);
+        assert_eq!(&encoded[private + 4..private + 8], &[0, 0, 0, 0]);
+
+        let shared = private + 8;
+        assert_eq!(&encoded[shared..shared + 10], &[0; 10]);
+        // player 0 is the current player, and player 1 is acting, choosing a card
+        assert_eq!(&encoded[shared + 10..shared + 14], &[0, 1, 0, 0]);
+        assert_eq!(&encoded[shared + 15..shared + 19], &[1, 0, 0, 0]);
+        assert_eq!(&encoded[shared + 19..shared + 23], &[0, 1, 0, 0]);
+        assert_eq!(&encoded[shared + 23..shared + 25], &[1, 1]);
+        assert_eq!(&encoded[shared + 25..shared + 29], &[0, 1, 1, 0]);
+        assert_eq!(&encoded[shared + 29..shared + 33], &[1, 0, 0, 0]);
+    }
+}
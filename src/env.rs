@@ -0,0 +1,268 @@
+use ouroboros::self_referencing;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::{
+    action::{Action, LegalActions},
+    card::Card,
+    error::{InnResult, InnovationError},
+    game::{GameConfig, OuterGame, PlayerId},
+    observation::{GameState, Observation},
+};
+
+/// How players are rewarded after each step.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reward {
+    /// 1 for the winners and -1 for the others when the game ends, 0 before that.
+    WinLoss,
+    /// The change of the difference between one's total score and the
+    /// highest total score of the opponents.
+    ///
+    /// The rewards of an episode sum up to the final difference.
+    ScoreDifference,
+}
+
+#[derive(Clone, Debug)]
+pub struct EnvConfig {
+    num_players: usize,
+    reward: Reward,
+    seed: u64,
+}
+
+impl EnvConfig {
+    pub fn new(num_players: usize) -> EnvConfig {
+        EnvConfig {
+            num_players,
+            reward: Reward::WinLoss,
+            seed: 0,
+        }
+    }
+
+    pub fn reward(mut self, reward: Reward) -> EnvConfig {
+        self.reward = reward;
+        self
+    }
+
+    /// Seeds the shuffling of the decks of all episodes.
+    pub fn seed(mut self, seed: u64) -> EnvConfig {
+        self.seed = seed;
+        self
+    }
+}
+
+/// A game environment that plays episodes with a fixed card list.
+#[self_referencing]
+pub struct Env {
+    cards: Vec<Card>,
+    #[borrows(cards)]
+    #[not_covariant]
+    current_game: Option<OuterGame<'this>>,
+    config: EnvConfig,
+    rng: StdRng,
+    score_differences: Vec<isize>,
+}
+
+impl Env {
+    pub fn create(cards: Vec<Card>, config: EnvConfig) -> InnResult<Env> {
+        if !(2..=4).contains(&config.num_players) {
+            return Err(InnovationError::WrongPlayerNum);
+        }
+        let rng = StdRng::seed_from_u64(config.seed);
+        let num_players = config.num_players;
+        Ok(EnvBuilder {
+            cards,
+            current_game_builder: |_| None,
+            config,
+            rng,
+            score_differences: vec![0; num_players],
+        }
+        .build())
+    }
+
+    pub fn num_players(&self) -> usize {
+        self.borrow_config().num_players
+    }
+
+    pub fn cards(&self) -> &[Card] {
+        self.borrow_cards()
+    }
+
+    /// Starts a new episode, returning the player to act and its observation.
+    pub fn reset(&mut self) -> InnResult<(PlayerId, Observation<'_>)> {
+        let player = self.with_mut(|fields| {
+            let cards: Vec<&Card> = fields.cards.iter().collect();
+            let game = fields.current_game.insert(
+                GameConfig::standard(cards, fields.rng.gen())
+                    .default_players(fields.config.num_players)
+                    .build(),
+            );
+            fields.score_differences.fill(0);
+            match game.start()? {
+                GameState::Normal(player) => Ok(player),
+                GameState::End(_) => panic!("Game should not end when starting"),
+            }
+        })?;
+        Ok((player, self.observe(player)))
+    }
+
+    /// Applies `action` of the acting player.
+    ///
+    /// Returns the player to act next with its observation, the rewards of
+    /// all players and whether the episode is done. When it's done, the
+    /// returned player is the one who acted last.
+    #[allow(clippy::type_complexity)]
+    pub fn step(
+        &mut self,
+        action: Action,
+    ) -> InnResult<(PlayerId, Observation<'_>, Vec<f32>, bool)> {
+        let (player, winners) = self.with_current_game_mut(|game| {
+            let game = game.as_mut().expect("reset() should be called first");
+            let actor = game.acting_player();
+            let winners = match game.step(action)? {
+                GameState::Normal(_) => None,
+                GameState::End(end) => Some(end.winners),
+            };
+            // an ended game waits for its turn player, who may not have acted
+            let player = match winners {
+                Some(_) => actor,
+                None => game.acting_player(),
+            };
+            Ok((player, winners))
+        })?;
+        let rewards = self.rewards(winners.as_deref());
+        Ok((player, self.observe(player), rewards, winners.is_some()))
+    }
+
+    fn rewards(&mut self, winners: Option<&[PlayerId]>) -> Vec<f32> {
+        let num_players = self.num_players();
+        match self.borrow_config().reward {
+            Reward::WinLoss => (0..num_players)
+                .map(|id| match winners {
+                    Some(winners) if winners.contains(&id) => 1.0,
+                    Some(_) => -1.0,
+                    None => 0.0,
+                })
+                .collect(),
+            Reward::ScoreDifference => {
+                let scores: Vec<isize> = self.with_game(|game| {
+                    game.players()
+                        .players_from(0)
+                        .map(|p| p.total_score() as isize)
+                        .collect()
+                });
+                self.with_score_differences_mut(|last| {
+                    (0..num_players)
+                        .map(|id| {
+                            let best_opponent = (0..num_players)
+                                .filter(|&other| other != id)
+                                .map(|other| scores[other])
+                                .max()
+                                .unwrap_or(0);
+                            let difference = scores[id] - best_opponent;
+                            let reward = (difference - last[id]) as f32;
+                            last[id] = difference;
+                            reward
+                        })
+                        .collect()
+                })
+            }
+        }
+    }
+
+    pub fn observe(&self, player: PlayerId) -> Observation<'_> {
+        self.with_game(|game| game.observe(player))
+    }
+
+    pub fn legal_actions(&self) -> LegalActions {
+        self.with_game(|game| game.legal_actions())
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.with_current_game(|game| game.as_ref().is_none_or(|game| game.is_over()))
+    }
+
+    /// Gives access to the game of the current episode.
+    ///
+    /// Panics if `reset()` has never been called.
+    pub fn with_game<'a, R>(&'a self, f: impl for<'c> FnOnce(&'a OuterGame<'c>) -> R) -> R {
+        self.with_current_game(|game| f(game.as_ref().expect("reset() should be called first")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{action::NoRefStep, card::default_cards::default_cards};
+
+    #[test]
+    fn random_episodes() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for num_players in 2..=4 {
+            for reward in [Reward::WinLoss, Reward::ScoreDifference] {
+                let mut env = Env::create(
+                    default_cards(),
+                    EnvConfig::new(num_players).reward(reward).seed(7),
+                )
+                .unwrap();
+                for _ in 0..3 {
+                    env.reset().unwrap();
+                    let mut total = vec![0.0; num_players];
+                    let mut done = false;
+                    while !done {
+                        let action = env.legal_actions().choose(&mut rng).unwrap();
+                        let (_, _, rewards, d) = env.step(action).unwrap();
+                        for (t, r) in total.iter_mut().zip(rewards) {
+                            *t += r;
+                        }
+                        done = d;
+                    }
+                    assert!(env.is_done());
+                    assert!(env.step(Action::Step(NoRefStep::Draw)).is_err());
+                    if reward == Reward::WinLoss {
+                        assert!(total.iter().all(|&r| r == 1.0 || r == -1.0));
+                        assert!(total.contains(&1.0));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn done_by_last_actor() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut env = Env::create(default_cards(), EnvConfig::new(2)).unwrap();
+        // until a game is ended by a choice of the player not in turn
+        let mut off_turn = false;
+        while !off_turn {
+            env.reset().unwrap();
+            let mut done = false;
+            while !done {
+                let actor = env.with_game(|game| game.acting_player());
+                let action = env.legal_actions().choose(&mut rng).unwrap();
+                let (player, _, _, d) = env.step(action).unwrap();
+                done = d;
+                if done {
+                    assert_eq!(player, actor);
+                    off_turn = env.with_game(|game| game.acting_player()) != actor;
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_reset() {
+        let mut env1 = Env::create(default_cards(), EnvConfig::new(3).seed(3)).unwrap();
+        let mut env2 = Env::create(default_cards(), EnvConfig::new(3).seed(3)).unwrap();
+        let hand = |env: &mut Env| -> Vec<String> {
+            let (_, obs) = env.reset().unwrap();
+            obs.main_player
+                .hand
+                .iter()
+                .map(|c| c.name().to_owned())
+                .collect()
+        };
+        for _ in 0..3 {
+            assert_eq!(hand(&mut env1), hand(&mut env2));
+        }
+        assert!(Env::create(default_cards(), EnvConfig::new(5)).is_err());
+    }
+}
@@ -53,6 +53,20 @@ pub enum InnovationError {
         current_player: Option<PlayerId>,
         situation: WinningSituation,
     },
+    /// The save data is not of a supported version.
+    UnsupportedSaveVersion(u32),
+    /// The save data is inconsistent, e.g. the states of observers are malformed.
+    InvalidSave,
+    /// The log file is not of a supported version.
+    UnsupportedLogVersion(u32),
+    /// The log file is inconsistent, e.g. a game has not ten piles of cards.
+    InvalidLog,
+    /// An execution stopped at a choice which is not made yet.
+    ///
+    /// Only seen by code running inside executions; see `card::dogma_fn::Context`.
+    Suspend,
+    /// A replayed game notified another item than the recorded one at this index.
+    ReplayMismatch(usize),
 }
 
 impl InnovationError {
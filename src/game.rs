@@ -1,24 +1,26 @@
 use std::rc::Rc;
-use std::{cell::RefCell, iter::repeat_with};
+use std::{
+    cell::{Cell, RefCell},
+    iter::repeat_with,
+};
 
-use generator::{done, Gn};
 use ouroboros::self_referencing;
+use rand::{rngs::StdRng, thread_rng, Rng, RngCore, SeedableRng};
+use serde_json::Value;
 use strum::IntoEnumIterator;
 
 use crate::{
-    action::{Action, NoRefChoice, NoRefStep, RefAction, RefChoice, RefStep},
+    action::{Action, LegalActions, NoRefStep, RefStep},
     auto_achieve::{AchievementManager, WinByAchievementChecker},
-    card::{
-        flow::FlowState, mk_execution, Achievement, Age, Card, Color, Dogma, SpecialAchievement,
-        Splay,
-    },
-    card_pile::MainCardPile,
+    card::{Achievement, Age, Card, Color, Context, Dogma, SpecialAchievement, Splay},
+    card_pile::{MainCardPile, MainCardPileBuilder},
     containers::{Addable, BoxCardSet, CardSet, Removeable, VecSet},
     error::{InnResult, InnovationError, WinningSituation},
     logger::{Item, Observer, Operation, SimpleOp, Subject},
     observation::{EndObservation, GameState, ObsType, Observation, SingleAchievementView},
     player::{Player, PlayerBuilder},
-    state::{ActionCheckResult, Choose, State},
+    save::{ExecutionData, PlayersData, SaveData, SAVE_VERSION},
+    state::{Execution, ExecutionState, Root, State},
     structure::{
         AddToGame, Board, Hand, MainCardPile as MainCardPile_, Place, RemoveFromGame, Score,
         TestRemoveFromGame,
@@ -30,11 +32,21 @@ use crate::{
 pub type RcCell<T> = Rc<RefCell<T>>;
 pub type PlayerId = usize;
 
+/// Checks if a player whose highest top card is of `highest_age` and whose
+/// score is `score` may claim the normal achievement of `age`, which is
+/// `available` if no one has claimed it.
+pub fn can_achieve(age: Age, highest_age: Age, score: usize, available: bool) -> bool {
+    highest_age >= age && score >= 5 * (age as usize) && available
+}
+
 pub struct Players<'c> {
     cards: Vec<&'c Card>,
     logger: Subject<'c>,
     main_card_pile: RcCell<MainCardPile<'c>>,
     players: Vec<Player<'c>>,
+    seed: Option<u64>,
+    // the number of cards moved out of hidden places
+    num_revealed: Cell<usize>,
 }
 
 macro_rules! impl_simple_op {
@@ -70,6 +82,8 @@ impl<'c> Players<'c> {
             logger: Subject::new(),
             main_card_pile: Rc::new(RefCell::new(MainCardPile::empty())),
             players: vec![],
+            seed: None,
+            num_revealed: Cell::new(0),
         }
     }
 
@@ -116,9 +130,57 @@ impl<'c> Players<'c> {
                 .enumerate()
                 .map(|(id, pb)| pb.build(id))
                 .collect(),
+            seed: None,
+            num_revealed: Cell::new(0),
         }
     }
 
+    pub fn cards(&self) -> &[&'c Card] {
+        &self.cards
+    }
+
+    /// An independent copy of the players and the main pile.
+    ///
+    /// Only the permanent internal observers are copied; see `Subject::fork`.
+    pub fn fork(&self) -> Players<'c> {
+        Players {
+            cards: self.cards.clone(),
+            logger: self.logger.fork(),
+            main_card_pile: Rc::new(RefCell::new(self.main_card_pile.borrow().clone())),
+            players: self.players.clone(),
+            seed: self.seed,
+            num_revealed: self.num_revealed.clone(),
+        }
+    }
+
+    /// The seed which the main card pile was shuffled with, if known;
+    /// see `GameConfig::seed`.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// The number of cards moved out of hidden places, i.e. out of the main
+    /// pile, hands and score piles.
+    pub fn num_revealed(&self) -> usize {
+        self.num_revealed.get()
+    }
+
+    fn reveal(&self, from: Place, num_cards: usize) {
+        if from.is_hidden() {
+            self.num_revealed.set(self.num_revealed.get() + num_cards);
+        }
+    }
+
+    /// The states of the permanent internal observers; see `Subject::save`.
+    pub fn save_observers(&self) -> Vec<Value> {
+        self.logger.save()
+    }
+
+    /// Sets the permanent internal observers back to saved states.
+    pub fn load_observers(&self, states: &[Value]) -> InnResult<()> {
+        self.logger.load(states)
+    }
+
     pub fn find_card(&self, name: &str) -> &'c Card {
         self.cards
             .iter()
@@ -287,6 +349,16 @@ impl<'c> Players<'c> {
         self.main_card_pile.borrow().has_achievement(view)
     }
 
+    /// Checks if `player` may claim the normal achievement of `age` as a step.
+    pub fn can_achieve(&self, player: &Player<'c>, age: Age) -> bool {
+        can_achieve(
+            age,
+            player.age(),
+            player.total_score(),
+            self.has_achievement(&SingleAchievementView::Normal(age)),
+        )
+    }
+
     pub fn try_achieve<'g>(
         &'g self,
         player: &'g Player<'c>,
@@ -319,43 +391,34 @@ impl<'c> Players<'c> {
         &'g self,
         player: &'g Player<'c>,
         card: &'c Card,
-    ) -> FlowState<'c, 'g> {
-        // this used an extra layer of generator
-        // may eliminate this by passing in ctx?
-        Gn::new_scoped_local(move |mut s| {
+        ctx: &mut Context<'c, 'g>,
+    ) -> InnResult<()> {
+        ctx.executing(card, |ctx| {
             for dogma in card.dogmas() {
                 if let Dogma::Share(flow) = dogma {
-                    let mut gen = flow(player, self);
-
-                    // s.yield_from(gen); but with or(card)
-                    let mut state = gen.resume();
-                    while let Some(st) = state {
-                        let a = s
-                            .yield_(
-                                st.map(|st| st.or(card))
-                                    .map_err(|e| e.or_set_current_player(player.id())),
-                            )
-                            .expect("Generator got None");
-                        gen.set_para(a);
-                        state = gen.resume();
-                    }
+                    flow(player, self, ctx).map_err(|e| e.or_set_current_player(player.id()))?;
                 }
             }
-            done!()
+            Ok(())
         })
     }
 
-    pub fn execute<'g>(&'g self, player: &'g Player<'c>, card: &'c Card) -> FlowState<'c, 'g> {
-        Gn::new_scoped_local(move |mut s| {
-            let id = player.id();
-            let main_icon = card.main_icon();
-            let main_icon_count = player.board().icon_count()[&main_icon];
-            // check eligible players before actual execution
-            let players_from_next = self.players_from(id + 1);
-            let can_be_shared: Vec<_> = players_from_next
-                .map(|p| p.board().icon_count()[&main_icon] >= main_icon_count)
-                .collect();
-            // execution
+    pub fn execute<'g>(
+        &'g self,
+        player: &'g Player<'c>,
+        card: &'c Card,
+        ctx: &mut Context<'c, 'g>,
+    ) -> InnResult<()> {
+        let id = player.id();
+        let main_icon = card.main_icon();
+        let main_icon_count = player.board().icon_count()[&main_icon];
+        // check eligible players before actual execution
+        let players_from_next = self.players_from(id + 1);
+        let can_be_shared: Vec<_> = players_from_next
+            .map(|p| p.board().icon_count()[&main_icon] >= main_icon_count)
+            .collect();
+        // execution
+        ctx.executing(card, |ctx| {
             for dogma in card.dogmas() {
                 match dogma {
                     Dogma::Share(flow) => {
@@ -365,20 +428,8 @@ impl<'c> Players<'c> {
                             .zip(can_be_shared.iter())
                             .filter_map(|(p, mask)| mask.then_some(p))
                         {
-                            let mut gen = flow(player, self);
-
-                            // s.yield_from(gen); but with or(card)
-                            let mut state = gen.resume();
-                            while let Some(st) = state {
-                                let a = s
-                                    .yield_(
-                                        st.map(|st| st.or(card))
-                                            .map_err(|e| e.or_set_current_player(player.id())),
-                                    )
-                                    .expect("Generator got None");
-                                gen.set_para(a);
-                                state = gen.resume();
-                            }
+                            flow(player, self, ctx)
+                                .map_err(|e| e.or_set_current_player(player.id()))?;
                         }
                     }
                     Dogma::Demand(flow) => {
@@ -389,27 +440,58 @@ impl<'c> Players<'c> {
                             .zip(can_be_shared.iter())
                             .filter_map(|(p, mask)| (!mask).then_some(p))
                         {
-                            let mut gen = flow(self.player_at(id), player, self);
-                            // s.yield_from(gen); but with or(card)
-                            let mut state = gen.resume();
-                            while let Some(st) = state {
-                                let a = s
-                                    .yield_(
-                                        st.map(|st| st.or(card))
-                                            .map_err(|e| e.or_set_current_player(player.id())),
-                                    )
-                                    .expect("Generator got None");
-                                gen.set_para(a);
-                                state = gen.resume();
-                            }
+                            flow(self.player_at(id), player, self, ctx)
+                                .map_err(|e| e.or_set_current_player(player.id()))?;
                         }
                     }
                 }
             }
-            done!()
+            Ok(())
         })
     }
 
+    /// Runs `execution` from the beginning, with the choices made so far.
+    ///
+    /// If some choices were made, the players are restored to those the
+    /// execution started from first, and the notifications delivered by the
+    /// previous runs are not delivered to external observers again.
+    ///
+    /// Returns the pending choice, or `None` if the execution has ended.
+    pub fn resume<'g>(
+        &'g self,
+        execution: &mut Execution<'c>,
+    ) -> InnResult<Option<ExecutionState<'c, 'g>>> {
+        if !execution.choices.is_empty() {
+            self.restore(&execution.players);
+        }
+        self.logger.mute(execution.delivered);
+        let start = self.logger.num_notified();
+        let mut ctx = Context::new(self, execution.choices.clone());
+        let result = match execution.root {
+            Root::Start => self.start_choice(&mut ctx),
+            Root::Execute(id, card) => self.execute(self.player_at(id), card, &mut ctx),
+        };
+        self.logger.mute(0);
+        match result {
+            Ok(()) => Ok(None),
+            Err(InnovationError::Suspend) => {
+                execution.delivered = self.logger.num_notified() - start;
+                Ok(ctx.into_pending())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets the players and the main pile back to `from`, a fork of these players.
+    fn restore(&self, from: &Players<'c>) {
+        for (player, from) in self.players.iter().zip(from.players.iter()) {
+            player.restore(from);
+        }
+        *self.main_card_pile.borrow_mut() = from.main_card_pile.borrow().clone();
+        self.num_revealed.set(from.num_revealed.get());
+        self.logger.restore(&from.logger);
+    }
+
     pub fn win<'g>(&'g self, player: &'g Player<'c>) -> InnResult<()> {
         Err(InnovationError::Win {
             current_player: None,
@@ -431,6 +513,7 @@ impl<'c> Players<'c> {
         let card = from.remove_from(self, remove_param);
         card.map(|card| {
             to.add_to(card, self, add_param);
+            self.reveal(from.pick(), 1);
             // MAYFIXED: TODO: this does not allow observers to perform operations (log)
             // log event, after actual operation, to ensure that observers act after operation
             self.logger
@@ -481,25 +564,26 @@ impl<'c> Players<'c> {
                 .expect("remove_from() should be consistent with test_remove().");
             place1.add_to(card, self, ());
         }
-        self.logger.operate(
-            Operation::Exchange(place1.into(), place2.into(), cards12, cards21),
-            self,
-        )?;
+        let (place1, place2) = (place1.into(), place2.into());
+        self.reveal(place1, cards12.len());
+        self.reveal(place2, cards21.len());
+        self.logger
+            .operate(Operation::Exchange(place1, place2, cards12, cards21), self)?;
         Ok(())
     }
 
-    pub fn start_choice<'g>(&'g self) -> FlowState<'c, 'g> {
-        mk_execution(move |ctx| {
-            for player in self.players_from(0) {
-                self.draw(player, 1)?;
-                self.draw(player, 1)?;
-            }
-            for player in self.players_from(0) {
-                let card = ctx.choose_one_card(player, player.hand().to_vec()).expect("Already checked, and all players have two cards, so they can always choose one");
-                self.meld(player, card)?;
-            }
-            Ok(())
-        })
+    pub fn start_choice<'g>(&'g self, ctx: &mut Context<'c, 'g>) -> InnResult<()> {
+        for player in self.players_from(0) {
+            self.draw(player, 1)?;
+            self.draw(player, 1)?;
+        }
+        for player in self.players_from(0) {
+            let card = ctx.choose_one_card(player, player.hand().to_vec())?.expect(
+                "Already checked, and all players have two cards, so they can always choose one",
+            );
+            self.meld(player, card)?;
+        }
+        Ok(())
     }
 
     pub fn notify(&self, item: Item<'c>) -> InnResult<()> {
@@ -515,12 +599,64 @@ pub struct OuterGame<'c> {
     #[borrows(players)]
     #[covariant]
     turn: LoggingTurn<'c, 'this>,
-    #[borrows()]
-    #[covariant]
-    state: State<'c, 'this>,
-    next_action_type: ObsType<'c>,
-    // TODO: figure out where to place this field
-    acting_player: PlayerId,
+    state: State<'c>,
+    next_action: NextAction<'c>,
+    winners: Option<Vec<PlayerId>>,
+    history: History<'c>,
+}
+
+// the player to act next, and the kind of action expected
+#[derive(Clone)]
+struct NextAction<'c> {
+    player: PlayerId,
+    obs_type: ObsType<'c>,
+}
+
+impl<'c> NextAction<'c> {
+    fn main(player: PlayerId) -> NextAction<'c> {
+        NextAction {
+            player,
+            obs_type: ObsType::Main,
+        }
+    }
+}
+
+/// Which actions can be taken back by `OuterGame::undo`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UndoPolicy {
+    /// Nothing can be undone, and no snapshots are kept.
+    #[default]
+    Disabled,
+    /// Actions can be undone back to the last one which moved a card out of
+    /// a hidden place, such as drawing or melding from a hand, so that
+    /// nobody can take back an action after seeing hidden cards.
+    KeepReveals,
+    /// Any action can be undone.
+    Enabled,
+}
+
+// the game before an action
+struct Snapshot<'c> {
+    players: Players<'c>,
+    turn: Turn,
+    state: State<'c>,
+    next_action: NextAction<'c>,
+    winners: Option<Vec<PlayerId>>,
+}
+
+// where a resumed game stops: the player to act next, or the current player
+// and the winners
+enum Resumed {
+    Normal(PlayerId),
+    End(PlayerId, Vec<PlayerId>),
+}
+
+#[derive(Default)]
+struct History<'c> {
+    policy: UndoPolicy,
+    undo: Vec<(Snapshot<'c>, Action)>,
+    // the undone actions, the last undone at the end
+    redo: Vec<Action>,
 }
 
 impl<'c> OuterGame<'c> {
@@ -536,8 +672,9 @@ impl<'c> OuterGame<'c> {
             players_ref_builder: |players| players,
             turn_builder: |players| LoggingTurn::new(turn, players),
             state: State::Main,
-            next_action_type: ObsType::Main,
-            acting_player: first_player,
+            next_action: NextAction::main(first_player),
+            winners: None,
+            history: History::default(),
         }
         .build()
     }
@@ -548,36 +685,224 @@ impl<'c> OuterGame<'c> {
         players: Vec<PlayerBuilder<'c>>,
         turn: TurnBuilder,
         subject: Subject<'c>,
+        seed: Option<u64>,
     ) -> OuterGame<'c> {
         // TODO: structure not clear
         let turn = turn.build(players.len());
         let first_player = turn.current_player();
+        let mut players = Players::from_builders(cards, main_pile, players, first_player, subject);
+        players.seed = seed;
+        OuterGame::from_parts(players, turn)
+    }
+
+    fn from_parts(players: Players<'c>, turn: Turn) -> OuterGame<'c> {
+        let current_player = turn.current_player();
         OuterGameBuilder {
-            players: Players::from_builders(
-                cards,
-                main_pile,
-                players,
-                turn.current_player(),
-                subject,
-            ),
+            players,
             players_ref_builder: |players| players,
             turn_builder: |players| LoggingTurn::new(turn, players),
             state: State::Main,
-            next_action_type: ObsType::Main,
-            acting_player: first_player,
+            next_action: NextAction::main(current_player),
+            winners: None,
+            history: History::default(),
         }
         .build()
     }
 
-    pub fn start(&mut self) -> InnResult<GameState> {
+    pub fn start(&mut self) -> InnResult<GameState<'_>> {
+        let execution = Execution::new(Root::Start, self.players().fork());
+        self.with_state_mut(|state| *state = State::Executing(Box::new(execution)));
+        self.resume_execution()
+    }
+
+    /// An independent copy of the game, which continues from the same point.
+    ///
+    /// Observers are not copied, except the permanent internal ones;
+    /// see `Players::fork`. The copy has the same `UndoPolicy`, but nothing
+    /// to undo or redo.
+    pub fn fork(&self) -> OuterGame<'c> {
+        let Snapshot {
+            players,
+            turn,
+            state,
+            next_action,
+            winners,
+        } = self.snapshot();
+        let mut game = OuterGame::from_parts(players, turn);
+        game.with_mut(|fields| {
+            *fields.state = state;
+            *fields.next_action = next_action;
+            *fields.winners = winners;
+            fields.history.policy = self.borrow_history().policy;
+        });
+        game
+    }
+
+    fn snapshot(&self) -> Snapshot<'c> {
+        Snapshot {
+            players: self.players().fork(),
+            turn: self.borrow_turn().turn().clone(),
+            state: self.borrow_state().fork(),
+            next_action: self.borrow_next_action().clone(),
+            winners: self.borrow_winners().clone(),
+        }
+    }
+
+    // sets the game back to `snapshot`, keeping the observers
+    fn restore(&mut self, snapshot: Snapshot<'c>) {
         self.with_mut(|fields| {
-            *fields.state = State::Executing((*fields.players_ref).start_choice());
+            fields.players_ref.restore(&snapshot.players);
+            fields.turn.restore(snapshot.turn);
+            *fields.state = snapshot.state;
+            *fields.next_action = snapshot.next_action;
+            *fields.winners = snapshot.winners;
         });
-        self.resume_execution()
+    }
+
+    pub fn undo_policy(&self) -> UndoPolicy {
+        self.borrow_history().policy
+    }
+
+    /// Sets which actions can be undone from now on.
+    ///
+    /// Disabling undo drops the actions to undo and redo.
+    pub fn set_undo_policy(&mut self, policy: UndoPolicy) {
+        self.with_history_mut(|history| {
+            history.policy = policy;
+            if policy == UndoPolicy::Disabled {
+                history.undo.clear();
+                history.redo.clear();
+            }
+        });
+    }
+
+    /// Whether `undo` would take back an action.
+    pub fn can_undo(&self) -> bool {
+        !self.borrow_history().undo.is_empty()
+            && (self.undo_policy() != UndoPolicy::KeepReveals || !self.revealed())
+    }
+
+    /// Whether `redo` would apply an action again.
+    pub fn can_redo(&self) -> bool {
+        !self.borrow_history().redo.is_empty()
+    }
+
+    /// Takes back the last action, which may be a choice in a dogma,
+    /// returning whether there was one to take back; see `UndoPolicy`.
+    ///
+    /// Observers are notified with `Item::Undo`. External ones still have
+    /// seen what the action revealed; a `Logger` drops its items.
+    pub fn undo(&mut self) -> bool {
+        if !self.can_undo() {
+            return false;
+        }
+        let (snapshot, action) = self.with_history_mut(|history| history.undo.pop().unwrap());
+        self.restore(snapshot);
+        self.with_history_mut(|history| history.redo.push(action));
+        // the internal observers are restored with the game, and ignore it
+        let _ = self.players().notify(Item::Undo);
+        true
+    }
+
+    /// Applies the last undone action again, returning whether there was one.
+    ///
+    /// Taking any other action drops the undone ones.
+    pub fn redo(&mut self) -> InnResult<bool> {
+        let action = match self.with_history_mut(|history| history.redo.pop()) {
+            Some(action) => action,
+            None => return Ok(false),
+        };
+        let redo = self.with_history_mut(|history| std::mem::take(&mut history.redo));
+        let result = self.step(action).map(|_| true);
+        self.with_history_mut(|history| history.redo = redo);
+        result
+    }
+
+    // whether the last action revealed cards since its snapshot
+    fn revealed(&self) -> bool {
+        self.with(|fields| match fields.history.undo.last() {
+            Some((snapshot, _)) => snapshot.players.num_revealed() != fields.players.num_revealed(),
+            None => false,
+        })
+    }
+
+    // the game before `action`, with whether the last action revealed
+    // cards, if it can be undone
+    fn history_entry(&self, action: &Action) -> Option<(Snapshot<'c>, Action, bool)> {
+        match self.undo_policy() {
+            UndoPolicy::Disabled => None,
+            _ => Some((self.snapshot(), action.clone(), self.revealed())),
+        }
+    }
+
+    // keeps the game before an action, once it's applied
+    fn push_history(&mut self, entry: Option<(Snapshot<'c>, Action, bool)>) {
+        let policy = self.undo_policy();
+        if let Some((snapshot, action, revealed)) = entry {
+            self.with_history_mut(|history| {
+                if policy == UndoPolicy::KeepReveals && revealed {
+                    history.undo.clear();
+                }
+                history.undo.push((snapshot, action));
+                history.redo.clear();
+            });
+        }
+    }
+
+    /// Saves the game, including the execution in progress if any.
+    pub fn save(&self) -> SaveData {
+        let (players, execution) = match self.borrow_state() {
+            State::Main => (self.players(), None),
+            State::Executing(execution) => {
+                (&execution.players, Some(ExecutionData::new(execution)))
+            }
+        };
+        SaveData {
+            version: SAVE_VERSION,
+            players: PlayersData::new(players),
+            turn: self.borrow_turn().turn().clone(),
+            execution,
+            winners: self.borrow_winners().clone(),
+            seed: self.players().seed(),
+        }
+    }
+
+    /// Loads a game saved by `save`, with its card list or another instance of it.
+    ///
+    /// An execution in progress is run again, up to the same choice; a recorded
+    /// choice which is not valid at its point makes the save `InvalidSave`.
+    /// Observers are not saved, so the loaded game has only the permanent
+    /// internal ones.
+    pub fn load(cards: Vec<&'c Card>, data: SaveData) -> InnResult<OuterGame<'c>> {
+        if data.version != SAVE_VERSION {
+            return Err(InnovationError::UnsupportedSaveVersion(data.version));
+        }
+        let mut players = data.players.load(cards)?;
+        players.seed = data.seed;
+        if data.turn.num_players() != players.num_players() {
+            return Err(InnovationError::InvalidSave);
+        }
+        let mut game = OuterGame::from_parts(players, data.turn);
+        if let Some(execution) = data.execution {
+            let execution = execution.load(game.players().fork())?;
+            game.with_state_mut(|state| *state = State::Executing(Box::new(execution)));
+            // a recorded choice which is not valid makes the save invalid
+            game.resume_execution().map_err(|e| match e {
+                InnovationError::InvalidAction => InnovationError::InvalidSave,
+                e => e,
+            })?;
+        }
+        if let Some(winners) = data.winners {
+            game.with_winners_mut(|field| *field = Some(winners));
+        }
+        Ok(game)
     }
 
     fn is_available_action(&self, action: &Action) -> bool {
-        self.with(|fields| match (action, fields.next_action_type) {
+        if self.is_over() {
+            return false;
+        }
+        self.with(|fields| match (action, &fields.next_action.obs_type) {
             (Action::Step(step), ObsType::Main) => {
                 if let NoRefStep::Draw = step {
                     true
@@ -586,62 +911,56 @@ impl<'c> OuterGame<'c> {
                     let player = &players.players[fields.turn.player_id()];
                     match step {
                         NoRefStep::Meld(c) => {
-                            player.hand().to_vec().contains(&players.find_card(c))
-                        }
-                        NoRefStep::Achieve(age) => {
-                            player.age() >= *age
-                                && player.total_score() >= 5 * (*age as usize)
-                                && players.has_achievement(&SingleAchievementView::Normal(*age))
+                            player.hand().to_vec().iter().any(|card| card.name() == c)
                         }
-                        NoRefStep::Execute(c) => player.board().contains(players.find_card(c)),
+                        NoRefStep::Achieve(age) => players.can_achieve(player, *age),
+                        // only top cards can be executed, as in legal_actions
+                        NoRefStep::Execute(c) => player
+                            .board()
+                            .top_cards()
+                            .iter()
+                            .any(|card| card.name() == c),
                         _ => panic!("just checked, action can't be Draw"),
                     }
                 }
             }
-            (Action::Executing(choice), ObsType::Executing(obs)) => match (choice, &obs.state) {
-                (
-                    NoRefChoice::Card(cards),
-                    &Choose::Card {
-                        min_num,
-                        max_num,
-                        ref from,
-                    },
-                ) => {
-                    let len = cards.len();
-                    len >= min_num
-                        && match max_num {
-                            Some(max) => len <= max,
-                            None => true,
-                        }
-                        && {
-                            // performance?
-                            // check if `cards` is a subset of `from`
-                            cards
-                                .iter()
-                                .all(|name| from.iter().any(|c| c.name() == name))
-                        }
-                }
-                (NoRefChoice::Opponent(_), Choose::Opponent) => true,
-                (NoRefChoice::Yn(_), Choose::Yn) => true,
-                _ => false,
-            },
+            (Action::Executing(choice), ObsType::Executing(obs)) => obs.state.accepts(
+                choice,
+                fields.next_action.player,
+                fields.players.num_players(),
+            ),
             _ => false,
         })
     }
 
-    pub fn step(&mut self, action: Action) -> InnResult<GameState> {
+    /// Lists the valid actions of the acting player.
+    ///
+    /// Card choices with more than `MAX_LISTED_CARD_CHOICES` valid subsets
+    /// are described by `LegalActions::CardSubsets` instead.
+    pub fn legal_actions(&self) -> LegalActions {
+        if self.is_over() {
+            return LegalActions::Listed(Vec::new());
+        }
+        self.observe(self.acting_player()).legal_actions()
+    }
+
+    pub fn step(&mut self, action: Action) -> InnResult<GameState<'_>> {
         if !self.is_available_action(&action) {
             return Err(InnovationError::InvalidAction);
         }
-        self.with_mut(|fields| {
+        // pushed once the action is applied, so that a failed one leaves none
+        let entry = self.history_entry(&action);
+        let result = self.with_mut(|fields| {
             let game = *fields.players_ref;
-            game.notify(Item::Action(action.clone()))?;
-            let action = action.to_ref(game);
+            game.notify(Item::Action(
+                fields.next_action.player,
+                action.map(|name| game.find_card(name)),
+            ))?;
             match action {
-                RefAction::Step(step) => match fields.state {
+                Action::Step(step) => match fields.state {
                     State::Main => {
                         let player = game.player_at(fields.turn.player_id());
-                        match step {
+                        match step.to_ref(game) {
                             RefStep::Draw => {
                                 // This current executor finder appears here and in Players.execute
                                 // because either he's executing something or not.
@@ -654,11 +973,16 @@ impl<'c> OuterGame<'c> {
                                 fields.turn.next_step()?;
                             }
                             RefStep::Achieve(age) => {
-                                game.try_achieve(player, &SingleAchievementView::Normal(age)).expect("Have checked action, corresponding achievement should be available.");
+                                // Have checked action, corresponding achievement should be available,
+                                // but achieving may win the game.
+                                game.try_achieve(player, &SingleAchievementView::Normal(age))?;
                                 fields.turn.next_step()?;
                             }
                             RefStep::Execute(card) => {
-                                *fields.state = State::Executing(game.execute(player, card));
+                                // the players before the execution, after notifying the action
+                                let execution =
+                                    Execution::new(Root::Execute(player.id(), card), game.fork());
+                                *fields.state = State::Executing(Box::new(execution));
                             }
                         }
                     }
@@ -666,19 +990,44 @@ impl<'c> OuterGame<'c> {
                         panic!("State and action mismatched");
                     }
                 },
-                RefAction::Executing(choice) => match fields.state {
+                Action::Executing(choice) => match fields.state {
                     State::Main => panic!("State and action mismatched"),
-                    State::Executing(state) => {
-                        state.set_para(choice);
+                    State::Executing(execution) => {
+                        execution.choices.push(choice);
                     }
                 },
             }
             Ok(())
-        })?;
-        self.resume_execution()
+        });
+        let resumed = match result {
+            Ok(()) => self.resume()?,
+            Err(InnovationError::Win {
+                current_player,
+                situation,
+            }) => {
+                let current_player = current_player.unwrap_or_else(|| self.acting_player());
+                Resumed::End(current_player, situation.winners(self.borrow_players()))
+            }
+            Err(e) => return Err(e),
+        };
+        self.push_history(entry);
+        Ok(self.resumed_state(resumed))
     }
 
-    fn resume_execution(&mut self) -> InnResult<GameState> {
+    fn resume_execution(&mut self) -> InnResult<GameState<'_>> {
+        let resumed = self.resume()?;
+        Ok(self.resumed_state(resumed))
+    }
+
+    fn resumed_state(&mut self, resumed: Resumed) -> GameState<'_> {
+        match resumed {
+            Resumed::Normal(player) => GameState::Normal(player),
+            Resumed::End(player, winners) => self.end(player, winners),
+        }
+    }
+
+    // resumes the execution, if any, to the next choice or its end
+    fn resume(&mut self) -> InnResult<Resumed> {
         // helper enums/functions
         enum Info<'a> {
             Normal(ObsType<'a>),
@@ -692,67 +1041,78 @@ impl<'c> OuterGame<'c> {
             // resume execution, change to Main if ended,
             // and get current player and the obsType, which contains
             // some information if it is executing
-            if let State::Executing(state) = fields.state {
-                // resume until can't choose action automatically
-                match loop {
-                    let new_state = state.resume();
-                    if let Some(Ok(st)) = &new_state {
-                        match st.check_valid_actions(fields.players_ref) {
-                            ActionCheckResult::Zero => {
-                                state.set_para(RefChoice::NoValidAction);
-                                continue;
-                            }
-                            ActionCheckResult::One(choice) => {
-                                state.set_para(choice);
-                                continue;
-                            }
-                            _ => {}
-                        }
-                    }
-                    break new_state;
-                } {
-                    Some(Ok(st)) => {
+            if let State::Executing(execution) = fields.state {
+                match fields.players_ref.resume(execution) {
+                    Ok(Some(st)) => {
                         let (p, o) = st.to_obs();
                         let id = p.id();
                         ok_normal(id, ObsType::Executing(o))
                     }
-                    Some(Err(e)) => {
-                        if let InnovationError::Win {
-                            current_player,
-                            situation,
-                        } = e
-                        {
-                            Ok((
-                                current_player.unwrap(),
-                                Info::End(situation.winners(fields.players_ref)),
-                            ))
-                        } else {
-                            Err(e)
-                        }
-                    }
-                    None => {
+                    Ok(None) => {
                         *fields.state = State::Main;
                         fields.turn.next_step()?;
                         ok_normal(fields.turn.player_id(), ObsType::Main)
                     }
+                    Err(InnovationError::Win {
+                        current_player,
+                        situation,
+                    }) => Ok((
+                        current_player.unwrap(),
+                        Info::End(situation.winners(fields.players_ref)),
+                    )),
+                    Err(e) => Err(e),
                 }
             } else {
                 ok_normal(fields.turn.player_id(), ObsType::Main)
             }
         })? {
             (player, Info::Normal(obs_type)) => {
-                self.with_next_action_type_mut(|field| *field = obs_type.clone());
-                self.with_acting_player_mut(|field| *field = player);
-                Ok(GameState::Normal(player))
+                self.with_next_action_mut(|field| {
+                    *field = NextAction {
+                        player,
+                        obs_type: obs_type.clone(),
+                    }
+                });
+                Ok(Resumed::Normal(player))
             }
-            (player, Info::End(winners)) => Ok(GameState::End(self.observe_end(player, winners))),
+            (player, Info::End(winners)) => Ok(Resumed::End(player, winners)),
         }
     }
 
-    pub fn observe(&self, id: PlayerId) -> Observation {
+    fn end(&mut self, current_player: PlayerId, winners: Vec<PlayerId>) -> GameState<'_> {
+        // no choice is pending in an ended game, as in one loaded after it ended
+        self.with_mut(|fields| *fields.next_action = NextAction::main(fields.turn.player_id()));
+        self.with_winners_mut(|field| *field = Some(winners.clone()));
+        GameState::End(self.observe_end(current_player, winners))
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.borrow_winners().is_some()
+    }
+
+    /// The winners, if the game is over.
+    pub fn winners(&self) -> Option<&[PlayerId]> {
+        self.borrow_winners().as_deref()
+    }
+
+    pub fn players(&self) -> &Players<'c> {
+        self.borrow_players()
+    }
+
+    /// The kind of action expected next, with the pending choice if executing.
+    pub fn next_action_type(&self) -> &ObsType<'c> {
+        &self.borrow_next_action().obs_type
+    }
+
+    pub fn acting_player(&self) -> PlayerId {
+        self.borrow_next_action().player
+    }
+
+    pub fn observe(&self, id: PlayerId) -> Observation<'_> {
         let players = *self.borrow_players_ref();
         Observation {
-            acting_player: *self.borrow_acting_player(),
+            acting_player: self.acting_player(),
+            main_player_id: id,
             main_player: players.player_at(id).self_view(),
             other_players: players
                 .players_from(id)
@@ -760,12 +1120,13 @@ impl<'c> OuterGame<'c> {
                 .map(|p| p.other_view())
                 .collect(),
             main_pile: players.main_card_pile.borrow().view(),
+            achievements: players.main_card_pile.borrow().achievement_views(),
             turn: self.borrow_turn().turn(),
-            obstype: self.borrow_next_action_type().clone(),
+            obstype: self.next_action_type().clone(),
         }
     }
 
-    fn observe_end(&self, current_player: PlayerId, winners: Vec<PlayerId>) -> EndObservation {
+    fn observe_end(&self, current_player: PlayerId, winners: Vec<PlayerId>) -> EndObservation<'_> {
         let players = *self.borrow_players_ref();
         EndObservation {
             players_from_current: players
@@ -782,9 +1143,13 @@ impl<'c> OuterGame<'c> {
 pub struct GameConfig<'c> {
     all_cards: Vec<&'c Card>,
     main_pile: MainCardPile<'c>,
+    main_pile_builder: Option<MainCardPileBuilder<'c>>,
     players: Vec<PlayerBuilder<'c>>,
     turn: TurnBuilder,
     subject: Subject<'c>,
+    seed: Option<u64>,
+    rng: Option<Box<dyn RngCore + 'c>>,
+    undo_policy: UndoPolicy,
 }
 
 impl<'c> GameConfig<'c> {
@@ -792,14 +1157,58 @@ impl<'c> GameConfig<'c> {
         Self {
             all_cards,
             main_pile: MainCardPile::empty(),
+            main_pile_builder: None,
             players: vec![Default::default(), Default::default()],
             turn: TurnBuilder::new(),
             subject: Subject::new(),
+            seed: None,
+            rng: None,
+            undo_policy: UndoPolicy::default(),
         }
     }
 
+    /// A game of `all_cards` with the normal and special achievements, whose
+    /// main card pile is shuffled when built, all seeded with `seed`.
+    pub fn standard(all_cards: Vec<&'c Card>, seed: u64) -> GameConfig<'c> {
+        GameConfig::new(all_cards.clone())
+            .main_pile_builder(
+                MainCardPile::builder()
+                    .draw_deck(all_cards)
+                    .shuffled()
+                    .pick_normal()
+                    .special_achievements(SpecialAchievement::iter().collect()),
+            )
+            .seed(seed)
+    }
+
     pub fn main_pile(mut self, pile: MainCardPile<'c>) -> GameConfig<'c> {
         self.main_pile = pile;
+        self.main_pile_builder = None;
+        self
+    }
+
+    /// Builds the main card pile with the randomness of the game when the
+    /// game is built; see `seed` and `rng`.
+    pub fn main_pile_builder(mut self, builder: MainCardPileBuilder<'c>) -> GameConfig<'c> {
+        self.main_pile_builder = Some(builder);
+        self
+    }
+
+    /// Drives all randomness of the game by a `StdRng` seeded with `seed`.
+    ///
+    /// The seed is recorded in `Players::seed` only if the main card pile is
+    /// built from it, with `main_pile_builder`; a pile given to `main_pile`
+    /// is not. Without a seed or an rng, a random seed is used and recorded.
+    pub fn seed(mut self, seed: u64) -> GameConfig<'c> {
+        self.seed = Some(seed);
+        self.rng = Some(Box::new(StdRng::seed_from_u64(seed)));
+        self
+    }
+
+    /// Drives all randomness of the game by `rng`. No seed is recorded.
+    pub fn rng(mut self, rng: impl RngCore + 'c) -> GameConfig<'c> {
+        self.seed = None;
+        self.rng = Some(Box::new(rng));
         self
     }
 
@@ -828,6 +1237,11 @@ impl<'c> GameConfig<'c> {
         self
     }
 
+    pub fn undo_policy(mut self, policy: UndoPolicy) -> GameConfig<'c> {
+        self.undo_policy = policy;
+        self
+    }
+
     pub fn observe(mut self, observer: &Rc<RefCell<dyn Observer<'c> + 'c>>) -> GameConfig<'c> {
         self.subject.register_external(observer);
         self
@@ -839,13 +1253,26 @@ impl<'c> GameConfig<'c> {
     }
 
     pub fn build(self) -> OuterGame<'c> {
-        OuterGame::from_config(
+        let (main_pile, seed) = match (self.main_pile_builder, self.rng) {
+            // the seed had no part in a prebuilt pile
+            (None, _) => (self.main_pile, None),
+            (Some(builder), Some(mut rng)) => (builder.build_with(&mut rng), self.seed),
+            (Some(builder), None) => {
+                let seed = thread_rng().gen();
+                let pile = builder.build_with(&mut StdRng::seed_from_u64(seed));
+                (pile, Some(seed))
+            }
+        };
+        let mut game = OuterGame::from_config(
             self.all_cards,
-            self.main_pile,
+            main_pile,
             self.players,
             self.turn,
             self.subject,
-        )
+            seed,
+        );
+        game.set_undo_policy(self.undo_policy);
+        game
     }
 }
 
@@ -854,7 +1281,11 @@ mod tests {
 
     use super::*;
     use crate::{
-        action::NoRefChoice, card::default_cards, logger::FnObserver, state::ExecutionObs,
+        action::NoRefChoice,
+        card::default_cards,
+        card_pile::CardOrder,
+        logger::{FnObserver, Logger},
+        state::{Choose, ExecutionObs},
         utils::vec_eq_unordered,
     };
 
@@ -952,7 +1383,7 @@ mod tests {
             assert!(matches!(obs.obstype, ObsType::Executing(ExecutionObs {
                 state: Choose::Card { min_num: 1, max_num: Some(1), from },
                 card,
-            }) if vec_eq_unordered(&from, [&agriculture, &pottery]) && card == &archery));
+            }) if vec_eq_unordered(&from, [&agriculture, &pottery]) && card == Some(&archery)));
         }
         assert!(game.step(Action::Step(NoRefStep::Draw)).is_err());
         assert!(game.step(Action::Executing(NoRefChoice::Yn(true))).is_err());
@@ -984,4 +1415,363 @@ mod tests {
             assert!(matches!(obs.obstype, ObsType::Main));
         }
     }
+
+    #[test]
+    fn legal_actions() {
+        let archery = default_cards::archery();
+        let pottery = default_cards::pottery();
+        let agriculture = default_cards::agriculture();
+        let optics = default_cards::optics();
+        let enterprise = default_cards::enterprise();
+        let tools = default_cards::tools();
+        let cards = vec![
+            &archery,
+            &pottery,
+            &agriculture,
+            &optics,
+            &enterprise,
+            &tools,
+        ];
+        let mut game = GameConfig::new(cards)
+            .main_pile(
+                MainCardPile::builder()
+                    .draw_deck(vec![&pottery])
+                    .achievements(vec![Achievement::Normal(&optics)])
+                    .build(),
+            )
+            .players(vec![
+                PlayerBuilder::default()
+                    .board(vec![&archery])
+                    .hand(vec![&agriculture])
+                    .score(vec![&enterprise, &tools]),
+                PlayerBuilder::default().hand(vec![&agriculture]),
+            ])
+            .build();
+        let legal = game.legal_actions();
+        assert_eq!(
+            legal,
+            LegalActions::Listed(vec![
+                Action::Step(NoRefStep::Draw),
+                Action::Step(NoRefStep::Meld("Agriculture".to_owned())),
+                Action::Step(NoRefStep::Execute("Archery".to_owned())),
+            ])
+        );
+        // the achievement is of age 3, higher than player's age
+        assert!(!legal.contains(&Action::Step(NoRefStep::Achieve(3))));
+        // agents list the same actions from their observations
+        assert_eq!(game.observe(0).legal_actions(), legal);
+        game.step(Action::Step(NoRefStep::Execute("Archery".to_owned())))
+            .unwrap();
+        let legal = game.legal_actions();
+        assert_eq!(legal.count(), 2);
+        assert!(legal.contains(&Action::Executing(NoRefChoice::Card(vec![
+            "Agriculture".to_owned()
+        ]))));
+        assert!(legal.contains(&Action::Executing(NoRefChoice::Card(vec![
+            "Pottery".to_owned()
+        ]))));
+        assert_eq!(game.observe(1).legal_actions(), legal);
+    }
+
+    #[test]
+    fn execute_top_cards_only() {
+        let archery = default_cards::archery();
+        let oars = default_cards::oars();
+        let mut game = GameConfig::new(vec![&archery, &oars])
+            .players(vec![
+                PlayerBuilder::default().board(vec![&archery, &oars]),
+                PlayerBuilder::default(),
+            ])
+            .build();
+        let top = game
+            .players()
+            .player_at(0)
+            .stack(Color::Red)
+            .top_card()
+            .unwrap();
+        let covered = if top == &archery { &oars } else { &archery };
+        assert!(matches!(
+            game.step(Action::Step(NoRefStep::Execute(covered.name().to_owned()))),
+            Err(InnovationError::InvalidAction)
+        ));
+        game.step(Action::Step(NoRefStep::Execute(top.name().to_owned())))
+            .unwrap();
+    }
+
+    #[test]
+    fn start_choice_has_no_card() {
+        let archery = default_cards::archery();
+        let oars = default_cards::oars();
+        let pottery = default_cards::pottery();
+        let clothing = default_cards::clothing();
+        let cards = vec![&archery, &oars, &pottery, &clothing];
+        let mut game = GameConfig::new(cards.clone())
+            .main_pile(MainCardPile::builder().draw_deck(cards).build())
+            .default_players(2)
+            .build();
+        // the opening meld is chosen outside the execution of any card
+        let player = game.start().unwrap().as_normal().unwrap();
+        assert!(matches!(
+            game.observe(player).obstype,
+            ObsType::Executing(ExecutionObs {
+                state: Choose::Card { .. },
+                card: None,
+            })
+        ));
+    }
+
+    #[test]
+    fn fork() {
+        let masonry = default_cards::masonry();
+        let archery = default_cards::archery();
+        let metalworking = default_cards::metalworking();
+        let oars = default_cards::oars();
+        let pottery = default_cards::pottery();
+        let cards = vec![&masonry, &archery, &metalworking, &oars, &pottery];
+        let mut game = GameConfig::new(cards)
+            .main_pile(MainCardPile::builder().draw_deck(vec![&pottery]).build())
+            .player(
+                0,
+                PlayerBuilder::default().board(vec![&masonry]).hand(vec![
+                    &archery,
+                    &metalworking,
+                    &oars,
+                ]),
+            )
+            .build();
+        let to_json = |game: &OuterGame| serde_json::to_value(game.observe(0)).unwrap();
+
+        // in the main phase
+        let mut forked = game.fork();
+        assert_eq!(to_json(&forked), to_json(&game));
+        forked.step(Action::Step(NoRefStep::Draw)).unwrap();
+        assert_eq!(forked.players().player_at(0).hand().to_vec().len(), 4);
+        assert_eq!(game.players().player_at(0).hand().to_vec().len(), 3);
+
+        // in the middle of a dogma
+        game.step(Action::Step(NoRefStep::Execute("Masonry".to_owned())))
+            .unwrap();
+        let mut forked = game.fork();
+        assert_eq!(to_json(&forked), to_json(&game));
+        assert_eq!(forked.legal_actions(), game.legal_actions());
+        forked
+            .step(Action::Executing(NoRefChoice::Card(vec![
+                "Archery".to_owned(),
+                "Oars".to_owned(),
+            ])))
+            .unwrap();
+        assert_eq!(forked.players().player_at(0).board().top_cards().len(), 2);
+        assert_eq!(game.players().player_at(0).board().top_cards().len(), 1);
+        game.step(Action::Executing(NoRefChoice::Card(vec![
+            "Metalworking".to_owned()
+        ])))
+        .unwrap();
+        assert_eq!(
+            forked.players().player_at(0).hand().to_vec(),
+            vec![&metalworking]
+        );
+        assert_eq!(
+            game.players().player_at(0).hand().to_vec(),
+            vec![&archery, &oars]
+        );
+    }
+
+    #[test]
+    fn execution_replay() {
+        let archery = default_cards::archery();
+        let clothing = default_cards::clothing();
+        let code_of_laws = default_cards::code_of_laws();
+        let pottery = default_cards::pottery();
+        let cards = vec![&archery, &clothing, &code_of_laws, &pottery];
+        let logger = Rc::new(RefCell::new(Logger::new()));
+        let observer: Rc<RefCell<dyn Observer>> = logger.clone();
+        let mut game = GameConfig::new(cards)
+            .main_pile(MainCardPile::builder().draw_deck(vec![&pottery]).build())
+            .players(vec![
+                PlayerBuilder::default().board(vec![&archery]),
+                PlayerBuilder::default().hand(vec![&clothing, &code_of_laws]),
+            ])
+            .observe(&observer)
+            .build();
+        logger.borrow_mut().start_game(game.players());
+        // the opponent draws a 1, then chooses one of three 1s
+        game.step(Action::Step(NoRefStep::Execute("Archery".to_owned())))
+            .unwrap();
+        assert_eq!(game.acting_player(), 1);
+        assert_eq!(game.players().player_at(1).hand().to_vec().len(), 3);
+        game.step(Action::Executing(NoRefChoice::Card(vec![
+            "Code Of Laws".to_owned()
+        ])))
+        .unwrap();
+        assert_eq!(
+            game.players().player_at(0).hand().to_vec(),
+            vec![&code_of_laws]
+        );
+        assert!(vec_eq_unordered(
+            &game.players().player_at(1).hand().to_vec(),
+            [&clothing, &pottery]
+        ));
+        assert!(matches!(game.next_action_type(), ObsType::Main));
+
+        // the draw before the choice is delivered once
+        let logger = logger.borrow();
+        let items = &logger.current_game().unwrap().items;
+        let draws = items
+            .iter()
+            .filter(|item| {
+                matches!(
+                    item,
+                    Item::Operation(Operation::SimpleOp(SimpleOp::Draw, ..))
+                )
+            })
+            .count();
+        assert_eq!(draws, 1);
+        let actions = items
+            .iter()
+            .filter(|item| matches!(item, Item::Action(..)))
+            .count();
+        assert_eq!(actions, 2);
+    }
+
+    #[test]
+    fn seeded() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let config = |seed| GameConfig::standard(cards.clone(), seed).default_players(3);
+        fn contents<'c>(game: &OuterGame<'c>) -> CardOrder<'c> {
+            game.players().main_card_pile().borrow().contents()
+        }
+
+        // the same seed and actions give the same game
+        let mut game = config(11).build();
+        let mut same = config(11).build();
+        assert_eq!(game.players().seed(), Some(11));
+        assert_eq!(contents(&game), contents(&same));
+        game.start().unwrap();
+        same.start().unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut step = 0;
+        while !game.is_over() && step < 100 {
+            let action = game.legal_actions().choose(&mut rng).unwrap();
+            game.step(action.clone()).unwrap();
+            same.step(action).unwrap();
+            assert_eq!(
+                serde_json::to_value(game.observe(0)).unwrap(),
+                serde_json::to_value(same.observe(0)).unwrap()
+            );
+            step += 1;
+        }
+        assert_eq!(contents(&game), contents(&same));
+        assert_ne!(contents(&config(12).build()), contents(&same));
+
+        // a random seed is recorded, and reproduces the game
+        let random = GameConfig::new(cards.clone())
+            .main_pile_builder(
+                MainCardPile::builder()
+                    .draw_deck(cards.clone())
+                    .shuffled()
+                    .pick_normal()
+                    .special_achievements(SpecialAchievement::iter().collect()),
+            )
+            .default_players(3)
+            .build();
+        let seed = random.players().seed().unwrap();
+        assert_eq!(contents(&config(seed).build()), contents(&random));
+        // an injected rng records no seed
+        let injected = config(0).rng(StdRng::seed_from_u64(11)).build();
+        assert_eq!(injected.players().seed(), None);
+        assert_eq!(contents(&injected), contents(&config(11).build()));
+        // a prebuilt pile is not built from the seed, which isn't recorded
+        let pile = MainCardPile::builder()
+            .draw_deck(cards.clone())
+            .shuffled()
+            .pick_normal()
+            .build_with(&mut StdRng::seed_from_u64(11));
+        assert_eq!(pile.contents(), contents(&config(11).build()));
+        let prebuilt = GameConfig::new(cards.clone())
+            .main_pile(pile)
+            .seed(11)
+            .build();
+        assert_eq!(prebuilt.players().seed(), None);
+    }
+
+    #[test]
+    fn undo_and_redo() {
+        let masonry = default_cards::masonry();
+        let archery = default_cards::archery();
+        let metalworking = default_cards::metalworking();
+        let oars = default_cards::oars();
+        let pottery = default_cards::pottery();
+        let cards = vec![&masonry, &archery, &metalworking, &oars, &pottery];
+        let config = |policy| {
+            GameConfig::new(cards.clone())
+                .main_pile(MainCardPile::builder().draw_deck(vec![&pottery]).build())
+                .player(
+                    0,
+                    PlayerBuilder::default().board(vec![&masonry]).hand(vec![
+                        &archery,
+                        &metalworking,
+                        &oars,
+                    ]),
+                )
+                .undo_policy(policy)
+        };
+        let to_json = |game: &OuterGame| serde_json::to_value(game.observe(0)).unwrap();
+        let execute = Action::Step(NoRefStep::Execute("Masonry".to_owned()));
+        let choose = Action::Executing(NoRefChoice::Card(vec!["Archery".to_owned()]));
+
+        let mut game = config(UndoPolicy::Disabled).build();
+        game.step(execute.clone()).unwrap();
+        assert!(!game.undo());
+
+        // a choice in a dogma, then the dogma
+        let logger = Rc::new(RefCell::new(Logger::new()));
+        let observer: Rc<RefCell<dyn Observer>> = logger.clone();
+        let mut game = config(UndoPolicy::Enabled).observe(&observer).build();
+        logger.borrow_mut().start_game(game.players());
+        let items = || logger.borrow().current_game().unwrap().items.clone();
+        let start = to_json(&game);
+        game.step(execute.clone()).unwrap();
+        let executing = to_json(&game);
+        let executing_items = items();
+        game.step(choose.clone()).unwrap();
+        let chosen = to_json(&game);
+        assert_eq!(game.players().player_at(0).hand().to_vec().len(), 2);
+        assert!(game.undo());
+        assert_eq!(to_json(&game), executing);
+        // the logger drops what was undone
+        assert_eq!(items(), executing_items);
+        assert!(game.undo());
+        assert_eq!(to_json(&game), start);
+        assert!(items().is_empty());
+        assert!(!game.undo());
+        assert!(game.redo().unwrap());
+        assert!(game.redo().unwrap());
+        assert_eq!(to_json(&game), chosen);
+        assert!(!game.redo().unwrap());
+        // another action drops the undone ones
+        game.undo();
+        game.step(Action::Executing(NoRefChoice::Card(vec![])))
+            .unwrap();
+        assert!(!game.can_redo());
+
+        // melding from the hand and drawing reveal cards
+        let draw = Action::Step(NoRefStep::Draw);
+        let mut game = config(UndoPolicy::KeepReveals).build();
+        game.step(execute.clone()).unwrap();
+        assert!(game.can_undo());
+        game.step(choose.clone()).unwrap();
+        assert!(!game.can_undo());
+        game.step(draw.clone()).unwrap();
+        assert!(!game.can_undo());
+        assert!(!game.undo());
+        let mut game = config(UndoPolicy::Enabled).build();
+        game.step(execute).unwrap();
+        game.step(choose).unwrap();
+        let chosen = to_json(&game);
+        game.step(draw).unwrap();
+        assert!(game.undo());
+        assert_eq!(to_json(&game), chosen);
+        assert_eq!(game.players().main_card_pile().borrow().view()[0], 1);
+    }
 }
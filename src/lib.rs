@@ -1,18 +1,46 @@
+// lints the long-standing tests are written against
+#![cfg_attr(
+    test,
+    allow(
+        clippy::bool_assert_comparison,
+        clippy::extra_unused_lifetimes,
+        clippy::needless_borrows_for_generic_args,
+        clippy::useless_vec
+    )
+)]
+
 pub mod action;
+pub mod agent;
 pub mod auto_achieve;
 pub mod board;
 pub mod card;
 pub mod card_pile;
+pub mod codec;
 pub mod containers;
+pub mod determinize;
+pub mod encoder;
+pub mod env;
 pub mod error;
 pub mod game;
 pub mod logger;
+pub mod narrator;
+pub mod notation;
 pub mod observation;
+#[allow(mismatched_lifetime_syntaxes)]
 pub mod player;
+pub mod protocol;
+pub mod record;
+pub mod render;
+pub mod replay;
+pub mod save;
+pub mod server;
 pub mod state;
 pub mod structure;
+pub mod tournament;
+pub mod trajectory;
 pub mod turn;
 pub mod utils;
+pub mod viewer;
 pub mod xx;
 
 #[cfg(test)]
@@ -1,20 +1,25 @@
 use std::{
-    cell::RefCell,
-    collections::VecDeque,
+    cell::{Cell, RefCell},
+    collections::{BTreeMap, VecDeque},
     rc::{Rc, Weak},
 };
 
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+
 use crate::{
     action::Action,
-    card::{Card, Color, Splay},
+    card::{Achievement, Card, Color, Splay},
     card_pile::CardOrder,
-    error::InnResult,
+    error::{InnResult, InnovationError},
     game::{PlayerId, Players},
     observation::SingleAchievementView,
+    record::{GameRecord, ItemData, LogFile, OperationData, LOG_VERSION},
     structure::Place,
 };
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SimpleOp {
     Draw,
     Meld,
@@ -26,7 +31,7 @@ pub enum SimpleOp {
     DrawAndTuck,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Operation<'c> {
     Splay(PlayerId, Color, Splay),
     Transfer(Place, Place, &'c Card),
@@ -35,13 +40,30 @@ pub enum Operation<'c> {
     Achieve(PlayerId, SingleAchievementView),
 }
 
+impl<'c> Serialize for Operation<'c> {
+    /// Serializes as `record::OperationData`, with cards by name.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        OperationData::new(self).serialize(serializer)
+    }
+}
+
 // TODO: GameStart, GameEnd message, etc.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Item<'c> {
-    Action(Action),
+    Action(PlayerId, Action<&'c Card>), // acting player, action
     Operation(Operation<'c>),
     NextAction(PlayerId),
     ChangeTurn(PlayerId, PlayerId), // last player, next player
+    /// The last action, with everything following it, was taken back;
+    /// see `OuterGame::undo`.
+    Undo,
+}
+
+impl<'c> Serialize for Item<'c> {
+    /// Serializes as `record::ItemData`, with cards by name.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ItemData::new(self).serialize(serializer)
+    }
 }
 
 #[derive(Default)]
@@ -56,6 +78,9 @@ pub struct Subject<'c> {
     owned_ext_observers: Vec<RefCell<Box<dyn Observer<'c> + 'c>>>,
     waiting: RefCell<VecDeque<Item<'c>>>,
     processing: RefCell<()>, // can't be bool because we need to mutate it
+    // number of items processed, and of the next items hidden from external observers
+    notified: Cell<usize>,
+    muted: Cell<usize>,
 }
 
 impl<'c> Subject<'c> {
@@ -67,6 +92,8 @@ impl<'c> Subject<'c> {
             owned_ext_observers: Vec::new(),
             waiting: RefCell::new(VecDeque::new()),
             processing: RefCell::new(()),
+            notified: Cell::new(0),
+            muted: Cell::new(0),
         }
     }
 
@@ -100,6 +127,63 @@ impl<'c> Subject<'c> {
             .push(RefCell::new(Box::new(new_observer)));
     }
 
+    /// A subject with forks of the permanent internal observers.
+    ///
+    /// External observers and the internal observers registered by reference
+    /// are left out, as they are owned by someone else.
+    pub fn fork(&self) -> Self {
+        Self {
+            owned_observers: self
+                .owned_observers
+                .iter()
+                .map(|observer| RefCell::new(observer.borrow().fork()))
+                .collect(),
+            ..Self::new()
+        }
+    }
+
+    /// Sets the permanent internal observers back to those of `from`,
+    /// a fork of this subject, and drops the waiting items.
+    pub fn restore(&self, from: &Subject<'c>) {
+        debug_assert_eq!(self.owned_observers.len(), from.owned_observers.len());
+        for (observer, from) in self.owned_observers.iter().zip(from.owned_observers.iter()) {
+            *observer.borrow_mut() = from.borrow().fork();
+        }
+        self.waiting.borrow_mut().clear();
+    }
+
+    /// The states of the permanent internal observers, in order of registration.
+    pub fn save(&self) -> Vec<Value> {
+        self.owned_observers
+            .iter()
+            .map(|observer| observer.borrow().save())
+            .collect()
+    }
+
+    /// Sets the permanent internal observers back to saved states.
+    ///
+    /// The observers should be registered in the same order as when saved.
+    pub fn load(&self, states: &[Value]) -> InnResult<()> {
+        if states.len() != self.owned_observers.len() {
+            return Err(InnovationError::InvalidSave);
+        }
+        for (observer, state) in self.owned_observers.iter().zip(states) {
+            observer.borrow_mut().load(state)?;
+        }
+        Ok(())
+    }
+
+    /// The number of items processed so far.
+    pub fn num_notified(&self) -> usize {
+        self.notified.get()
+    }
+
+    /// Hides the next `num` items from external observers, e.g. when
+    /// replaying operations they have seen already.
+    pub fn mute(&self, num: usize) {
+        self.muted.set(num);
+    }
+
     // must be immutable &self, because there may be multiple calls in the stack
     pub fn notify(&self, item: Item<'c>, game: &Players<'c>) -> InnResult<()> {
         self.waiting.borrow_mut().push_back(item);
@@ -121,20 +205,25 @@ impl<'c> Subject<'c> {
             }
 
             let item = next.unwrap();
+            self.notified.set(self.notified.get() + 1);
 
             // first notify external observers, which may log events and don't modify game state,
             // so we won't worry about multiple RefCell borrow_mut
-            for owned_observer in self.owned_ext_observers.iter() {
-                owned_observer.borrow_mut().on_notify(&item);
-            }
-            self.ext_observers.borrow_mut().retain_mut(|observer| {
-                if let Some(active_observer) = observer.upgrade() {
-                    active_observer.borrow_mut().on_notify(&item);
-                    true
-                } else {
-                    false
+            if self.muted.get() > 0 {
+                self.muted.set(self.muted.get() - 1);
+            } else {
+                for owned_observer in self.owned_ext_observers.iter() {
+                    owned_observer.borrow_mut().on_notify(&item);
                 }
-            });
+                self.ext_observers.borrow_mut().retain_mut(|observer| {
+                    if let Some(active_observer) = observer.upgrade() {
+                        active_observer.borrow_mut().on_notify(&item);
+                        true
+                    } else {
+                        false
+                    }
+                });
+            }
 
             // second notify internal observers, letting them modify the game state and send new events
             for owned_observer in self.owned_observers.iter() {
@@ -158,8 +247,13 @@ impl<'c> Subject<'c> {
         Ok(())
     }
 
-    pub fn act(&self, action: Action, game: &Players<'c>) -> InnResult<()> {
-        self.notify(Item::Action(action), game)
+    pub fn act(
+        &self,
+        player: PlayerId,
+        action: Action<&'c Card>,
+        game: &Players<'c>,
+    ) -> InnResult<()> {
+        self.notify(Item::Action(player, action), game)
     }
 
     pub fn operate(&self, operation: Operation<'c>, game: &Players<'c>) -> InnResult<()> {
@@ -175,14 +269,35 @@ pub trait Observer<'c> {
 
 pub trait InternalObserver<'c> {
     fn update(&mut self, event: &Item<'c>, game: &Players<'c>) -> InnResult<()>;
+    /// An independent copy of the observer, for a forked game.
+    fn fork(&self) -> Box<dyn InternalObserver<'c> + 'c>;
+    /// The state of the observer to save with the game, if any.
+    fn save(&self) -> Value {
+        Value::Null
+    }
+    /// Sets the state back to one returned by `save`.
+    fn load(&mut self, _state: &Value) -> InnResult<()> {
+        Ok(())
+    }
 }
 
-// there's really no way to factor the type
-#[allow(clippy::type_complexity)]
-pub struct FnInternalObserver<'c>(Box<dyn FnMut(&Item<'c>, &Players<'c>) -> InnResult<()> + 'c>);
+trait InternalFn<'c>: FnMut(&Item<'c>, &Players<'c>) -> InnResult<()> {
+    fn box_clone(&self) -> Box<dyn InternalFn<'c> + 'c>;
+}
+
+impl<'c, F> InternalFn<'c> for F
+where
+    F: FnMut(&Item<'c>, &Players<'c>) -> InnResult<()> + Clone + 'c,
+{
+    fn box_clone(&self) -> Box<dyn InternalFn<'c> + 'c> {
+        Box::new(self.clone())
+    }
+}
+
+pub struct FnInternalObserver<'c>(Box<dyn InternalFn<'c> + 'c>);
 
 impl<'c> FnInternalObserver<'c> {
-    pub fn new(f: impl FnMut(&Item<'c>, &Players<'c>) -> InnResult<()> + 'c) -> Self {
+    pub fn new(f: impl FnMut(&Item<'c>, &Players<'c>) -> InnResult<()> + Clone + 'c) -> Self {
         Self(Box::new(f))
     }
 }
@@ -191,6 +306,10 @@ impl<'c> InternalObserver<'c> for FnInternalObserver<'c> {
     fn update(&mut self, event: &Item<'c>, game: &Players<'c>) -> InnResult<()> {
         self.0(event, game)
     }
+
+    fn fork(&self) -> Box<dyn InternalObserver<'c> + 'c> {
+        Box::new(FnInternalObserver(self.0.box_clone()))
+    }
 }
 
 pub struct FnObserver<'c>(Box<dyn FnMut(&Item<'c>) + 'c>);
@@ -209,15 +328,42 @@ impl<'c> Observer<'c> for FnObserver<'c> {
 
 #[derive(Clone)]
 pub struct Game<'c> {
+    pub num_players: usize,
+    /// Free-form information about the game, like the names of the players.
+    pub metadata: BTreeMap<String, String>,
     pub initial_cards: CardOrder<'c>,
+    /// The achievements available at the start.
+    pub achievements: Vec<Achievement<'c>>,
+    /// The seed of the game, if known; see `Players::seed`.
+    pub seed: Option<u64>,
     pub items: Vec<Item<'c>>,
+    /// The winners, once the game is finished.
+    pub winners: Option<Vec<PlayerId>>,
 }
 
 impl<'c> Game<'c> {
     fn new(initial_cards: CardOrder<'c>) -> Self {
         Self {
+            num_players: 0,
+            metadata: BTreeMap::new(),
             initial_cards,
+            achievements: Vec::new(),
+            seed: None,
             items: Vec::new(),
+            winners: None,
+        }
+    }
+
+    fn of(game: &Players<'c>) -> Self {
+        let pile = game.main_card_pile().borrow();
+        Self {
+            num_players: game.num_players(),
+            metadata: BTreeMap::new(),
+            initial_cards: pile.contents(),
+            achievements: pile.achievements().to_vec(),
+            seed: game.seed(),
+            items: Vec::new(),
+            winners: None,
         }
     }
 }
@@ -244,23 +390,62 @@ impl<'c> Logger<'c> {
             .push(item);
     }
 
-    pub fn act(&mut self, action: Action) {
-        self.log(Item::Action(action));
+    pub fn act(&mut self, player: PlayerId, action: Action<&'c Card>) {
+        self.log(Item::Action(player, action));
     }
 
     pub fn operate(&mut self, operation: Operation<'c>) {
         self.log(Item::Operation(operation));
     }
 
-    pub fn finish(&mut self) {
-        self.history
-            .push(std::mem::take(&mut self.current_game).expect("cards not initialized"));
+    /// Drops the items of the last action, so that the game is recorded as
+    /// if it was never taken.
+    pub fn undo(&mut self) {
+        let items = &mut self
+            .current_game
+            .as_mut()
+            .expect("cards not initialized")
+            .items;
+        if let Some(index) = items
+            .iter()
+            .rposition(|item| matches!(item, Item::Action(..)))
+        {
+            items.truncate(index);
+        }
+    }
+
+    pub fn finish(&mut self, winners: Vec<PlayerId>) {
+        let mut game = std::mem::take(&mut self.current_game).expect("cards not initialized");
+        game.winners = Some(winners);
+        self.history.push(game);
+    }
+
+    /// Sets a metadata entry of the current game.
+    pub fn set_metadata(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.current_game
+            .as_mut()
+            .expect("cards not initialized")
+            .metadata
+            .insert(key.into(), value.into());
     }
 
+    /// Starts recording a game with only its draw deck, `initial_cards`.
+    ///
+    /// The number of players, the achievements and the seed are not known,
+    /// so the record can't be replayed; see `start_game`.
     pub fn start(&mut self, initial_cards: [Vec<&'c Card>; 10]) {
+        self.begin(Game::new(initial_cards));
+    }
+
+    /// Starts recording `game`, which should not have started yet.
+    pub fn start_game(&mut self, game: &Players<'c>) {
+        self.begin(Game::of(game));
+    }
+
+    fn begin(&mut self, game: Game<'c>) {
         match self.current_game {
             Some(_) => panic!("already initialized"),
-            None => self.current_game = Some(Game::new(initial_cards)),
+            None => self.current_game = Some(game),
         }
     }
 
@@ -271,10 +456,36 @@ impl<'c> Logger<'c> {
     pub fn current_game(&self) -> Option<&Game<'c>> {
         self.current_game.as_ref()
     }
+
+    /// The finished games, to be written to a file; see `record`.
+    pub fn save(&self) -> LogFile {
+        LogFile {
+            version: LOG_VERSION,
+            games: self.history.iter().map(GameRecord::new).collect(),
+        }
+    }
+
+    /// A logger with the finished games of `file`, with the cards in `cards`.
+    pub fn load(cards: &[&'c Card], file: &LogFile) -> InnResult<Logger<'c>> {
+        if file.version != LOG_VERSION {
+            return Err(InnovationError::UnsupportedLogVersion(file.version));
+        }
+        Ok(Logger {
+            history: file
+                .games
+                .iter()
+                .map(|game| game.load(cards))
+                .collect::<InnResult<_>>()?,
+            current_game: None,
+        })
+    }
 }
 
 impl<'c> Observer<'c> for Logger<'c> {
     fn on_notify(&mut self, event: &Item<'c>) {
-        self.log(event.clone());
+        match event {
+            Item::Undo => self.undo(),
+            _ => self.log(event.clone()),
+        }
     }
 }
@@ -0,0 +1,404 @@
+//! Narrating games in English, mostly for debugging.
+//!
+//! `Narrator` is an observer writing a sentence per item, grouped by turn.
+//! Players are numbered from 1, so player 0 is "Player 1".
+//!
+//! Draws, melds and the like are notified as a transfer followed by the
+//! operation; the narrator tells only the latter.
+//!
+//! Items are told as the `protocol::Event`s seen by the viewer, so a narrator
+//! may also be given the events an agent receives.
+
+use std::io::{self, Write};
+
+use crate::{
+    action::{Action, NoRefChoice, NoRefStep},
+    card::Splay,
+    game::PlayerId,
+    logger::{Item, Observer, SimpleOp},
+    observation::SingleAchievementView,
+    protocol::{redact, Event, SeenCard},
+    record::{ItemData, OperationData},
+    structure::{Place, PlayerPlace},
+};
+
+pub struct Narrator<W: Write> {
+    writer: W,
+    viewer: Option<PlayerId>,
+    turn: usize,
+    started: bool,
+    // a transfer not written yet, which may be told by the next operation
+    transfer: Option<(Place, Place, SeenCard, String)>,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Narrator<W> {
+    /// A narrator seeing all cards.
+    pub fn new(writer: W) -> Narrator<W> {
+        Narrator {
+            writer,
+            viewer: None,
+            turn: 0,
+            started: false,
+            transfer: None,
+            error: None,
+        }
+    }
+
+    /// Redacts the cards hidden from `player`, showing their ages only.
+    pub fn viewer(mut self, player: PlayerId) -> Narrator<W> {
+        self.viewer = Some(player);
+        self
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    /// Writes the last transfer, if it's held back.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        if let Some((_, _, _, line)) = self.transfer.take() {
+            writeln!(self.writer, "  {}", line)?;
+        }
+        self.writer.flush()
+    }
+
+    /// The writer, after `flush`.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Tells `event`, which is seen as it is, as by an agent given it.
+    pub fn tell(&mut self, event: &Event) {
+        if self.error.is_none() {
+            if let Err(error) = self.write(event) {
+                self.error = Some(error);
+            }
+        }
+    }
+
+    fn write(&mut self, event: &Event) -> io::Result<()> {
+        if !self.started {
+            self.started = true;
+            writeln!(self.writer, "Setup")?;
+        }
+        if let Some((from, to, card, line)) = self.transfer.take() {
+            let told = matches!(
+                event,
+                ItemData::Operation(OperationData::SimpleOp(op, id, c, f))
+                    if *f == from && destination(op, *id) == to && *c == card
+            );
+            if !told {
+                writeln!(self.writer, "  {}", line)?;
+            }
+        }
+        match event {
+            ItemData::Operation(OperationData::Transfer(from, to, card)) => {
+                self.transfer = Some((*from, *to, card.clone(), event_sentence(event)));
+                Ok(())
+            }
+            ItemData::ChangeTurn(_, next) => {
+                self.turn += 1;
+                writeln!(self.writer, "Turn {}: {}", self.turn, player(*next))
+            }
+            _ => writeln!(self.writer, "  {}", event_sentence(event)),
+        }
+    }
+}
+
+impl<'c, W: Write> Observer<'c> for Narrator<W> {
+    fn on_notify(&mut self, event: &Item<'c>) {
+        let event = redact(event, self.viewer);
+        self.tell(&event);
+    }
+}
+
+/// Describes `item` as seen by `viewer`, or by someone seeing all cards.
+pub fn sentence(item: &Item, viewer: Option<PlayerId>) -> String {
+    event_sentence(&redact(item, viewer))
+}
+
+/// Describes `event`, with the cards it shows.
+pub fn event_sentence(event: &Event) -> String {
+    let sentence = match event {
+        ItemData::Action(id, action) => action_sentence(action, *id),
+        ItemData::Operation(operation) => operation_sentence(operation),
+        ItemData::NextAction(next) => format!("{} takes another action", player(*next)),
+        ItemData::ChangeTurn(last, next) => {
+            format!(
+                "{} ends the turn, {} goes next",
+                player(*last),
+                player(*next)
+            )
+        }
+        ItemData::Undo => "the last action is taken back".to_owned(),
+    };
+    let mut chars = sentence.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => sentence,
+    }
+}
+
+fn player(id: PlayerId) -> String {
+    format!("Player {}", id + 1)
+}
+
+fn owner(place: Place) -> Option<PlayerId> {
+    match place {
+        Place::MainCardPile => None,
+        Place::Player(id, _) => Some(id),
+    }
+}
+
+// the place, from the point of view of `subject`
+fn place(place: Place, subject: Option<PlayerId>) -> String {
+    match place {
+        Place::MainCardPile => "the main pile".to_owned(),
+        Place::Player(id, player_place) => {
+            let name = match player_place {
+                PlayerPlace::Hand => "hand",
+                PlayerPlace::Score => "score pile",
+                PlayerPlace::Board => "board",
+            };
+            if subject == Some(id) {
+                name.to_owned()
+            } else {
+                format!("{}'s {}", player(id), name)
+            }
+        }
+    }
+}
+
+// whether `viewer` sees the cards in `place`; everyone sees all if `None`
+pub(crate) fn is_visible(place: Place, viewer: Option<PlayerId>) -> bool {
+    match (place, viewer) {
+        (_, None) => true,
+        (Place::MainCardPile, _) => false,
+        (Place::Player(_, PlayerPlace::Board), _) => true,
+        (Place::Player(id, _), Some(viewer)) => id == viewer,
+    }
+}
+
+/// Where `op` by player `id` puts the card.
+pub(crate) fn destination(op: &SimpleOp, id: PlayerId) -> Place {
+    match op {
+        SimpleOp::Draw => Place::Player(id, PlayerPlace::Hand),
+        SimpleOp::Meld | SimpleOp::Tuck | SimpleOp::DrawAndMeld | SimpleOp::DrawAndTuck => {
+            Place::Player(id, PlayerPlace::Board)
+        }
+        SimpleOp::Score | SimpleOp::DrawAndScore => Place::Player(id, PlayerPlace::Score),
+        SimpleOp::Return => Place::MainCardPile,
+    }
+}
+
+fn card(card: &SeenCard) -> String {
+    match card {
+        SeenCard::Name(name) => name.clone(),
+        SeenCard::Hidden { age } => format!("an age {} card", age),
+    }
+}
+
+fn cards(cards: &[SeenCard]) -> String {
+    if cards.is_empty() {
+        "nothing".to_owned()
+    } else {
+        cards.iter().map(card).collect::<Vec<_>>().join(", ")
+    }
+}
+
+fn achievement(view: &SingleAchievementView) -> String {
+    match view {
+        SingleAchievementView::Normal(age) => format!("the age {} achievement", age),
+        SingleAchievementView::Special(special) => format!("{:?}", special),
+    }
+}
+
+fn action_sentence(action: &Action<SeenCard>, id: PlayerId) -> String {
+    let subject = player(id);
+    match action {
+        Action::Step(NoRefStep::Draw) => format!("{} takes a Draw action", subject),
+        Action::Step(NoRefStep::Meld(name)) => {
+            format!("{} takes a Meld action with {}", subject, name)
+        }
+        Action::Step(NoRefStep::Achieve(age)) => {
+            format!("{} takes an Achieve action for age {}", subject, age)
+        }
+        Action::Step(NoRefStep::Execute(name)) => {
+            format!("{} takes a Dogma action with {}", subject, name)
+        }
+        // the chosen cards are hidden from the others, which see what's
+        // done with them
+        Action::Executing(NoRefChoice::Card(chosen)) => match chosen.len() {
+            0 => format!("{} chooses nothing", subject),
+            _ if chosen.iter().all(|c| matches!(c, SeenCard::Name(_))) => {
+                format!("{} chooses {}", subject, cards(chosen))
+            }
+            1 => format!("{} chooses 1 card", subject),
+            n => format!("{} chooses {} cards", subject, n),
+        },
+        Action::Executing(NoRefChoice::Opponent(opponent)) => {
+            format!("{} chooses {}", subject, player(*opponent))
+        }
+        Action::Executing(NoRefChoice::Yn(yes)) => {
+            format!("{} chooses {}", subject, if *yes { "yes" } else { "no" })
+        }
+    }
+}
+
+fn operation_sentence(operation: &OperationData<SeenCard>) -> String {
+    match operation {
+        OperationData::Splay(id, color, Splay::NoSplay) => {
+            format!("{} unsplays {:?}", player(*id), color)
+        }
+        OperationData::Splay(id, color, direction) => format!(
+            "{} splays {:?} {}",
+            player(*id),
+            color,
+            format!("{:?}", direction).to_lowercase()
+        ),
+        OperationData::Transfer(from, to, c) => match owner(*from) {
+            Some(id) => format!(
+                "{} transfers {} from {} to {}",
+                player(id),
+                card(c),
+                place(*from, Some(id)),
+                place(*to, Some(id))
+            ),
+            None => format!(
+                "{} goes from {} to {}",
+                card(c),
+                place(*from, None),
+                place(*to, None)
+            ),
+        },
+        OperationData::Exchange(from, to, from_cards, to_cards) => {
+            let subject = owner(*from).or_else(|| owner(*to));
+            format!(
+                "{} exchanges {} in {} with {} in {}",
+                subject.map_or_else(|| "A player".to_owned(), player),
+                cards(from_cards),
+                place(*from, subject),
+                cards(to_cards),
+                place(*to, subject)
+            )
+        }
+        OperationData::SimpleOp(op, id, c, from) => {
+            let hand = Place::Player(*id, PlayerPlace::Hand);
+            let (verb, default_from) = match op {
+                SimpleOp::Draw => ("draws", Place::MainCardPile),
+                SimpleOp::Meld => ("melds", hand),
+                SimpleOp::Tuck => ("tucks", hand),
+                SimpleOp::Score => ("scores", hand),
+                SimpleOp::Return => ("returns", hand),
+                SimpleOp::DrawAndMeld => ("draws and melds", Place::MainCardPile),
+                SimpleOp::DrawAndScore => ("draws and scores", Place::MainCardPile),
+                SimpleOp::DrawAndTuck => ("draws and tucks", Place::MainCardPile),
+            };
+            let mut sentence = format!("{} {} {}", player(*id), verb, card(c));
+            if *from != default_from {
+                sentence += &format!(" from {}", place(*from, Some(*id)));
+            }
+            sentence
+        }
+        OperationData::Achieve(id, view) => {
+            format!("{} achieves {}", player(*id), achievement(view))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::{default_cards, Color},
+        card_pile::MainCardPile,
+        game::GameConfig,
+        logger::Operation,
+        player::PlayerBuilder,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn sentences() {
+        let oars = default_cards::oars();
+        let transfer = Item::Operation(Operation::Transfer(
+            Place::Player(1, PlayerPlace::Hand),
+            Place::Player(0, PlayerPlace::Hand),
+            &oars,
+        ));
+        assert_eq!(
+            sentence(&transfer, None),
+            "Player 2 transfers Oars from hand to Player 1's hand"
+        );
+        assert_eq!(
+            sentence(&transfer, Some(0)),
+            "Player 2 transfers Oars from hand to Player 1's hand"
+        );
+        assert_eq!(
+            sentence(&transfer, Some(2)),
+            "Player 2 transfers an age 1 card from hand to Player 1's hand"
+        );
+        let splay = Item::Operation(Operation::Splay(0, Color::Yellow, Splay::Right));
+        assert_eq!(sentence(&splay, None), "Player 1 splays Yellow right");
+        let draw = Item::Operation(Operation::SimpleOp(
+            SimpleOp::Draw,
+            1,
+            &oars,
+            Place::MainCardPile,
+        ));
+        assert_eq!(sentence(&draw, Some(1)), "Player 2 draws Oars");
+        assert_eq!(sentence(&draw, Some(0)), "Player 2 draws an age 1 card");
+        let action = Item::Action(0, Action::Step(NoRefStep::Execute("Oars".to_owned())));
+        assert_eq!(
+            sentence(&action, None),
+            "Player 1 takes a Dogma action with Oars"
+        );
+        let choice = Item::Action(1, Action::Executing(NoRefChoice::Card(vec![&oars])));
+        assert_eq!(sentence(&choice, Some(1)), "Player 2 chooses Oars");
+        assert_eq!(sentence(&choice, Some(0)), "Player 2 chooses 1 card");
+    }
+
+    #[test]
+    fn narrate_game() {
+        let archery = default_cards::archery();
+        let clothing = default_cards::clothing();
+        let code_of_laws = default_cards::code_of_laws();
+        let pottery = default_cards::pottery();
+        let narrator = Rc::new(RefCell::new(Narrator::new(Vec::new()).viewer(0)));
+        let observer: Rc<RefCell<dyn Observer>> = narrator.clone();
+        let mut game = GameConfig::new(vec![&archery, &clothing, &code_of_laws, &pottery])
+            .main_pile(MainCardPile::builder().draw_deck(vec![&pottery]).build())
+            .players(vec![
+                PlayerBuilder::default().board(vec![&archery]),
+                PlayerBuilder::default().hand(vec![&clothing, &code_of_laws]),
+            ])
+            .observe(&observer)
+            .build();
+        game.step(Action::Step(NoRefStep::Execute("Archery".to_owned())))
+            .unwrap();
+        game.step(Action::Executing(NoRefChoice::Card(vec![
+            "Code Of Laws".to_owned()
+        ])))
+        .unwrap();
+        let mut narrator = narrator.borrow_mut();
+        narrator.flush().unwrap();
+        assert_eq!(
+            String::from_utf8_lossy(narrator.get_ref()),
+            "Setup\n\
+             \x20 Player 1 takes a Dogma action with Archery\n\
+             \x20 Player 2 draws an age 1 card\n\
+             \x20 Player 2 chooses 1 card\n\
+             \x20 Player 2 transfers Code Of Laws from hand to Player 1's hand\n\
+             Turn 1: Player 2\n"
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! A short text notation for actions, for terminals and tests.
+//!
+//! | notation              | action                                   |
+//! |-----------------------|------------------------------------------|
+//! | `draw`                | `NoRefStep::Draw`                        |
+//! | `meld Agriculture`    | `NoRefStep::Meld`                        |
+//! | `achieve 3`           | `NoRefStep::Achieve`                     |
+//! | `dogma Archery`       | `NoRefStep::Execute`                     |
+//! | `pick Pottery, Oars`  | `NoRefChoice::Card`, `pick` for no cards |
+//! | `opp 2`               | `NoRefChoice::Opponent`                  |
+//! | `yes`, `no`           | `NoRefChoice::Yn`                        |
+//!
+//! Players are numbered from 1 as in `narrator`, so `opp 2` chooses player 1.
+//! Commands and card names are case-insensitive, and a card can be given by
+//! a prefix of its name if no other card has it.
+
+use std::{error::Error, fmt};
+
+use crate::{
+    action::{Action, NoRefChoice, NoRefStep},
+    card::{Age, Card},
+};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    UnknownCommand(String),
+    /// The command needs an argument, described here.
+    MissingArgument(&'static str),
+    /// The command takes no argument, but got this.
+    UnexpectedArgument(String),
+    InvalidNumber(String),
+    UnknownCard(String),
+    /// The name is a prefix of these cards.
+    AmbiguousCard(String, Vec<String>),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(
+                f,
+                "empty action; try draw, meld, achieve, dogma, pick, opp, yes or no"
+            ),
+            ParseError::UnknownCommand(command) => write!(
+                f,
+                "unknown command '{}'; try draw, meld, achieve, dogma, pick, opp, yes or no",
+                command
+            ),
+            ParseError::MissingArgument(argument) => write!(f, "missing {}", argument),
+            ParseError::UnexpectedArgument(argument) => {
+                write!(f, "unexpected argument '{}'", argument)
+            }
+            ParseError::InvalidNumber(number) => write!(f, "'{}' is not a valid number", number),
+            ParseError::UnknownCard(name) => write!(f, "no card named '{}'", name),
+            ParseError::AmbiguousCard(name, candidates) => {
+                write!(f, "'{}' could be any of {}", name, candidates.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Finds the card named `name`, ignoring case, or the only card whose name
+/// starts with `name`.
+pub fn find_card<'c>(name: &str, cards: &[&'c Card]) -> Result<&'c Card, ParseError> {
+    let lower = name.to_lowercase();
+    if let Some(card) = cards
+        .iter()
+        .find(|card| card.name().to_lowercase() == lower)
+    {
+        return Ok(card);
+    }
+    let candidates: Vec<&'c Card> = cards
+        .iter()
+        .filter(|card| card.name().to_lowercase().starts_with(&lower))
+        .copied()
+        .collect();
+    match candidates[..] {
+        [] => Err(ParseError::UnknownCard(name.to_owned())),
+        [card] => Ok(card),
+        _ => Err(ParseError::AmbiguousCard(
+            name.to_owned(),
+            candidates
+                .iter()
+                .map(|card| card.name().to_owned())
+                .collect(),
+        )),
+    }
+}
+
+fn card_name(argument: &str, cards: &[&Card]) -> Result<String, ParseError> {
+    if argument.is_empty() {
+        return Err(ParseError::MissingArgument("card name"));
+    }
+    Ok(find_card(argument, cards)?.name().to_owned())
+}
+
+fn number(argument: &str, what: &'static str) -> Result<usize, ParseError> {
+    if argument.is_empty() {
+        return Err(ParseError::MissingArgument(what));
+    }
+    argument
+        .parse()
+        .map_err(|_| ParseError::InvalidNumber(argument.to_owned()))
+}
+
+fn no_argument(action: Action, argument: &str) -> Result<Action, ParseError> {
+    if argument.is_empty() {
+        Ok(action)
+    } else {
+        Err(ParseError::UnexpectedArgument(argument.to_owned()))
+    }
+}
+
+/// Parses `text` in the notation, with card names among `cards`.
+pub fn parse_action(text: &str, cards: &[&Card]) -> Result<Action, ParseError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err(ParseError::Empty);
+    }
+    let (command, argument) = match text.find(char::is_whitespace) {
+        Some(index) => (&text[..index], text[index..].trim()),
+        None => (text, ""),
+    };
+    match command.to_lowercase().as_str() {
+        "draw" => no_argument(Action::Step(NoRefStep::Draw), argument),
+        "meld" => Ok(Action::Step(NoRefStep::Meld(card_name(argument, cards)?))),
+        "achieve" => {
+            let age = number(argument, "age")?;
+            if !(1..=10).contains(&age) {
+                return Err(ParseError::InvalidNumber(argument.to_owned()));
+            }
+            Ok(Action::Step(NoRefStep::Achieve(age as Age)))
+        }
+        "dogma" => Ok(Action::Step(NoRefStep::Execute(card_name(
+            argument, cards,
+        )?))),
+        "pick" => Ok(Action::Executing(NoRefChoice::Card(
+            if argument.is_empty() {
+                Vec::new()
+            } else {
+                argument
+                    .split(',')
+                    .map(|name| card_name(name.trim(), cards))
+                    .collect::<Result<_, _>>()?
+            },
+        ))),
+        "opp" => match number(argument, "player number")? {
+            0 => Err(ParseError::InvalidNumber(argument.to_owned())),
+            player => Ok(Action::Executing(NoRefChoice::Opponent(player - 1))),
+        },
+        "yes" => no_argument(Action::Executing(NoRefChoice::Yn(true)), argument),
+        "no" => no_argument(Action::Executing(NoRefChoice::Yn(false)), argument),
+        _ => Err(ParseError::UnknownCommand(command.to_owned())),
+    }
+}
+
+/// Writes `action` in the notation.
+pub fn format_action(action: &Action) -> String {
+    match action {
+        Action::Step(NoRefStep::Draw) => "draw".to_owned(),
+        Action::Step(NoRefStep::Meld(name)) => format!("meld {}", name),
+        Action::Step(NoRefStep::Achieve(age)) => format!("achieve {}", age),
+        Action::Step(NoRefStep::Execute(name)) => format!("dogma {}", name),
+        Action::Executing(NoRefChoice::Card(names)) if names.is_empty() => "pick".to_owned(),
+        Action::Executing(NoRefChoice::Card(names)) => format!("pick {}", names.join(", ")),
+        Action::Executing(NoRefChoice::Opponent(player)) => format!("opp {}", player + 1),
+        Action::Executing(NoRefChoice::Yn(true)) => "yes".to_owned(),
+        Action::Executing(NoRefChoice::Yn(false)) => "no".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::card::default_cards;
+
+    #[test]
+    fn parse_and_format() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let parse = |text| parse_action(text, &cards);
+        for (text, action) in [
+            ("draw", Action::Step(NoRefStep::Draw)),
+            (
+                "meld Agriculture",
+                Action::Step(NoRefStep::Meld("Agriculture".to_owned())),
+            ),
+            ("achieve 3", Action::Step(NoRefStep::Achieve(3))),
+            (
+                "dogma Archery",
+                Action::Step(NoRefStep::Execute("Archery".to_owned())),
+            ),
+            (
+                "pick Pottery, Oars",
+                Action::Executing(NoRefChoice::Card(vec![
+                    "Pottery".to_owned(),
+                    "Oars".to_owned(),
+                ])),
+            ),
+            ("pick", Action::Executing(NoRefChoice::Card(vec![]))),
+            ("opp 2", Action::Executing(NoRefChoice::Opponent(1))),
+            ("yes", Action::Executing(NoRefChoice::Yn(true))),
+            ("no", Action::Executing(NoRefChoice::Yn(false))),
+        ] {
+            assert_eq!(parse(text), Ok(action.clone()));
+            assert_eq!(format_action(&action), text);
+        }
+
+        // case and prefixes
+        assert_eq!(
+            parse("  MELD code of  "),
+            Ok(Action::Step(NoRefStep::Meld("Code Of Laws".to_owned())))
+        );
+        assert_eq!(parse("pick pott,oars"), parse("pick Pottery, Oars"));
+
+        // errors
+        assert_eq!(parse(""), Err(ParseError::Empty));
+        assert_eq!(
+            parse("play Oars"),
+            Err(ParseError::UnknownCommand("play".to_owned()))
+        );
+        assert_eq!(parse("meld"), Err(ParseError::MissingArgument("card name")));
+        assert_eq!(
+            parse("draw 2"),
+            Err(ParseError::UnexpectedArgument("2".to_owned()))
+        );
+        assert_eq!(
+            parse("achieve eleven"),
+            Err(ParseError::InvalidNumber("eleven".to_owned()))
+        );
+        assert_eq!(
+            parse("achieve 11"),
+            Err(ParseError::InvalidNumber("11".to_owned()))
+        );
+        assert_eq!(
+            parse("opp 0"),
+            Err(ParseError::InvalidNumber("0".to_owned()))
+        );
+        assert_eq!(
+            parse("dogma Nothing"),
+            Err(ParseError::UnknownCard("Nothing".to_owned()))
+        );
+        match parse("meld c") {
+            Err(ParseError::AmbiguousCard(name, candidates)) => {
+                assert_eq!(name, "c");
+                assert!(candidates.len() > 1);
+                assert!(candidates.iter().all(|c| c.to_lowercase().starts_with('c')));
+            }
+            result => panic!("unexpected {:?}", result),
+        }
+    }
+}
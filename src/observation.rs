@@ -1,13 +1,15 @@
 use std::cell::Ref;
 
-use serde::{Serialize, Serializer};
+use serde::{Deserialize, Serialize, Serializer};
 
 use crate::{
+    action::{Action, LegalActions, NoRefChoice, NoRefStep, MAX_LISTED_CARD_CHOICES},
     board::Board,
     card::{Achievement, Age, Card, SpecialAchievement},
-    game::PlayerId,
-    state::ExecutionObs,
+    game::{can_achieve, PlayerId},
+    state::{Choose, ExecutionObs},
     turn::Turn,
+    utils::{combinations, num_combinations},
 };
 
 // lifetime?
@@ -20,7 +22,7 @@ fn serialize_board<S: Serializer>(board: &BoardView, serializer: S) -> Result<S:
     board.serialize(serializer)
 }
 
-#[derive(Debug, Serialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(tag = "type", content = "view", rename_all = "snake_case")]
 pub enum SingleAchievementView {
     Special(SpecialAchievement),
@@ -95,14 +97,101 @@ pub enum ObsType<'a> {
 pub struct Observation<'a> {
     /// The player who is making choice.
     pub acting_player: PlayerId,
+    /// The id of the player who is observing.
+    pub main_player_id: PlayerId,
     /// The player who is observing.
     pub main_player: MainPlayerView<'a>,
     pub other_players: Vec<OtherPlayerView<'a>>,
     pub main_pile: [usize; 10],
+    /// The achievements which are not claimed yet.
+    pub achievements: AchievementView,
     pub turn: &'a Turn,
     pub obstype: ObsType<'a>,
 }
 
+impl<'a> Observation<'a> {
+    pub fn num_players(&self) -> usize {
+        self.other_players.len() + 1
+    }
+
+    /// Checks if the observer may claim the normal achievement of `age` as a step.
+    pub fn can_achieve(&self, age: Age) -> bool {
+        let player = &self.main_player;
+        can_achieve(
+            age,
+            player.board.highest_age(),
+            player.score.iter().map(|c| c.age() as usize).sum(),
+            self.achievements
+                .contains(&SingleAchievementView::Normal(age)),
+        )
+    }
+
+    /// Lists the valid actions of the observer, who should be the acting player.
+    ///
+    /// Card choices with more than `MAX_LISTED_CARD_CHOICES` valid subsets
+    /// are described by `LegalActions::CardSubsets` instead.
+    pub fn legal_actions(&self) -> LegalActions {
+        if self.acting_player != self.main_player_id {
+            return LegalActions::Listed(Vec::new());
+        }
+        match &self.obstype {
+            ObsType::Main => {
+                let player = &self.main_player;
+                let mut actions = vec![Action::Step(NoRefStep::Draw)];
+                actions.extend(
+                    player
+                        .hand
+                        .iter()
+                        .map(|card| Action::Step(NoRefStep::Meld(card.name().to_owned()))),
+                );
+                actions.extend(
+                    (1..=10)
+                        .filter(|&age| self.can_achieve(age))
+                        .map(|age| Action::Step(NoRefStep::Achieve(age))),
+                );
+                actions.extend(
+                    player
+                        .board
+                        .top_cards()
+                        .into_iter()
+                        .map(|card| Action::Step(NoRefStep::Execute(card.name().to_owned()))),
+                );
+                LegalActions::Listed(actions)
+            }
+            ObsType::Executing(obs) => match obs.state {
+                Choose::Card { ref from, .. } => {
+                    let (min_num, max_num) = obs.state.card_num_bounds().unwrap();
+                    let names: Vec<String> = from.iter().map(|c| c.name().to_owned()).collect();
+                    if num_combinations(names.len(), min_num, max_num) > MAX_LISTED_CARD_CHOICES {
+                        LegalActions::CardSubsets {
+                            min_num,
+                            max_num,
+                            from: names,
+                        }
+                    } else {
+                        LegalActions::Listed(
+                            combinations(&names, min_num, max_num)
+                                .into_iter()
+                                .map(|cards| Action::Executing(NoRefChoice::Card(cards)))
+                                .collect(),
+                        )
+                    }
+                }
+                Choose::Opponent => LegalActions::Listed(
+                    (1..self.num_players())
+                        .map(|i| (self.acting_player + i) % self.num_players())
+                        .map(|id| Action::Executing(NoRefChoice::Opponent(id)))
+                        .collect(),
+                ),
+                Choose::Yn => LegalActions::Listed(vec![
+                    Action::Executing(NoRefChoice::Yn(true)),
+                    Action::Executing(NoRefChoice::Yn(false)),
+                ]),
+            },
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct EndObservation<'a> {
     // todo: reveal achievement
@@ -156,7 +245,7 @@ mod tests {
         assert_eq!(
             to_value(&ObsType::Executing(ExecutionObs {
                 state: Choose::Opponent,
-                card: &card,
+                card: Some(&card),
             }))
             .unwrap(),
             json!({
@@ -15,6 +15,18 @@ pub struct Player<'c> {
     achievements: RefCell<VecSet<Achievement<'c>>>,
 }
 
+impl<'c> Clone for Player<'c> {
+    fn clone(&self) -> Self {
+        Player {
+            id: self.id,
+            main_board: self.main_board.clone(),
+            hand: RefCell::new(self.hand.borrow().box_clone()),
+            score_pile: RefCell::new(self.score_pile.borrow().box_clone()),
+            achievements: self.achievements.clone(),
+        }
+    }
+}
+
 impl<'c> Player<'c> {
     pub fn new(
         id: usize,
@@ -31,6 +43,14 @@ impl<'c> Player<'c> {
         }
     }
 
+    /// Sets the board, hand, score pile and achievements back to those of `from`.
+    pub fn restore(&self, from: &Player<'c>) {
+        *self.main_board.borrow_mut() = from.main_board.borrow().clone();
+        *self.hand.borrow_mut() = from.hand.borrow().box_clone();
+        *self.score_pile.borrow_mut() = from.score_pile.borrow().box_clone();
+        *self.achievements.borrow_mut() = from.achievements.borrow().clone();
+    }
+
     pub fn builder<C>() -> PlayerBuilder<'c>
     where
         C: CardSet<'c, Card> + Default + 'c,
@@ -168,7 +188,7 @@ impl<'c> PlayerBuilder<'c> {
     }
 
     pub fn splay(mut self, color: Color, direction: Splay) -> PlayerBuilder<'c> {
-        self.main_board.get_stack_mut(color).splay(direction);
+        self.main_board.get_stack_mut(color).set_splay(direction);
         self
     }
 
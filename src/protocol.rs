@@ -0,0 +1,610 @@
+//! A line-based JSON protocol for bots in other processes, in the spirit of
+//! UCI for chess.
+//!
+//! Every line is one JSON object. A controller sends commands to an
+//! `Engine`, which answers every command with exactly one response:
+//!
+//! | command                                    | response                        |
+//! |--------------------------------------------|---------------------------------|
+//! | `{"cmd":"new_game","players":2,"seed":5}`  | `{"type":"state","state":0}`    |
+//! | `{"cmd":"observe","player":1}`             | `{"type":"observation",...}`    |
+//! | `{"cmd":"legal_actions"}`                  | `{"type":"legal_actions",...}`  |
+//! | `{"cmd":"step","action":"draw"}`           | `{"type":"state","state":...}`  |
+//! | `{"cmd":"state"}`                          | `{"type":"state","state":...}`  |
+//! | `{"cmd":"watch","player":1}`               | `{"type":"ok"}`                 |
+//! | `{"cmd":"unwatch"}`                        | `{"type":"ok"}`                 |
+//! | `{"cmd":"quit"}`                           | `{"type":"ok"}`, then it stops  |
+//!
+//! A failed command is answered with `{"type":"error","message":...}`. The
+//! `seed` of `new_game` is optional. `state` is the id of the player to act,
+//! or the end observation with the winners.
+//!
+//! Actions are written as `Action` is serialized: `"draw"`,
+//! `{"meld":"Archery"}`, `{"achieve":3}`, `{"execute":"Archery"}`,
+//! `{"card":["Oars"]}`, `{"opponent":1}` and `{"yn":true}`.
+//!
+//! After `watch`, every item the game notifies is sent as
+//! `{"type":"event","event":...}` before the response of the command
+//! causing it. Events are `ItemData` with the cards hidden from the watching
+//! player as `{"age":1}`, including the cards others choose; watch with
+//! `"player":null` to see all cards.
+//!
+//! `ExternalAgent` turns the protocol around to let a process be a seat of
+//! a `Match`: it sends `game_start`, `event`, `act` and `game_end` messages,
+//! and reads an action line after each `act`.
+
+use std::{
+    cell::RefCell,
+    io::{self, BufRead, Write},
+    rc::Rc,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    action::{Action, LegalActions},
+    agent::Agent,
+    card::{Age, Card},
+    error::{InnResult, InnovationError},
+    game::{GameConfig, OuterGame, PlayerId},
+    logger::{Item, Logger, Observer, Operation},
+    narrator::{destination, is_visible},
+    observation::{GameState, Observation},
+    record::{ItemData, OperationData},
+    structure::Place,
+};
+
+/// A card as seen by a player: its name, or only its age if hidden.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SeenCard {
+    Name(String),
+    Hidden { age: Age },
+}
+
+/// An item as seen by a player.
+pub type Event = ItemData<SeenCard>;
+
+// a card moving between two places is seen if it's seen in either
+fn seen(card: &Card, from: Place, to: Place, viewer: Option<PlayerId>) -> SeenCard {
+    if is_visible(from, viewer) || is_visible(to, viewer) {
+        SeenCard::Name(card.name().to_owned())
+    } else {
+        SeenCard::Hidden { age: card.age() }
+    }
+}
+
+fn seen_all(cards: &[&Card], from: Place, to: Place, viewer: Option<PlayerId>) -> Vec<SeenCard> {
+    cards.iter().map(|c| seen(c, from, to, viewer)).collect()
+}
+
+/// `item` with the cards hidden from `viewer`, or with all cards if `None`.
+pub fn redact(item: &Item, viewer: Option<PlayerId>) -> Event {
+    match item {
+        // the chosen cards are seen by their chooser only, as in narrations
+        Item::Action(player, action) => ItemData::Action(
+            *player,
+            action.map(|card| {
+                if viewer.is_none() || viewer == Some(*player) {
+                    SeenCard::Name(card.name().to_owned())
+                } else {
+                    SeenCard::Hidden { age: card.age() }
+                }
+            }),
+        ),
+        Item::Operation(operation) => ItemData::Operation(match operation {
+            Operation::Splay(player, color, splay) => OperationData::Splay(*player, *color, *splay),
+            Operation::Transfer(from, to, card) => {
+                OperationData::Transfer(*from, *to, seen(card, *from, *to, viewer))
+            }
+            Operation::Exchange(from, to, from_cards, to_cards) => OperationData::Exchange(
+                *from,
+                *to,
+                seen_all(from_cards, *from, *to, viewer),
+                seen_all(to_cards, *from, *to, viewer),
+            ),
+            Operation::SimpleOp(op, player, card, from) => OperationData::SimpleOp(
+                op.clone(),
+                *player,
+                seen(card, *from, destination(op, *player), viewer),
+                *from,
+            ),
+            Operation::Achieve(player, view) => OperationData::Achieve(*player, view.clone()),
+        }),
+        Item::NextAction(player) => ItemData::NextAction(*player),
+        Item::ChangeTurn(last, next) => ItemData::ChangeTurn(*last, *next),
+        Item::Undo => ItemData::Undo,
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Command {
+    NewGame {
+        players: usize,
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    Observe {
+        player: PlayerId,
+    },
+    LegalActions,
+    Step {
+        action: Action,
+    },
+    State,
+    Watch {
+        player: Option<PlayerId>,
+    },
+    Unwatch,
+    Quit,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response<'a> {
+    Ok,
+    Error {
+        message: String,
+    },
+    Observation {
+        observation: &'a Observation<'a>,
+    },
+    LegalActions {
+        actions: LegalActions,
+    },
+    State {
+        state: Value,
+    },
+    Event {
+        event: Event,
+    },
+    /// The messages of `server`.
+    Created {
+        game: usize,
+    },
+    Joined {
+        game: usize,
+        seat: PlayerId,
+        token: String,
+    },
+    /// The messages of `ExternalAgent`, which `server` sends too.
+    GameStart {
+        player: PlayerId,
+        num_players: usize,
+    },
+    Act {
+        observation: &'a Observation<'a>,
+    },
+    GameEnd {
+        winners: Vec<PlayerId>,
+    },
+}
+
+impl Response<'_> {
+    fn error(message: impl ToString) -> Response<'static> {
+        Response::Error {
+            message: message.to_string(),
+        }
+    }
+}
+
+pub(crate) fn send<W: Write>(writer: &mut W, response: &Response) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, response)?;
+    writeln!(writer)?;
+    writer.flush()
+}
+
+/// A game of `cards` with the normal and special achievements, not started,
+/// and the logger observing it. The seed is drawn at random if not given.
+pub(crate) fn logged_game<'c>(
+    cards: Vec<&'c Card>,
+    num_players: usize,
+    seed: Option<u64>,
+) -> InnResult<(OuterGame<'c>, Rc<RefCell<Logger<'c>>>)> {
+    if !(2..=4).contains(&num_players) {
+        return Err(InnovationError::WrongPlayerNum);
+    }
+    let config =
+        GameConfig::standard(cards, seed.unwrap_or_else(rand::random)).default_players(num_players);
+    let logger = Rc::new(RefCell::new(Logger::new()));
+    let observer: Rc<RefCell<dyn Observer<'c> + 'c>> = logger.clone();
+    let game = config.observe(&observer).build();
+    drop(observer);
+    logger.borrow_mut().start_game(game.players());
+    Ok((game, logger))
+}
+
+// a game with the logger observing it
+struct Session<'c> {
+    game: OuterGame<'c>,
+    logger: Rc<RefCell<Logger<'c>>>,
+    delivered: usize,
+    // the last `GameState`, which borrows the game
+    state: Value,
+}
+
+fn state_value(state: GameState) -> Value {
+    serde_json::to_value(state).expect("Game state should be serializable.")
+}
+
+/// Runs the commands of a controller on games with a fixed card list.
+pub struct Engine<'c> {
+    cards: Vec<&'c Card>,
+    session: Option<Session<'c>>,
+    // `Some(viewer)` if watching
+    watching: Option<Option<PlayerId>>,
+}
+
+impl<'c> Engine<'c> {
+    pub fn new(cards: Vec<&'c Card>) -> Engine<'c> {
+        Engine {
+            cards,
+            session: None,
+            watching: None,
+        }
+    }
+
+    /// Answers the commands read from `reader` until `quit` or the end of
+    /// the input.
+    pub fn run<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if !self.handle(&line, &mut writer)? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers the command in `line`, returning `false` after `quit`.
+    pub fn handle<W: Write>(&mut self, line: &str, writer: &mut W) -> io::Result<bool> {
+        let command = match serde_json::from_str(line) {
+            Ok(command) => command,
+            Err(e) => {
+                send(writer, &Response::error(e))?;
+                return Ok(true);
+            }
+        };
+        let quit = command == Command::Quit;
+        match command {
+            Command::NewGame { players, seed } => {
+                let result = self.new_game(players, seed);
+                self.send_events(writer)?;
+                match result {
+                    Ok(()) => self.send_state(writer)?,
+                    Err(e) => send(writer, &Response::error(format!("{:?}", e)))?,
+                }
+            }
+            Command::Step { action } => {
+                let session = match &mut self.session {
+                    Some(session) => session,
+                    None => {
+                        send(writer, &Response::error("no game"))?;
+                        return Ok(true);
+                    }
+                };
+                let result = session.game.step(action).map(state_value);
+                let result = result.map(|state| session.state = state);
+                self.send_events(writer)?;
+                match result {
+                    Ok(()) => self.send_state(writer)?,
+                    Err(e) => send(writer, &Response::error(format!("{:?}", e)))?,
+                }
+            }
+            Command::Observe { player } => match &self.session {
+                Some(session) if player < session.game.players().num_players() => {
+                    let observation = session.game.observe(player);
+                    send(
+                        writer,
+                        &Response::Observation {
+                            observation: &observation,
+                        },
+                    )?
+                }
+                Some(_) => send(writer, &Response::error("no such player"))?,
+                None => send(writer, &Response::error("no game"))?,
+            },
+            Command::LegalActions => match &self.session {
+                Some(session) => send(
+                    writer,
+                    &Response::LegalActions {
+                        actions: session.game.legal_actions(),
+                    },
+                )?,
+                None => send(writer, &Response::error("no game"))?,
+            },
+            Command::State => self.send_state(writer)?,
+            Command::Watch { player } => {
+                self.watching = Some(player);
+                send(writer, &Response::Ok)?;
+            }
+            Command::Unwatch => {
+                self.watching = None;
+                send(writer, &Response::Ok)?;
+            }
+            Command::Quit => send(writer, &Response::Ok)?,
+        }
+        Ok(!quit)
+    }
+
+    fn new_game(&mut self, num_players: usize, seed: Option<u64>) -> InnResult<()> {
+        let (mut game, logger) = logged_game(self.cards.clone(), num_players, seed)?;
+        let result = game.start().map(state_value);
+        let (state, result) = match result {
+            Ok(state) => (state, Ok(())),
+            Err(e) => (Value::Null, Err(e)),
+        };
+        self.session = Some(Session {
+            game,
+            logger,
+            delivered: 0,
+            state,
+        });
+        result
+    }
+
+    fn send_state<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match &self.session {
+            Some(session) => send(
+                writer,
+                &Response::State {
+                    state: session.state.clone(),
+                },
+            ),
+            None => send(writer, &Response::error("no game")),
+        }
+    }
+
+    fn send_events<W: Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        let session = match &mut self.session {
+            Some(session) => session,
+            None => return Ok(()),
+        };
+        let logger = session.logger.borrow();
+        let items = &logger
+            .current_game()
+            .expect("The logger is started with the game.")
+            .items[session.delivered..];
+        if let Some(viewer) = self.watching {
+            for item in items {
+                send(
+                    writer,
+                    &Response::Event {
+                        event: redact(item, viewer),
+                    },
+                )?;
+            }
+        }
+        session.delivered += items.len();
+        Ok(())
+    }
+}
+
+/// A seat played by another process, which reads the messages of the seat
+/// from `writer` and answers each `act` with an action line to `reader`.
+///
+/// # Panics
+///
+/// `act` panics if `reader` ends or fails; writing failures panic too.
+pub struct ExternalAgent<R: BufRead, W: Write> {
+    reader: R,
+    writer: W,
+    player: PlayerId,
+}
+
+impl<R: BufRead, W: Write> ExternalAgent<R, W> {
+    pub fn new(reader: R, writer: W) -> ExternalAgent<R, W> {
+        ExternalAgent {
+            reader,
+            writer,
+            player: 0,
+        }
+    }
+
+    fn send(&mut self, response: &Response) {
+        send(&mut self.writer, response).expect("Failed to write to the external seat.");
+    }
+}
+
+impl<R: BufRead, W: Write> Agent for ExternalAgent<R, W> {
+    fn act(&mut self, obs: &Observation) -> Action {
+        self.send(&Response::Act { observation: obs });
+        loop {
+            let mut line = String::new();
+            match self.reader.read_line(&mut line) {
+                Ok(0) => panic!("The external seat closed its output."),
+                Ok(_) => {}
+                Err(e) => panic!("Failed to read from the external seat: {}", e),
+            }
+            match serde_json::from_str(&line) {
+                Ok(action) => return action,
+                Err(e) => self.send(&Response::error(e)),
+            }
+        }
+    }
+
+    fn on_game_start(&mut self, player: PlayerId, num_players: usize) {
+        self.player = player;
+        self.send(&Response::GameStart {
+            player,
+            num_players,
+        });
+    }
+
+    fn on_event(&mut self, event: &Event) {
+        self.send(&Response::Event {
+            event: event.clone(),
+        });
+    }
+
+    fn on_game_end(&mut self, winners: &[PlayerId]) {
+        self.send(&Response::GameEnd {
+            winners: winners.to_vec(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{action::NoRefChoice, card::default_cards};
+    use serde_json::json;
+
+    fn run(engine: &mut Engine, commands: &[Value]) -> Vec<Value> {
+        let mut output = Vec::new();
+        for command in commands {
+            engine.handle(&command.to_string(), &mut output).unwrap();
+        }
+        String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn engine() {
+        let cards = default_cards::default_cards();
+        let mut engine = Engine::new(cards.iter().collect());
+        let responses = run(
+            &mut engine,
+            &[
+                json!({"cmd": "state"}),
+                json!({"cmd": "new_game", "players": 5}),
+                json!({"cmd": "watch", "player": 1}),
+                json!({"cmd": "new_game", "players": 2, "seed": 5}),
+            ],
+        );
+        assert_eq!(responses[0]["type"], "error");
+        assert_eq!(responses[1]["type"], "error");
+        assert_eq!(responses[2], json!({"type": "ok"}));
+        assert_eq!(
+            responses.last().unwrap(),
+            &json!({"type": "state", "state": 0})
+        );
+        // player 1 sees the cards drawn by itself only
+        let events = &responses[3..responses.len() - 1];
+        assert!(events.iter().all(|r| r["type"] == "event"));
+        let draws: Vec<&Value> = events
+            .iter()
+            .map(|r| &r["event"]["operation"]["transfer"][2])
+            .filter(|card| !card.is_null())
+            .collect();
+        assert_eq!(draws.len(), 4);
+        assert_eq!(draws[0], &json!({"age": 1}));
+        assert!(draws[2].is_string());
+
+        let responses = run(
+            &mut engine,
+            &[
+                json!({"cmd": "unwatch"}),
+                json!({"cmd": "legal_actions"}),
+                json!({"cmd": "observe", "player": 0}),
+                json!({"cmd": "observe", "player": 2}),
+                json!({"cmd": "step", "action": "draw"}),
+                json!("nonsense"),
+            ],
+        );
+        let meld = &responses[1]["actions"]["listed"][0];
+        assert_eq!(responses[2]["type"], "observation");
+        assert_eq!(responses[2]["observation"]["main_player_id"], 0);
+        assert_eq!(responses[3]["type"], "error");
+        assert_eq!(responses[4]["type"], "error");
+        assert_eq!(responses[5]["type"], "error");
+
+        let responses = run(
+            &mut engine,
+            &[
+                json!({"cmd": "step", "action": meld}),
+                json!({"cmd": "quit"}),
+            ],
+        );
+        assert_eq!(
+            responses,
+            vec![json!({"type": "state", "state": 1}), json!({"type": "ok"})]
+        );
+        let mut output = Vec::new();
+        assert!(!engine.handle("{\"cmd\":\"quit\"}", &mut output).unwrap());
+    }
+
+    #[test]
+    fn hidden_choices() {
+        let cards = default_cards::default_cards();
+        let mut engine = Engine::new(cards.iter().collect());
+        let responses = run(
+            &mut engine,
+            &[
+                json!({"cmd": "new_game", "players": 2, "seed": 5}),
+                json!({"cmd": "legal_actions"}),
+            ],
+        );
+        let choice = &responses[1]["actions"]["listed"][0];
+        let name = &choice["card"][0];
+        assert!(name.is_string());
+        let responses = run(
+            &mut engine,
+            &[
+                json!({"cmd": "watch", "player": 1}),
+                json!({"cmd": "step", "action": choice}),
+            ],
+        );
+        // player 1 sees that player 0 chooses an age 1 card
+        assert_eq!(
+            responses[1],
+            json!({"type": "event", "event": {"action": [0, {"card": [{"age": 1}]}]}})
+        );
+        // and sees it once it's melded to the board
+        assert_eq!(&responses[2]["event"]["operation"]["transfer"][2], name);
+
+        let oars = default_cards::oars();
+        let item = Item::Action(1, Action::Executing(NoRefChoice::Card(vec![&oars])));
+        let seen = |viewer| redact(&item, viewer);
+        let hidden = SeenCard::Hidden { age: 1 };
+        let named = SeenCard::Name("Oars".to_owned());
+        assert_eq!(
+            seen(Some(0)),
+            ItemData::Action(1, Action::Executing(NoRefChoice::Card(vec![hidden])))
+        );
+        assert_eq!(
+            seen(Some(1)),
+            ItemData::Action(1, Action::Executing(NoRefChoice::Card(vec![named.clone()])))
+        );
+        assert_eq!(
+            seen(None),
+            ItemData::Action(1, Action::Executing(NoRefChoice::Card(vec![named])))
+        );
+    }
+
+    #[test]
+    fn external_agent() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let mut game = GameConfig::standard(cards, 2).build();
+        game.start().unwrap();
+        let obs = game.observe(0);
+        let name = obs.main_player.hand[0].name().to_owned();
+        let action = Action::Executing(NoRefChoice::Card(vec![name]));
+        let input = format!("oops\n{}\n", serde_json::to_string(&action).unwrap());
+        let mut output = Vec::new();
+        let mut agent = ExternalAgent::new(input.as_bytes(), &mut output);
+        agent.on_game_start(0, 2);
+        assert_eq!(agent.act(&obs), action);
+        let messages: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            messages[0],
+            json!({"type": "game_start", "player": 0, "num_players": 2})
+        );
+        assert_eq!(messages[1]["type"], "act");
+        assert_eq!(messages[2]["type"], "error");
+        assert_eq!(messages.len(), 3);
+    }
+}
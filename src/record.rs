@@ -0,0 +1,247 @@
+//! Writing game logs to files and reading them back.
+//!
+//! The items of `logger` refer to cards, so they are written as the owned
+//! `ItemData` and `OperationData`, with cards by name, as in `save`.
+
+use std::{collections::BTreeMap, convert::TryInto};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::Action,
+    card::{Card, Color, Splay},
+    card_pile::CardOrder,
+    error::{InnResult, InnovationError},
+    game::PlayerId,
+    logger::{Game, Item, Operation, SimpleOp},
+    observation::SingleAchievementView,
+    save::{find, find_all, names, AchievementData},
+    structure::Place,
+};
+
+/// The version of `LogFile` written by this crate.
+pub const LOG_VERSION: u32 = 1;
+
+/// The finished games of a logger, as returned by `Logger::save`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LogFile {
+    pub version: u32,
+    pub games: Vec<GameRecord>,
+}
+
+impl LogFile {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Log file should be serializable.")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<LogFile> {
+        serde_json::from_str(json)
+    }
+}
+
+/// A `logger::Game`, with cards by name.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub num_players: usize,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    pub seed: Option<u64>,
+    /// The draw piles of ages 1 to 10 at the start, from top to bottom.
+    pub initial_cards: Vec<Vec<String>>,
+    pub achievements: Vec<AchievementData>,
+    pub items: Vec<ItemData>,
+    pub winners: Option<Vec<PlayerId>>,
+}
+
+/// A `logger::Item`, with cards as `C`, which are names in logs.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemData<C = String> {
+    Action(PlayerId, Action<C>),
+    Operation(OperationData<C>),
+    NextAction(PlayerId),
+    ChangeTurn(PlayerId, PlayerId),
+    Undo,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationData<C = String> {
+    Splay(PlayerId, Color, Splay),
+    Transfer(Place, Place, C),
+    Exchange(Place, Place, Vec<C>, Vec<C>),
+    SimpleOp(SimpleOp, PlayerId, C, Place),
+    Achieve(PlayerId, SingleAchievementView),
+}
+
+impl OperationData {
+    pub fn new(operation: &Operation) -> OperationData {
+        match operation {
+            Operation::Splay(player, color, splay) => OperationData::Splay(*player, *color, *splay),
+            Operation::Transfer(from, to, card) => {
+                OperationData::Transfer(*from, *to, card.name().to_owned())
+            }
+            Operation::Exchange(from, to, from_cards, to_cards) => OperationData::Exchange(
+                *from,
+                *to,
+                names(from_cards.iter().copied()),
+                names(to_cards.iter().copied()),
+            ),
+            Operation::SimpleOp(op, player, card, from) => {
+                OperationData::SimpleOp(op.clone(), *player, card.name().to_owned(), *from)
+            }
+            Operation::Achieve(player, view) => OperationData::Achieve(*player, view.clone()),
+        }
+    }
+
+    pub fn load<'c>(&self, cards: &[&'c Card]) -> InnResult<Operation<'c>> {
+        Ok(match self {
+            OperationData::Splay(player, color, splay) => Operation::Splay(*player, *color, *splay),
+            OperationData::Transfer(from, to, card) => {
+                Operation::Transfer(*from, *to, find(cards, card)?)
+            }
+            OperationData::Exchange(from, to, from_cards, to_cards) => Operation::Exchange(
+                *from,
+                *to,
+                find_all(cards, from_cards)?,
+                find_all(cards, to_cards)?,
+            ),
+            OperationData::SimpleOp(op, player, card, from) => {
+                Operation::SimpleOp(op.clone(), *player, find(cards, card)?, *from)
+            }
+            OperationData::Achieve(player, view) => Operation::Achieve(*player, view.clone()),
+        })
+    }
+}
+
+impl ItemData {
+    pub fn new(item: &Item) -> ItemData {
+        match item {
+            Item::Action(player, action) => ItemData::Action(*player, action.no_ref()),
+            Item::Operation(operation) => ItemData::Operation(OperationData::new(operation)),
+            Item::NextAction(player) => ItemData::NextAction(*player),
+            Item::ChangeTurn(last, next) => ItemData::ChangeTurn(*last, *next),
+            Item::Undo => ItemData::Undo,
+        }
+    }
+
+    pub fn load<'c>(&self, cards: &[&'c Card]) -> InnResult<Item<'c>> {
+        Ok(match self {
+            ItemData::Action(player, action) => {
+                Item::Action(*player, action.try_map(|name| find(cards, name))?)
+            }
+            ItemData::Operation(operation) => Item::Operation(operation.load(cards)?),
+            ItemData::NextAction(player) => Item::NextAction(*player),
+            ItemData::ChangeTurn(last, next) => Item::ChangeTurn(*last, *next),
+            ItemData::Undo => Item::Undo,
+        })
+    }
+}
+
+impl GameRecord {
+    pub fn new(game: &Game) -> GameRecord {
+        GameRecord {
+            num_players: game.num_players,
+            metadata: game.metadata.clone(),
+            seed: game.seed,
+            initial_cards: game
+                .initial_cards
+                .iter()
+                .map(|cards| names(cards.iter().copied()))
+                .collect(),
+            achievements: game.achievements.iter().map(AchievementData::new).collect(),
+            items: game.items.iter().map(ItemData::new).collect(),
+            winners: game.winners.clone(),
+        }
+    }
+
+    pub fn load<'c>(&self, cards: &[&'c Card]) -> InnResult<Game<'c>> {
+        let piles = self
+            .initial_cards
+            .iter()
+            .map(|pile| find_all(cards, pile))
+            .collect::<InnResult<Vec<_>>>()?;
+        let initial_cards: CardOrder<'c> =
+            piles.try_into().map_err(|_| InnovationError::InvalidLog)?;
+        Ok(Game {
+            num_players: self.num_players,
+            metadata: self.metadata.clone(),
+            initial_cards,
+            achievements: self
+                .achievements
+                .iter()
+                .map(|a| a.load(cards))
+                .collect::<InnResult<_>>()?,
+            seed: self.seed,
+            items: self
+                .items
+                .iter()
+                .map(|item| item.load(cards))
+                .collect::<InnResult<_>>()?,
+            winners: self.winners.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{Agent, HeuristicAgent, Match, RandomAgent},
+        card::default_cards,
+        game::GameConfig,
+        logger::Logger,
+        replay::Replayer,
+    };
+
+    #[test]
+    fn log_file() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let config = || GameConfig::standard(cards.clone(), 8);
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(RandomAgent::new(4)),
+            Box::new(HeuristicAgent::new()),
+        ];
+        let logger = Match::new(agents).play(config()).unwrap().logger;
+        let mut file = logger.save();
+        file.games[0]
+            .metadata
+            .insert("event".to_owned(), "test".to_owned());
+        let file = LogFile::from_json(&file.to_json()).unwrap();
+
+        // another instance of the card list
+        let own_cards = default_cards::default_cards();
+        let own_cards: Vec<_> = own_cards.iter().collect();
+        let loaded = Logger::load(&own_cards, &file).unwrap();
+        let (game, loaded_game) = (&logger.history()[0], &loaded.history()[0]);
+        assert_eq!(loaded_game.items, game.items);
+        assert_eq!(loaded_game.initial_cards, game.initial_cards);
+        assert_eq!(loaded_game.achievements, game.achievements);
+        assert_eq!(loaded_game.seed, Some(8));
+        assert_eq!(loaded_game.metadata["event"], "test");
+        assert!(loaded_game.winners.is_some());
+        assert_eq!(loaded_game.winners, game.winners);
+
+        // the loaded record replays to the same end
+        let replayed = Replayer::new(loaded_game, config())
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert_eq!(replayed.winners(), game.winners.as_deref());
+
+        let mut file = file;
+        let mut missing_pile = file.clone();
+        missing_pile.games[0].initial_cards.pop();
+        assert!(matches!(
+            Logger::load(&own_cards, &missing_pile),
+            Err(InnovationError::InvalidLog)
+        ));
+        file.version = LOG_VERSION + 1;
+        assert!(matches!(
+            Logger::load(&own_cards, &file),
+            Err(InnovationError::UnsupportedLogVersion(_))
+        ));
+        assert!(Logger::load(&own_cards[1..], &logger.save()).is_err());
+    }
+}
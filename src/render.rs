@@ -0,0 +1,239 @@
+//! Rendering observations as text, for terminals.
+//!
+//! Players are numbered from 1 as in `narrator`, and prompts use the
+//! commands of `notation`.
+
+use std::fmt::Write;
+
+use strum::IntoEnumIterator;
+
+use crate::{
+    board::Board,
+    card::{Age, Card, Color, Icon, Splay},
+    game::PlayerId,
+    observation::{ObsType, Observation, SingleAchievementView},
+    state::Choose,
+};
+
+fn player(id: PlayerId) -> String {
+    format!("Player {}", id + 1)
+}
+
+fn card(card: &Card) -> String {
+    format!("{} ({})", card.name(), card.age())
+}
+
+fn list(items: Vec<String>) -> String {
+    if items.is_empty() {
+        "none".to_owned()
+    } else {
+        items.join(", ")
+    }
+}
+
+pub(crate) fn achievement(view: &SingleAchievementView) -> String {
+    match view {
+        SingleAchievementView::Normal(age) => age.to_string(),
+        SingleAchievementView::Special(special) => format!("{:?}", special),
+    }
+}
+
+fn achievements(views: &[SingleAchievementView]) -> String {
+    list(views.iter().map(achievement).collect())
+}
+
+fn points(ages: impl Iterator<Item = Age>) -> usize {
+    ages.map(|age| age as usize).sum()
+}
+
+fn write_board(out: &mut String, board: &Board) {
+    let mut empty = true;
+    for color in Color::iter() {
+        let stack = board.get_stack(color);
+        let top_card = match stack.top_card() {
+            Some(card) => card,
+            None => continue,
+        };
+        empty = false;
+        let splay = match stack.splay_direction() {
+            Splay::NoSplay => String::new(),
+            direction => format!(", splayed {:?}", direction).to_lowercase(),
+        };
+        let icons = top_card
+            .icons()
+            .iter()
+            .filter(|&&icon| icon != Icon::Empty)
+            .map(|icon| format!("{:?}", icon))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            out,
+            "    {:?} ({} cards{}): {} [{}]",
+            color,
+            stack.len(),
+            splay,
+            card(top_card),
+            icons
+        )
+        .unwrap();
+    }
+    if empty {
+        writeln!(out, "    empty").unwrap();
+    }
+    let count = board.icon_count();
+    let icons: Vec<String> = Icon::iter()
+        .map(|icon| format!("{:?} {}", icon, count[&icon]))
+        .collect();
+    writeln!(out, "  Icons: {}", icons.join(", ")).unwrap();
+}
+
+fn prompt(obs: &Observation) -> String {
+    match &obs.obstype {
+        ObsType::Main => "draw, meld CARD, achieve AGE or dogma CARD".to_owned(),
+        ObsType::Executing(execution) => {
+            let prefix = match execution.card {
+                Some(card) => format!("{}: ", card.name()),
+                None => String::new(),
+            };
+            let question = match &execution.state {
+                Choose::Card { from, .. } => {
+                    let (min_num, max_num) = execution.state.card_num_bounds().unwrap();
+                    let num = if min_num == max_num {
+                        min_num.to_string()
+                    } else {
+                        format!("{} to {}", min_num, max_num)
+                    };
+                    format!(
+                        "choose {} of {} (pick CARD, ...)",
+                        num,
+                        list(from.iter().map(|&c| card(c)).collect())
+                    )
+                }
+                Choose::Opponent => "choose an opponent (opp PLAYER)".to_owned(),
+                Choose::Yn => "yes or no".to_owned(),
+            };
+            prefix + &question
+        }
+    }
+}
+
+/// Renders what the main player of `obs` sees, ending with a prompt if
+/// they are the acting player.
+pub fn render_observation(obs: &Observation) -> String {
+    let mut out = String::new();
+    let num_players = obs.num_players();
+    writeln!(
+        out,
+        "{}'s turn, {} to act",
+        player(obs.turn.player_id()),
+        player(obs.acting_player)
+    )
+    .unwrap();
+    let pile: Vec<String> = obs
+        .main_pile
+        .iter()
+        .enumerate()
+        .map(|(age, num)| format!("{}:{}", age + 1, num))
+        .collect();
+    writeln!(out, "Draw piles: {}", pile.join(" ")).unwrap();
+    writeln!(
+        out,
+        "Available achievements: {}",
+        achievements(&obs.achievements)
+    )
+    .unwrap();
+
+    for (i, other) in obs.other_players.iter().enumerate() {
+        let id = (obs.main_player_id + 1 + i) % num_players;
+        writeln!(out, "\n{}", player(id)).unwrap();
+        writeln!(out, "  Board:").unwrap();
+        write_board(&mut out, &other.board);
+        writeln!(
+            out,
+            "  Hand: {}",
+            list(
+                other
+                    .hand
+                    .iter()
+                    .map(|age| format!("age {}", age))
+                    .collect()
+            )
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "  Score: {} points in {} cards",
+            points(other.score.iter().copied()),
+            other.score.len()
+        )
+        .unwrap();
+        writeln!(out, "  Achievements: {}", achievements(&other.achievements)).unwrap();
+    }
+
+    let main = &obs.main_player;
+    writeln!(out, "\nYou ({})", player(obs.main_player_id)).unwrap();
+    writeln!(out, "  Board:").unwrap();
+    write_board(&mut out, &main.board);
+    writeln!(
+        out,
+        "  Hand: {}",
+        list(main.hand.iter().map(|&c| card(c)).collect())
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "  Score: {} points: {}",
+        points(main.score.iter().map(|c| c.age())),
+        list(main.score.iter().map(|&c| card(c)).collect())
+    )
+    .unwrap();
+    writeln!(out, "  Achievements: {}", achievements(&main.achievements)).unwrap();
+
+    if obs.acting_player == obs.main_player_id {
+        writeln!(out, "\n> {}", prompt(obs)).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        card::default_cards,
+        game::{GameConfig, OuterGame},
+        notation::parse_action,
+    };
+
+    #[test]
+    fn render() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let mut game = GameConfig::standard(cards.clone(), 5).build();
+        game.start().unwrap();
+        let first_meld = |game: &OuterGame, id| match &game.observe(id).obstype {
+            ObsType::Executing(execution) => match &execution.state {
+                Choose::Card { from, .. } => from[0].name().to_owned(),
+                _ => unreachable!(),
+            },
+            ObsType::Main => unreachable!(),
+        };
+        let text = render_observation(&game.observe(0));
+        assert!(text.contains("You (Player 1)"));
+        assert!(text.contains("  Hand: age 1, age 1"));
+        assert!(text.contains("choose 1 of "));
+        assert!(text.contains("Available achievements: Monument, Empire, World, Wonder, Universe, 1, 2, "));
+
+        // melding the first cards reveals them on both boards
+        let name = first_meld(&game, 0);
+        game.step(parse_action(&format!("pick {}", name), &cards).unwrap())
+            .unwrap();
+        let name2 = first_meld(&game, 1);
+        game.step(parse_action(&format!("pick {}", name2), &cards).unwrap())
+            .unwrap();
+        let text = render_observation(&game.observe(1));
+        assert!(text.contains(&format!("{} (1) [", name)));
+        assert!(text.contains(&format!("{} (1) [", name2)));
+        assert!(text.contains("  Hand: age 1\n"));
+        assert!(text.contains("Icons: Castle "));
+    }
+}
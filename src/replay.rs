@@ -0,0 +1,221 @@
+//! Replaying games recorded by `logger::Logger`.
+//!
+//! A record holds the draw piles and achievements at the start, and every
+//! item the game notified. Replaying rebuilds the game with the same piles,
+//! applies the recorded actions again and checks that the game notifies the
+//! same items as recorded.
+
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    action::Action,
+    card_pile::MainCardPile,
+    error::{InnResult, InnovationError},
+    game::{GameConfig, OuterGame},
+    logger::{Game, Item, Logger, Observer},
+};
+
+pub struct Replayer<'c, 'r> {
+    record: &'r Game<'c>,
+    game: OuterGame<'c>,
+    logger: Rc<RefCell<Logger<'c>>>,
+    // indices of the recorded actions in the items
+    actions: Vec<usize>,
+    position: usize,
+}
+
+impl<'c, 'r> Replayer<'c, 'r> {
+    /// Starts replaying `record`, stopping before its first action.
+    ///
+    /// `config` should have the players and turn the game was played with;
+    /// its main card pile is replaced by the one in `record`.
+    pub fn new(record: &'r Game<'c>, config: GameConfig<'c>) -> InnResult<Replayer<'c, 'r>> {
+        let deck = record.initial_cards.iter().flatten().copied().collect();
+        let pile = MainCardPile::new(deck, record.achievements.iter().copied());
+        let logger = Rc::new(RefCell::new(Logger::new()));
+        let observer: Rc<RefCell<dyn Observer<'c> + 'c>> = logger.clone();
+        let mut game = config.main_pile(pile).observe(&observer).build();
+        drop(observer);
+        if game.players().num_players() != record.num_players {
+            return Err(InnovationError::WrongPlayerNum);
+        }
+        logger.borrow_mut().start_game(game.players());
+        game.start()?;
+        let replayer = Replayer {
+            record,
+            game,
+            logger,
+            actions: record
+                .items
+                .iter()
+                .enumerate()
+                .filter(|(_, item)| matches!(item, Item::Action(..)))
+                .map(|(index, _)| index)
+                .collect(),
+            position: 0,
+        };
+        replayer.check()?;
+        Ok(replayer)
+    }
+
+    /// The number of actions in the record.
+    pub fn num_actions(&self) -> usize {
+        self.actions.len()
+    }
+
+    /// The number of actions applied so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn game(&self) -> &OuterGame<'c> {
+        &self.game
+    }
+
+    pub fn into_game(self) -> OuterGame<'c> {
+        self.game
+    }
+
+    /// Applies the next recorded action, returning it, or `None` if all
+    /// actions are applied.
+    pub fn step(&mut self) -> InnResult<Option<Action>> {
+        let index = match self.actions.get(self.position) {
+            Some(&index) => index,
+            None => return Ok(None),
+        };
+        let action = match &self.record.items[index] {
+            Item::Action(_, action) => action.no_ref(),
+            _ => unreachable!("Only actions are indexed."),
+        };
+        self.game.step(action.clone())?;
+        self.position += 1;
+        self.check()?;
+        Ok(Some(action))
+    }
+
+    /// Applies the recorded actions until `position` of them are applied.
+    ///
+    /// Earlier positions can't be reached again, and are
+    /// `InnovationError::InvalidAction` like positions past the last action,
+    /// leaving the replayer where it was; replay from a new `Replayer` instead.
+    pub fn replay_to(&mut self, position: usize) -> InnResult<()> {
+        if position < self.position || position > self.num_actions() {
+            return Err(InnovationError::InvalidAction);
+        }
+        while self.position < position {
+            if self.step()?.is_none() {
+                return Err(InnovationError::InvalidAction);
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the remaining actions and checks that nothing recorded is
+    /// left out, returning the final game.
+    pub fn finish(mut self) -> InnResult<OuterGame<'c>> {
+        while self.step()?.is_some() {}
+        let replayed = self.num_replayed();
+        if replayed != self.record.items.len() {
+            return Err(InnovationError::ReplayMismatch(replayed));
+        }
+        Ok(self.game)
+    }
+
+    fn num_replayed(&self) -> usize {
+        self.logger
+            .borrow()
+            .current_game()
+            .expect("The logger is started with the game.")
+            .items
+            .len()
+    }
+
+    // the items notified so far should be the recorded ones
+    fn check(&self) -> InnResult<()> {
+        let logger = self.logger.borrow();
+        let items = &logger
+            .current_game()
+            .expect("The logger is started with the game.")
+            .items;
+        match items
+            .iter()
+            .zip(self.record.items.iter())
+            .position(|(replayed, recorded)| replayed != recorded)
+        {
+            Some(index) => Err(InnovationError::ReplayMismatch(index)),
+            None if items.len() > self.record.items.len() => {
+                Err(InnovationError::ReplayMismatch(self.record.items.len()))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{Agent, Evaluation, GreedyAgent, Match, RandomAgent},
+        card::default_cards,
+        logger::Operation,
+    };
+
+    #[test]
+    fn replay_match() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let config = || GameConfig::standard(cards.clone(), 3).default_players(3);
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(RandomAgent::new(1)),
+            Box::new(GreedyAgent::new(Evaluation::default(), 2)),
+            Box::new(RandomAgent::new(3)),
+        ];
+        let result = Match::new(agents).play(config()).unwrap();
+        let record = &result.logger.history()[0];
+
+        // to the end
+        let game = Replayer::new(record, config()).unwrap().finish().unwrap();
+        assert_eq!(game.winners(), Some(&result.winners[..]));
+
+        // stopping halfway, then going on
+        let mut replayer = Replayer::new(record, config()).unwrap();
+        let half = replayer.num_actions() / 2;
+        replayer.replay_to(half).unwrap();
+        assert_eq!(replayer.position(), half);
+        // not backwards
+        assert!(matches!(
+            replayer.replay_to(half - 1),
+            Err(InnovationError::InvalidAction)
+        ));
+        assert_eq!(replayer.position(), half);
+        let mut played = Replayer::new(record, config()).unwrap();
+        for _ in 0..half {
+            played.step().unwrap();
+        }
+        assert_eq!(
+            serde_json::to_value(replayer.game().observe(0)).unwrap(),
+            serde_json::to_value(played.game().observe(0)).unwrap()
+        );
+        assert!(replayer.finish().is_ok());
+        // nor past the last action
+        let end = played.num_actions() + 1;
+        assert!(matches!(
+            played.replay_to(end),
+            Err(InnovationError::InvalidAction)
+        ));
+        assert_eq!(played.position(), half);
+
+        // an operation that didn't happen
+        let mut tampered = record.clone();
+        let index = tampered
+            .items
+            .iter()
+            .position(|item| matches!(item, Item::Operation(Operation::SimpleOp(..))))
+            .unwrap();
+        tampered.items[index] = Item::NextAction(7);
+        assert!(matches!(
+            Replayer::new(&tampered, config()).and_then(Replayer::finish),
+            Err(InnovationError::ReplayMismatch(i)) if i == index
+        ));
+    }
+}
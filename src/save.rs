@@ -0,0 +1,392 @@
+//! Saving games and loading them back.
+//!
+//! Cards are saved by name, so a game can be loaded with another instance of
+//! its card list. An execution in progress is saved as the players before it
+//! and the choices made so far, and is run again when loading; see
+//! `state::Execution`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum::IntoEnumIterator;
+
+use crate::{
+    action::NoRefChoice,
+    card::{Achievement, Card, Color, SpecialAchievement, Splay},
+    card_pile::MainCardPile,
+    error::{InnResult, InnovationError},
+    game::{PlayerId, Players},
+    logger::Subject,
+    player::{Player, PlayerBuilder},
+    state::{Execution, Root},
+    turn::Turn,
+};
+
+/// The version of `SaveData` written by this crate.
+pub const SAVE_VERSION: u32 = 1;
+
+/// A saved game, as returned by `OuterGame::save`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    /// The players, or the players before the execution in progress.
+    pub players: PlayersData,
+    pub turn: Turn,
+    pub execution: Option<ExecutionData>,
+    pub winners: Option<Vec<PlayerId>>,
+    /// See `Players::seed`.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl SaveData {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Save data should be serializable.")
+    }
+
+    pub fn from_json(json: &str) -> serde_json::Result<SaveData> {
+        serde_json::from_str(json)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayersData {
+    /// The draw piles of ages 1 to 10, from top to bottom.
+    pub main_pile: Vec<Vec<String>>,
+    /// The achievements which are not claimed yet.
+    pub achievements: Vec<AchievementData>,
+    pub players: Vec<PlayerData>,
+    /// The states of the permanent internal observers; see `Subject::save`.
+    pub observers: Vec<Value>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlayerData {
+    pub board: Vec<StackData>,
+    pub hand: Vec<String>,
+    pub score: Vec<String>,
+    pub achievements: Vec<AchievementData>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StackData {
+    pub color: Color,
+    /// From top to bottom.
+    pub cards: Vec<String>,
+    pub splay: Splay,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AchievementData {
+    Normal(String),
+    Special(SpecialAchievement),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionData {
+    pub root: RootData,
+    pub choices: Vec<NoRefChoice>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RootData {
+    Start,
+    Execute { player: PlayerId, card: String },
+}
+
+pub(crate) fn names<'c>(cards: impl IntoIterator<Item = &'c Card>) -> Vec<String> {
+    cards
+        .into_iter()
+        .map(|card| card.name().to_owned())
+        .collect()
+}
+
+pub(crate) fn find<'c>(cards: &[&'c Card], name: &str) -> InnResult<&'c Card> {
+    cards
+        .iter()
+        .find(|card| card.name() == name)
+        .copied()
+        .ok_or(InnovationError::CardNotFound)
+}
+
+pub(crate) fn find_all<'c>(cards: &[&'c Card], names: &[String]) -> InnResult<Vec<&'c Card>> {
+    names.iter().map(|name| find(cards, name)).collect()
+}
+
+impl AchievementData {
+    pub(crate) fn new(achievement: &Achievement) -> AchievementData {
+        match achievement {
+            Achievement::Normal(card) => AchievementData::Normal(card.name().to_owned()),
+            Achievement::Special(special) => AchievementData::Special(*special),
+        }
+    }
+
+    pub(crate) fn load<'c>(&self, cards: &[&'c Card]) -> InnResult<Achievement<'c>> {
+        Ok(match self {
+            AchievementData::Normal(name) => Achievement::Normal(find(cards, name)?),
+            AchievementData::Special(special) => Achievement::Special(*special),
+        })
+    }
+}
+
+impl PlayerData {
+    fn new(player: &Player) -> PlayerData {
+        let board = player.board();
+        PlayerData {
+            board: Color::iter()
+                .map(|color| {
+                    let stack = board.get_stack(color);
+                    StackData {
+                        color,
+                        cards: names(stack.iter()),
+                        splay: stack.splay_direction(),
+                    }
+                })
+                .collect(),
+            hand: names(player.hand().to_vec()),
+            score: names(player.score_pile().to_vec()),
+            achievements: player
+                .achievements()
+                .inner()
+                .iter()
+                .map(AchievementData::new)
+                .collect(),
+        }
+    }
+
+    fn load<'c>(&self, cards: &[&'c Card]) -> InnResult<PlayerBuilder<'c>> {
+        let mut builder = PlayerBuilder::default()
+            .hand(find_all(cards, &self.hand)?)
+            .score(find_all(cards, &self.score)?)
+            .achievements(
+                self.achievements
+                    .iter()
+                    .map(|a| a.load(cards))
+                    .collect::<InnResult<_>>()?,
+            );
+        for stack in self.board.iter() {
+            let stack_cards = find_all(cards, &stack.cards)?;
+            if stack_cards.iter().any(|card| card.color() != stack.color) {
+                return Err(InnovationError::InvalidSave);
+            }
+            builder = builder.board(stack_cards).splay(stack.color, stack.splay);
+        }
+        Ok(builder)
+    }
+}
+
+impl PlayersData {
+    pub fn new(players: &Players) -> PlayersData {
+        let pile = players.main_card_pile().borrow();
+        PlayersData {
+            main_pile: pile
+                .contents()
+                .iter()
+                .map(|cards| names(cards.iter().copied()))
+                .collect(),
+            achievements: pile
+                .achievements()
+                .iter()
+                .map(AchievementData::new)
+                .collect(),
+            players: players.players().into_iter().map(PlayerData::new).collect(),
+            observers: players.save_observers(),
+        }
+    }
+
+    pub fn load<'c>(&self, cards: Vec<&'c Card>) -> InnResult<Players<'c>> {
+        let mut deck = Vec::new();
+        for pile in self.main_pile.iter() {
+            deck.extend(find_all(&cards, pile)?);
+        }
+        let achievements = self
+            .achievements
+            .iter()
+            .map(|a| a.load(&cards))
+            .collect::<InnResult<Vec<_>>>()?;
+        let builders = self
+            .players
+            .iter()
+            .map(|p| p.load(&cards))
+            .collect::<InnResult<_>>()?;
+        // the first player is overwritten by the states of the observers
+        let players = Players::from_builders(
+            cards,
+            MainCardPile::new(deck, achievements),
+            builders,
+            0,
+            Subject::new(),
+        );
+        players.load_observers(&self.observers)?;
+        Ok(players)
+    }
+}
+
+impl ExecutionData {
+    pub fn new(execution: &Execution) -> ExecutionData {
+        ExecutionData {
+            root: match execution.root {
+                Root::Start => RootData::Start,
+                Root::Execute(player, card) => RootData::Execute {
+                    player,
+                    card: card.name().to_owned(),
+                },
+            },
+            choices: execution.choices.clone(),
+        }
+    }
+
+    /// The execution, starting from `players`.
+    pub fn load<'c>(&self, players: Players<'c>) -> InnResult<Execution<'c>> {
+        let root = match &self.root {
+            RootData::Start => Root::Start,
+            RootData::Execute { player, card } => {
+                if *player >= players.num_players() {
+                    return Err(InnovationError::InvalidSave);
+                }
+                Root::Execute(*player, find(players.cards(), card)?)
+            }
+        };
+        let mut execution = Execution::new(root, players);
+        execution.choices = self.choices.clone();
+        Ok(execution)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::{Action, NoRefStep},
+        card::default_cards,
+        game::{GameConfig, OuterGame},
+        observation::{GameState, ObsType},
+        player::PlayerBuilder,
+    };
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn assert_same(game: &OuterGame, loaded: &OuterGame) {
+        for id in 0..game.players().num_players() {
+            assert_eq!(
+                serde_json::to_value(game.observe(id)).unwrap(),
+                serde_json::to_value(loaded.observe(id)).unwrap()
+            );
+        }
+        assert_eq!(
+            game.players().main_card_pile().borrow().contents(),
+            loaded.players().main_card_pile().borrow().contents()
+        );
+        assert_eq!(game.legal_actions(), loaded.legal_actions());
+        assert_eq!(game.winners(), loaded.winners());
+    }
+
+    #[test]
+    fn save_and_load() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut game = GameConfig::standard(cards.clone(), 5)
+            .default_players(3)
+            .build();
+        // another instance of the card list
+        let own_cards = default_cards::default_cards();
+        let own_cards: Vec<_> = own_cards.iter().collect();
+
+        // before and during the initial melds
+        let data = SaveData::from_json(&game.save().to_json()).unwrap();
+        assert_same(&game, &OuterGame::load(own_cards.clone(), data).unwrap());
+        game.start().unwrap();
+        let data = SaveData::from_json(&game.save().to_json()).unwrap();
+        assert!(data.execution.is_some());
+        assert_same(&game, &OuterGame::load(own_cards.clone(), data).unwrap());
+
+        let mut saved_in_dogma = 0;
+        let mut step = 0;
+        while !game.is_over() && step < 300 {
+            if step % 7 == 0 || matches!(game.next_action_type(), ObsType::Executing(_)) {
+                let data = SaveData::from_json(&game.save().to_json()).unwrap();
+                let mut loaded = OuterGame::load(own_cards.clone(), data).unwrap();
+                assert_same(&game, &loaded);
+                if let ObsType::Executing(_) = game.next_action_type() {
+                    saved_in_dogma += 1;
+                }
+                // both continue the same way
+                let action = game.legal_actions().choose(&mut rng).unwrap();
+                loaded.step(action.clone()).unwrap();
+                game.step(action).unwrap();
+                assert_same(&game, &loaded);
+            } else {
+                let action = game.legal_actions().choose(&mut rng).unwrap();
+                game.step(action).unwrap();
+            }
+            step += 1;
+        }
+        assert!(saved_in_dogma > 0);
+        let data = SaveData::from_json(&game.save().to_json()).unwrap();
+        assert_same(&game, &OuterGame::load(own_cards.clone(), data).unwrap());
+
+        // unsupported version and missing cards
+        let mut data = game.save();
+        data.version = SAVE_VERSION + 1;
+        assert!(matches!(
+            OuterGame::load(own_cards.clone(), data),
+            Err(InnovationError::UnsupportedSaveVersion(_))
+        ));
+        assert!(OuterGame::load(own_cards[1..].to_vec(), game.save()).is_err());
+    }
+
+    #[test]
+    fn load_invalid_choices() {
+        let archery = default_cards::archery();
+        let oars = default_cards::oars();
+        let pottery = default_cards::pottery();
+        let clothing = default_cards::clothing();
+        let cards = vec![&archery, &oars, &pottery, &clothing];
+        let mut game = GameConfig::new(cards.clone())
+            .main_pile(MainCardPile::builder().draw_deck(cards.clone()).build())
+            .default_players(2)
+            .build();
+        // the first player chooses a card to meld
+        game.start().unwrap();
+        let load = |choice: NoRefChoice| {
+            let mut data = game.save();
+            data.execution.as_mut().unwrap().choices = vec![choice];
+            OuterGame::load(cards.clone(), data)
+        };
+        assert!(matches!(
+            load(NoRefChoice::Card(vec!["No Such Card".to_owned()])),
+            Err(InnovationError::InvalidSave)
+        ));
+        assert!(matches!(
+            load(NoRefChoice::Opponent(5)),
+            Err(InnovationError::InvalidSave)
+        ));
+    }
+
+    #[test]
+    fn load_ended_in_execution() {
+        let city_states = default_cards::city_states();
+        let metalworking = default_cards::metalworking();
+        let masonry = default_cards::masonry();
+        let cards = vec![&city_states, &metalworking, &masonry];
+        let mut game = GameConfig::new(cards.clone())
+            .players(vec![
+                PlayerBuilder::default().board(vec![&city_states]),
+                PlayerBuilder::default().board(vec![&metalworking, &masonry]),
+            ])
+            .build();
+        game.step(Action::Step(NoRefStep::Execute("City States".to_owned())))
+            .unwrap();
+        // drawing from the empty pile after the transfer ends the game
+        assert!(matches!(
+            game.step(Action::Executing(NoRefChoice::Card(vec![
+                "Metalworking".to_owned()
+            ]))),
+            Ok(GameState::End(_))
+        ));
+        let loaded = OuterGame::load(cards.clone(), game.save()).unwrap();
+        assert_same(&game, &loaded);
+        assert!(matches!(game.next_action_type(), ObsType::Main));
+    }
+}
@@ -0,0 +1,515 @@
+//! A game server for local networks.
+//!
+//! Clients connect over TCP and speak JSON lines with the messages of
+//! `protocol`:
+//!
+//! | command                                    | response                                   |
+//! |--------------------------------------------|--------------------------------------------|
+//! | `{"cmd":"create","players":2,"seed":5}`    | `{"type":"created","game":0}`              |
+//! | `{"cmd":"join","game":0}`                  | `{"type":"joined","game":0,"seat":1,...}`  |
+//! | `{"cmd":"rejoin","game":0,"token":"..."}`  | `{"type":"joined",...}`                    |
+//! | `{"cmd":"act","action":"draw"}`            | nothing, or `{"type":"error",...}`         |
+//!
+//! `join` takes the first free seat of a game and answers with a token,
+//! which `rejoin` takes to return to the seat after disconnecting. The
+//! `seed` of `create` is optional.
+//!
+//! A game starts when all of its seats are taken; every seat then gets
+//! `game_start`, and after the start and every action, the `event`s since
+//! then followed by its `observation`, and `game_end` when the game is over.
+//! Events and observations are redacted on the server for each seat, so a
+//! connection never receives cards hidden from its seat. A seat rejoining a
+//! started game gets `game_start` and its current `observation` again; the
+//! events it missed are not sent.
+//!
+//! All games are played on one thread, which owns them, while every
+//! connection is read on a thread of its own.
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    io::{self, BufRead, BufReader},
+    net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs},
+    rc::Rc,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+use rand::{thread_rng, Rng};
+use serde::Deserialize;
+
+use crate::{
+    action::Action,
+    card::{default_cards, Card},
+    game::{OuterGame, PlayerId},
+    logger::Logger,
+    protocol::{logged_game, redact, send, Response},
+};
+
+type ConnectionId = usize;
+type GameId = usize;
+
+#[derive(Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+enum Command {
+    Create {
+        players: usize,
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    Join {
+        game: GameId,
+    },
+    Rejoin {
+        game: GameId,
+        token: String,
+    },
+    Act {
+        action: Action,
+    },
+}
+
+enum Message {
+    Connected(ConnectionId, TcpStream),
+    Line(ConnectionId, String),
+    Disconnected(ConnectionId),
+}
+
+struct Seat {
+    token: String,
+    connection: Option<ConnectionId>,
+}
+
+struct Table<'c> {
+    game: OuterGame<'c>,
+    logger: Rc<RefCell<Logger<'c>>>,
+    // the number of logged items sent to the seats
+    delivered: usize,
+    num_players: usize,
+    seats: Vec<Seat>,
+}
+
+struct Connection {
+    stream: TcpStream,
+    seat: Option<(GameId, PlayerId)>,
+}
+
+// a write failure means the connection is closing, and its reader tells so
+fn write(connection: &mut Connection, response: &Response) {
+    send(&mut connection.stream, response).ok();
+}
+
+impl<'c> Table<'c> {
+    fn is_started(&self) -> bool {
+        self.seats.len() == self.num_players
+    }
+
+    fn send_observation(&self, seat: PlayerId, connection: &mut Connection) {
+        write(
+            connection,
+            &Response::Observation {
+                observation: &self.game.observe(seat),
+            },
+        );
+        if let Some(winners) = self.game.winners() {
+            write(
+                connection,
+                &Response::GameEnd {
+                    winners: winners.to_vec(),
+                },
+            );
+        }
+    }
+
+    // sends every seat the new events it may see and its observation
+    fn broadcast(&mut self, connections: &mut HashMap<ConnectionId, Connection>) {
+        let logger = self.logger.borrow();
+        let items = &logger
+            .current_game()
+            .expect("The logger is started with the game.")
+            .items[self.delivered..];
+        for (id, seat) in self.seats.iter().enumerate() {
+            let connection = match seat.connection.and_then(|c| connections.get_mut(&c)) {
+                Some(connection) => connection,
+                None => continue,
+            };
+            for item in items {
+                write(
+                    connection,
+                    &Response::Event {
+                        event: redact(item, Some(id)),
+                    },
+                );
+            }
+            self.send_observation(id, connection);
+        }
+        self.delivered += items.len();
+    }
+}
+
+struct Hub<'c> {
+    cards: Vec<&'c Card>,
+    tables: Vec<Table<'c>>,
+    connections: HashMap<ConnectionId, Connection>,
+}
+
+impl<'c> Hub<'c> {
+    fn run(&mut self, receiver: Receiver<Message>) {
+        for message in receiver {
+            match message {
+                Message::Connected(id, stream) => {
+                    self.connections
+                        .insert(id, Connection { stream, seat: None });
+                }
+                Message::Line(id, line) => {
+                    if let Err(message) = self.handle(id, &line) {
+                        if let Some(connection) = self.connections.get_mut(&id) {
+                            write(connection, &Response::Error { message });
+                        }
+                    }
+                }
+                Message::Disconnected(id) => {
+                    if let Some(Connection {
+                        seat: Some((game, seat)),
+                        ..
+                    }) = self.connections.remove(&id)
+                    {
+                        let seat = &mut self.tables[game].seats[seat];
+                        if seat.connection == Some(id) {
+                            seat.connection = None;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, id: ConnectionId, line: &str) -> Result<(), String> {
+        let command = serde_json::from_str(line).map_err(|e| e.to_string())?;
+        let Hub {
+            cards,
+            tables,
+            connections,
+        } = self;
+        let connection = connections
+            .get_mut(&id)
+            .expect("Lines come from connected clients.");
+        match command {
+            Command::Create { players, seed } => {
+                let (game, logger) =
+                    logged_game(cards.clone(), players, seed).map_err(|e| format!("{:?}", e))?;
+                tables.push(Table {
+                    game,
+                    logger,
+                    delivered: 0,
+                    num_players: players,
+                    seats: Vec::new(),
+                });
+                write(
+                    connection,
+                    &Response::Created {
+                        game: tables.len() - 1,
+                    },
+                );
+            }
+            Command::Join { game } => {
+                if connection.seat.is_some() {
+                    return Err("already seated".to_owned());
+                }
+                let table = tables.get_mut(game).ok_or("no such game")?;
+                if table.is_started() {
+                    return Err("the game is full".to_owned());
+                }
+                let seat = table.seats.len();
+                let token = format!("{:016x}", thread_rng().gen::<u64>());
+                table.seats.push(Seat {
+                    token: token.clone(),
+                    connection: Some(id),
+                });
+                connection.seat = Some((game, seat));
+                write(connection, &Response::Joined { game, seat, token });
+                if table.is_started() {
+                    table.game.start().map_err(|e| format!("{:?}", e))?;
+                    for seat in table.seats.iter() {
+                        if let Some(connection) =
+                            seat.connection.and_then(|c| connections.get_mut(&c))
+                        {
+                            write(
+                                connection,
+                                &Response::GameStart {
+                                    player: connection.seat.unwrap().1,
+                                    num_players: table.num_players,
+                                },
+                            );
+                        }
+                    }
+                    table.broadcast(connections);
+                }
+            }
+            Command::Rejoin { game, token } => {
+                let table = tables.get_mut(game).ok_or("no such game")?;
+                let seat = table
+                    .seats
+                    .iter()
+                    .position(|seat| seat.token == token)
+                    .ok_or("wrong token")?;
+                match connection.seat {
+                    Some(seated) if seated != (game, seat) => {
+                        return Err("already seated".to_owned())
+                    }
+                    _ => connection.seat = Some((game, seat)),
+                }
+                // the seat moves to this connection
+                if let Some(old) = table.seats[seat].connection.replace(id) {
+                    if old != id {
+                        if let Some(old) = connections.get_mut(&old) {
+                            old.seat = None;
+                        }
+                    }
+                }
+                let connection = connections.get_mut(&id).unwrap();
+                write(connection, &Response::Joined { game, seat, token });
+                if table.is_started() {
+                    write(
+                        connection,
+                        &Response::GameStart {
+                            player: seat,
+                            num_players: table.num_players,
+                        },
+                    );
+                    table.send_observation(seat, connection);
+                }
+            }
+            Command::Act { action } => {
+                let (game, seat) = connection.seat.ok_or("not seated")?;
+                let table = &mut tables[game];
+                if !table.is_started() || table.game.is_over() {
+                    return Err("the game is not being played".to_owned());
+                }
+                if table.game.acting_player() != seat {
+                    return Err("not your turn".to_owned());
+                }
+                table.game.step(action).map_err(|e| format!("{:?}", e))?;
+                table.broadcast(connections);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Server> {
+        Ok(Server {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Serves games of the default cards until accepting a connection fails.
+    pub fn run(self) -> io::Result<()> {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let cards = default_cards::default_cards();
+            let mut hub = Hub {
+                cards: cards.iter().collect(),
+                tables: Vec::new(),
+                connections: HashMap::new(),
+            };
+            hub.run(receiver);
+        });
+        for (id, stream) in self.listener.incoming().enumerate() {
+            let stream = stream?;
+            let writer = stream.try_clone()?;
+            // the hub ends only with the server
+            sender.send(Message::Connected(id, writer)).ok();
+            let sender = sender.clone();
+            thread::spawn(move || read(id, stream, sender));
+        }
+        Ok(())
+    }
+}
+
+fn read(id: ConnectionId, stream: TcpStream, sender: Sender<Message>) {
+    for line in BufReader::new(stream).lines() {
+        match line {
+            Ok(line) if line.trim().is_empty() => {}
+            Ok(line) => {
+                if sender.send(Message::Line(id, line)).is_err() {
+                    return;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    sender.send(Message::Disconnected(id)).ok();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::{io::Write, time::Duration};
+
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Client {
+            let stream = TcpStream::connect(addr).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(10)))
+                .unwrap();
+            Client {
+                reader: BufReader::new(stream.try_clone().unwrap()),
+                writer: stream,
+            }
+        }
+
+        fn send(&mut self, command: Value) {
+            writeln!(self.writer, "{}", command).unwrap();
+        }
+
+        fn recv(&mut self) -> Value {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            serde_json::from_str(&line).unwrap()
+        }
+
+        // the messages up to the next observation, which comes last
+        fn recv_observation(&mut self) -> Vec<Value> {
+            let mut messages = Vec::new();
+            loop {
+                let message = self.recv();
+                let done = message["type"] == "observation";
+                messages.push(message);
+                if done {
+                    return messages;
+                }
+            }
+        }
+    }
+
+    fn first_in_hand(observation: &Value) -> Value {
+        json!({"card": [observation["observation"]["main_player"]["hand"][0]["name"]]})
+    }
+
+    fn hand(observation: &Value) -> Vec<String> {
+        observation["observation"]["main_player"]["hand"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|card| card["name"].as_str().unwrap().to_owned())
+            .collect()
+    }
+
+    // asserts that no event of `messages` names one of `hidden`
+    fn assert_unnamed(messages: &[Value], hidden: &[String]) {
+        for message in messages.iter().filter(|m| m["type"] == "event") {
+            let event = message["event"].to_string();
+            for name in hidden {
+                assert!(!event.contains(&format!("\"{}\"", name)), "{}", event);
+            }
+        }
+    }
+
+    #[test]
+    fn scripted_clients() {
+        let server = Server::bind("127.0.0.1:0").unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+
+        let mut a = Client::connect(addr);
+        let mut b = Client::connect(addr);
+        a.send(json!({"cmd": "create", "players": 2, "seed": 3}));
+        assert_eq!(a.recv(), json!({"type": "created", "game": 0}));
+        a.send(json!({"cmd": "act", "action": "draw"}));
+        assert_eq!(a.recv()["message"], "not seated");
+        a.send(json!({"cmd": "join", "game": 0}));
+        assert_eq!(a.recv()["seat"], 0);
+        b.send(json!({"cmd": "join", "game": 0}));
+        let joined = b.recv();
+        assert_eq!(joined["seat"], 1);
+        let token = joined["token"].clone();
+
+        let mut observations = Vec::new();
+        let mut events = Vec::new();
+        for (client, seat) in [(&mut a, 0), (&mut b, 1)] {
+            assert_eq!(
+                client.recv(),
+                json!({"type": "game_start", "player": seat, "num_players": 2})
+            );
+            let mut messages = client.recv_observation();
+            let observation = messages.pop().unwrap();
+            // each seat sees its own cards only
+            for message in &messages {
+                let transfer = &message["event"]["operation"]["transfer"];
+                if !transfer.is_null() {
+                    let own = transfer[1]["player"][0] == seat;
+                    assert_eq!(transfer[2].is_string(), own);
+                }
+            }
+            assert_eq!(observation["observation"]["main_player_id"], seat);
+            assert_eq!(observation["observation"]["acting_player"], 0);
+            observations.push(observation);
+            events.push(messages);
+        }
+        let hands = [hand(&observations[0]), hand(&observations[1])];
+        assert_unnamed(&events[0], &hands[1]);
+        assert_unnamed(&events[1], &hands[0]);
+
+        b.send(json!({"cmd": "act", "action": first_in_hand(&observations[1])}));
+        assert_eq!(b.recv()["message"], "not your turn");
+        a.send(json!({"cmd": "act", "action": "draw"}));
+        assert_eq!(a.recv()["type"], "error");
+        a.send(json!({"cmd": "join", "game": 0}));
+        assert_eq!(a.recv()["message"], "already seated");
+        a.send(json!({"cmd": "rejoin", "game": 0, "token": "nothing"}));
+        assert_eq!(a.recv()["message"], "wrong token");
+
+        a.send(json!({"cmd": "act", "action": first_in_hand(&observations[0])}));
+        let mut messages = a.recv_observation();
+        let observation = messages.pop().unwrap();
+        assert_eq!(observation["observation"]["acting_player"], 1);
+        assert_unnamed(&messages, &hands[1]);
+        // b sees the age of the card a chooses, and its name once it's melded
+        let messages = b.recv_observation();
+        assert_eq!(
+            messages[0],
+            json!({"type": "event", "event": {"action": [0, {"card": [{"age": 1}]}]}})
+        );
+        assert_unnamed(&messages, &hands[0][1..]);
+
+        // another connection takes the seat of b
+        drop(b);
+        let mut c = Client::connect(addr);
+        c.send(json!({"cmd": "rejoin", "game": 0, "token": token}));
+        assert_eq!(c.recv()["seat"], 1);
+        assert_eq!(c.recv()["type"], "game_start");
+        let observation = c.recv();
+        assert_eq!(observation["observation"]["main_player_id"], 1);
+        c.send(json!({"cmd": "act", "action": first_in_hand(&observation)}));
+        let mut messages = a.recv_observation();
+        let observation = messages.pop().unwrap();
+        // both first cards are melded
+        assert_eq!(observation["observation"]["obstype"], "main");
+        assert_unnamed(&messages, &hands[1][1..]);
+        assert_unnamed(&c.recv_observation(), &hands[0][1..]);
+
+        let mut d = Client::connect(addr);
+        d.send(json!({"cmd": "join", "game": 0}));
+        assert_eq!(d.recv()["message"], "the game is full");
+        d.send(json!({"cmd": "join", "game": 1}));
+        assert_eq!(d.recv()["message"], "no such game");
+        d.send(json!({"cmd": "create", "players": 5}));
+        assert_eq!(d.recv()["type"], "error");
+    }
+}
@@ -2,9 +2,13 @@ use std::cmp::min;
 
 use serde::Serialize;
 
-use crate::card::flow::{FlowState, GenResume};
+use crate::game::PlayerId;
 use crate::player::Player;
-use crate::{action::RefChoice, card::Card, game::Players};
+use crate::{
+    action::{NoRefChoice, RefChoice},
+    card::Card,
+    game::Players,
+};
 
 #[derive(Clone, Debug, Serialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -19,9 +23,58 @@ pub enum Choose<'c> {
     Yn,
 }
 
+impl<'c> Choose<'c> {
+    /// The bounds of the number of cards to choose, with `max_num` limited by
+    /// the number of available cards. `None` if not choosing cards.
+    pub fn card_num_bounds(&self) -> Option<(usize, usize)> {
+        match *self {
+            Choose::Card {
+                min_num,
+                max_num,
+                ref from,
+            } => Some((
+                min_num,
+                max_num.map_or(from.len(), |max| min(max, from.len())),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Checks if `choice` is a valid answer to this choice of `actor`.
+    pub fn accepts(&self, choice: &NoRefChoice, actor: PlayerId, num_players: usize) -> bool {
+        match (choice, self) {
+            (
+                NoRefChoice::Card(cards),
+                &Choose::Card {
+                    min_num,
+                    max_num,
+                    ref from,
+                },
+            ) => {
+                let len = cards.len();
+                len >= min_num
+                    && match max_num {
+                        Some(max) => len <= max,
+                        None => true,
+                    }
+                    && {
+                        // performance?
+                        // check if `cards` is a subset of `from`
+                        cards
+                            .iter()
+                            .all(|name| from.iter().any(|c| c.name() == name))
+                    }
+            }
+            (NoRefChoice::Opponent(id), Choose::Opponent) => *id < num_players && *id != actor,
+            (NoRefChoice::Yn(_), Choose::Yn) => true,
+            _ => false,
+        }
+    }
+}
+
 pub enum ActionCheckResult<'c, 'g> {
     Zero,
-    One(GenResume<'c, 'g>),
+    One(RefChoice<'c, 'g>),
     Many,
 }
 
@@ -52,11 +105,16 @@ impl<'c, 'g> ExecutionState<'c, 'g> {
             self.actor,
             ExecutionObs {
                 state: self.state,
-                card: self.card.unwrap(),
+                card: self.card,
             },
         )
     }
 
+    /// Checks if `choice` is a valid answer to this choice.
+    pub fn accepts(&self, choice: &NoRefChoice, num_players: usize) -> bool {
+        self.state.accepts(choice, self.actor.id(), num_players)
+    }
+
     pub fn check_valid_actions(&self, game: &'g Players<'c>) -> ActionCheckResult<'c, 'g> {
         match self.state {
             Choose::Card {
@@ -107,14 +165,67 @@ impl<'c, 'g> ExecutionState<'c, 'g> {
 #[derive(Clone, Debug, Serialize)]
 pub struct ExecutionObs<'c> {
     pub state: Choose<'c>,
-    pub card: &'c Card,
+    /// The card being executed, or `None` when choosing the first card to meld.
+    pub card: Option<&'c Card>,
+}
+
+/// Where an execution starts.
+#[derive(Clone, Copy)]
+pub enum Root<'c> {
+    /// The initial meld of `OuterGame::start`.
+    Start,
+    /// A card executed by a player as a step.
+    Execute(PlayerId, &'c Card),
+}
+
+/// An execution in progress, as plain data.
+///
+/// The execution is resumed by restoring the players it started from and
+/// running it from `root` again, with the choices made so far; see
+/// `Players::resume`.
+pub struct Execution<'c> {
+    pub root: Root<'c>,
+    pub choices: Vec<NoRefChoice>,
+    /// The players before the execution started.
+    pub players: Players<'c>,
+    /// The number of notifications already delivered to external observers.
+    pub delivered: usize,
+}
+
+impl<'c> Execution<'c> {
+    pub fn new(root: Root<'c>, players: Players<'c>) -> Execution<'c> {
+        Execution {
+            root,
+            choices: Vec::new(),
+            players,
+            delivered: 0,
+        }
+    }
+
+    pub fn fork(&self) -> Execution<'c> {
+        Execution {
+            root: self.root,
+            choices: self.choices.clone(),
+            players: self.players.fork(),
+            delivered: self.delivered,
+        }
+    }
 }
 
 #[derive(Default)]
-pub enum State<'c, 'g> {
+pub enum State<'c> {
     #[default]
     Main,
-    Executing(FlowState<'c, 'g>),
+    Executing(Box<Execution<'c>>),
+}
+
+impl<'c> State<'c> {
+    pub fn fork(&self) -> State<'c> {
+        match self {
+            State::Main => State::Main,
+            State::Executing(execution) => State::Executing(Box::new(execution.fork())),
+        }
+    }
 }
 
 #[cfg(test)]
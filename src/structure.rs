@@ -1,3 +1,5 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
     board::Board as Board_,
     card::{Age, Card, Color},
@@ -177,7 +179,8 @@ impl<'c> AddToGame<'c, ()> for MainCardPile {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PlayerPlace {
     Hand,
     Score,
@@ -202,7 +205,8 @@ impl FromRef<Board> for PlayerPlace {
     }
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Place {
     MainCardPile,
     Player(usize, PlayerPlace),
@@ -220,6 +224,12 @@ impl Place {
     pub fn board(player: &Player) -> Place {
         Place::Player(player.id(), PlayerPlace::Board)
     }
+
+    /// Whether the cards here are hidden from some player; only boards
+    /// are seen by everyone.
+    pub fn is_hidden(self) -> bool {
+        !matches!(self, Place::Player(_, PlayerPlace::Board))
+    }
 }
 
 impl<T> FromRef<(usize, T)> for Place
@@ -0,0 +1,525 @@
+//! Tournaments between agents, to compare bots.
+//!
+//! Entrants are seated at tables of 2 to 4 players, either every group of
+//! entrants once (round robin) or by standing for some rounds (Swiss). Each
+//! table plays one game per seat rotation, all with the same deck, so that
+//! every entrant gets every seat. Games are played on worker threads, which
+//! build their own cards and agents, and the results are the same for any
+//! number of threads.
+//!
+//! Entrants are rated by Elo, taking every game as matches between each
+//! pair of its players, where a winner beats a non-winner and other pairs
+//! draw.
+
+use std::{
+    cmp::Reverse,
+    iter,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Serialize;
+
+use crate::{
+    agent::{Agent, InvalidActionPolicy, Match},
+    card::{default_cards, Card},
+    error::{InnResult, InnovationError},
+    game::{GameConfig, PlayerId},
+    utils::{combinations, csv_line},
+};
+
+/// Makes the agent of an entrant from the cards of the game and a seed.
+pub type AgentFactory = Arc<dyn for<'c> Fn(&[&'c Card], u64) -> Box<dyn Agent + 'c> + Send + Sync>;
+
+pub const INITIAL_RATING: f64 = 1500.0;
+pub const ELO_K: f64 = 32.0;
+
+#[derive(Clone)]
+pub struct Entrant {
+    name: String,
+    factory: AgentFactory,
+}
+
+impl Entrant {
+    pub fn new<F>(name: impl Into<String>, factory: F) -> Entrant
+    where
+        F: for<'c> Fn(&[&'c Card], u64) -> Box<dyn Agent + 'c> + Send + Sync + 'static,
+    {
+        Entrant {
+            name: name.into(),
+            factory: Arc::new(factory),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pairing {
+    /// Every group of `table_size` entrants plays at a table once.
+    RoundRobin,
+    /// In each round, entrants are ranked by wins so far and seated in that
+    /// order, avoiding tables with entrants who have met if possible. Those
+    /// left over sit the round out, by turns from the lowest ranked.
+    Swiss { rounds: usize },
+}
+
+/// A game of a tournament.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct GameResult {
+    pub round: usize,
+    /// The entrant in each seat.
+    pub seats: Vec<usize>,
+    /// The seed of the deck.
+    pub seed: u64,
+    pub winners: Vec<PlayerId>,
+    pub forfeited: Option<PlayerId>,
+    pub scores: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Standing {
+    pub name: String,
+    pub games: usize,
+    pub wins: usize,
+    pub win_rate: f64,
+    pub average_score: f64,
+    pub rating: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TournamentResult {
+    /// The games in the order they were scheduled.
+    pub games: Vec<GameResult>,
+    /// The entrants in the order they were given.
+    pub standings: Vec<Standing>,
+}
+
+impl TournamentResult {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("Tournament results should be serializable.")
+    }
+
+    /// The standings as CSV, with a header line.
+    pub fn standings_csv(&self) -> String {
+        let mut csv = csv_line(&[
+            "name",
+            "games",
+            "wins",
+            "win_rate",
+            "average_score",
+            "rating",
+        ]) + "\n";
+        for s in &self.standings {
+            csv += &csv_line(&[
+                s.name.clone(),
+                s.games.to_string(),
+                s.wins.to_string(),
+                format!("{:.4}", s.win_rate),
+                format!("{:.2}", s.average_score),
+                format!("{:.1}", s.rating),
+            ]);
+            csv += "\n";
+        }
+        csv
+    }
+
+    /// The games as CSV, with a header line. Seats, winners and scores are
+    /// joined by spaces; entrants are by name.
+    pub fn games_csv(&self) -> String {
+        fn joined<T: ToString>(items: impl Iterator<Item = T>) -> String {
+            items.map(|i| i.to_string()).collect::<Vec<_>>().join(" ")
+        }
+        let mut csv =
+            csv_line(&["round", "seed", "seats", "winners", "forfeited", "scores"]) + "\n";
+        for game in &self.games {
+            csv += &csv_line(&[
+                game.round.to_string(),
+                game.seed.to_string(),
+                joined(game.seats.iter().map(|&e| &self.standings[e].name)),
+                joined(game.winners.iter()),
+                game.forfeited.map(|p| p.to_string()).unwrap_or_default(),
+                joined(game.scores.iter()),
+            ]);
+            csv += "\n";
+        }
+        csv
+    }
+}
+
+// a game to play
+#[derive(Clone)]
+struct Job {
+    round: usize,
+    seats: Vec<usize>,
+    seed: u64,
+    agent_seeds: Vec<u64>,
+}
+
+pub struct Tournament {
+    entrants: Vec<Entrant>,
+    cards: fn() -> Vec<Card>,
+    table_size: usize,
+    pairing: Pairing,
+    seed: u64,
+    threads: usize,
+}
+
+impl Tournament {
+    pub fn new(entrants: Vec<Entrant>) -> Tournament {
+        Tournament {
+            entrants,
+            cards: default_cards::default_cards,
+            table_size: 2,
+            pairing: Pairing::RoundRobin,
+            seed: 0,
+            threads: 1,
+        }
+    }
+
+    pub fn entrant(mut self, entrant: Entrant) -> Tournament {
+        self.entrants.push(entrant);
+        self
+    }
+
+    /// The cards of the games, which default to `default_cards`.
+    pub fn cards(mut self, cards: fn() -> Vec<Card>) -> Tournament {
+        self.cards = cards;
+        self
+    }
+
+    /// The number of players at a table, from 2 to 4.
+    pub fn table_size(mut self, table_size: usize) -> Tournament {
+        self.table_size = table_size;
+        self
+    }
+
+    pub fn pairing(mut self, pairing: Pairing) -> Tournament {
+        self.pairing = pairing;
+        self
+    }
+
+    /// Seeds the decks and the agents.
+    pub fn seed(mut self, seed: u64) -> Tournament {
+        self.seed = seed;
+        self
+    }
+
+    /// The number of worker threads playing games.
+    pub fn threads(mut self, threads: usize) -> Tournament {
+        self.threads = threads.max(1);
+        self
+    }
+
+    pub fn run(&self) -> InnResult<TournamentResult> {
+        if !(2..=4).contains(&self.table_size) || self.entrants.len() < self.table_size {
+            return Err(InnovationError::WrongPlayerNum);
+        }
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut games = Vec::new();
+        match self.pairing {
+            Pairing::RoundRobin => {
+                let indices: Vec<usize> = (0..self.entrants.len()).collect();
+                let tables = combinations(&indices, self.table_size, self.table_size);
+                games = self.play(&self.schedule(0, tables, &mut rng))?;
+            }
+            Pairing::Swiss { rounds } => {
+                let mut byes = vec![0; self.entrants.len()];
+                for round in 0..rounds {
+                    let tables = self.swiss_tables(&games, &mut byes);
+                    games.extend(self.play(&self.schedule(round, tables, &mut rng))?);
+                }
+            }
+        }
+        let standings = self.standings(&games);
+        Ok(TournamentResult { games, standings })
+    }
+
+    // the tables of a Swiss round after `games`, counting the rounds each
+    // entrant sits out in `byes`
+    fn swiss_tables(&self, games: &[GameResult], byes: &mut [usize]) -> Vec<Vec<usize>> {
+        let n = self.entrants.len();
+        let standings = self.standings(games);
+        let mut ranked: Vec<usize> = (0..n).collect();
+        ranked.sort_by_key(|&e| Reverse(standings[e].wins));
+        // the lowest ranked of those who sat out the fewest rounds sit out
+        let mut out: Vec<usize> = ranked.iter().rev().copied().collect();
+        out.sort_by_key(|&e| byes[e]);
+        out.truncate(n % self.table_size);
+        for &e in &out {
+            byes[e] += 1;
+        }
+        ranked.retain(|e| !out.contains(e));
+
+        let mut met = vec![vec![false; n]; n];
+        for game in games {
+            for &a in &game.seats {
+                for &b in &game.seats {
+                    met[a][b] = true;
+                }
+            }
+        }
+        let mut tables = Vec::new();
+        if seat_new(&ranked, &met, self.table_size, &mut tables) {
+            tables
+        } else {
+            ranked
+                .chunks_exact(self.table_size)
+                .map(<[usize]>::to_vec)
+                .collect()
+        }
+    }
+
+    // a game for every seat rotation of every table, with a deck per table
+    fn schedule(&self, round: usize, tables: Vec<Vec<usize>>, rng: &mut StdRng) -> Vec<Job> {
+        let mut jobs = Vec::new();
+        for table in tables {
+            let seed = rng.gen();
+            for rotation in 0..table.len() {
+                let mut seats = table.clone();
+                seats.rotate_left(rotation);
+                jobs.push(Job {
+                    round,
+                    agent_seeds: seats.iter().map(|_| rng.gen()).collect(),
+                    seats,
+                    seed,
+                });
+            }
+        }
+        jobs
+    }
+
+    fn play(&self, jobs: &[Job]) -> InnResult<Vec<GameResult>> {
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(vec![None; jobs.len()]);
+        let error = Mutex::new(None);
+        thread::scope(|scope| {
+            for _ in 0..self.threads.min(jobs.len()) {
+                scope.spawn(|| {
+                    let cards = (self.cards)();
+                    let cards: Vec<&Card> = cards.iter().collect();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= jobs.len() || error.lock().unwrap().is_some() {
+                            break;
+                        }
+                        match self.play_game(&cards, &jobs[index]) {
+                            Ok(result) => results.lock().unwrap()[index] = Some(result),
+                            Err(e) => *error.lock().unwrap() = Some(e),
+                        }
+                    }
+                });
+            }
+        });
+        if let Some(e) = error.into_inner().unwrap() {
+            return Err(e);
+        }
+        Ok(results
+            .into_inner()
+            .unwrap()
+            .into_iter()
+            .map(|result| result.expect("Every game is played without errors."))
+            .collect())
+    }
+
+    fn play_game(&self, cards: &[&Card], job: &Job) -> InnResult<GameResult> {
+        let agents = job
+            .seats
+            .iter()
+            .zip(&job.agent_seeds)
+            .map(|(&entrant, &seed)| (self.entrants[entrant].factory)(cards, seed))
+            .collect();
+        let config =
+            GameConfig::standard(cards.to_vec(), job.seed).default_players(job.seats.len());
+        let result = Match::new(agents)
+            .invalid_action_policy(InvalidActionPolicy::Forfeit)
+            .play(config)?;
+        Ok(GameResult {
+            round: job.round,
+            seats: job.seats.clone(),
+            seed: job.seed,
+            winners: result.winners,
+            forfeited: result.forfeited,
+            scores: result.scores,
+        })
+    }
+
+    fn standings(&self, games: &[GameResult]) -> Vec<Standing> {
+        let n = self.entrants.len();
+        let (mut played, mut wins, mut scores) = (vec![0; n], vec![0; n], vec![0; n]);
+        let mut ratings = vec![INITIAL_RATING; n];
+        for game in games {
+            let won = |seat: PlayerId| game.winners.contains(&seat);
+            for (seat, &entrant) in game.seats.iter().enumerate() {
+                played[entrant] += 1;
+                wins[entrant] += won(seat) as usize;
+                scores[entrant] += game.scores[seat];
+            }
+            let k = ELO_K / (game.seats.len() - 1) as f64;
+            let mut deltas = vec![0.0; n];
+            for (i, &a) in game.seats.iter().enumerate() {
+                for (j, &b) in game.seats.iter().enumerate() {
+                    if i == j {
+                        continue;
+                    }
+                    let actual = match (won(i), won(j)) {
+                        (true, false) => 1.0,
+                        (false, true) => 0.0,
+                        _ => 0.5,
+                    };
+                    let expected = 1.0 / (1.0 + 10f64.powf((ratings[b] - ratings[a]) / 400.0));
+                    deltas[a] += k * (actual - expected);
+                }
+            }
+            for (rating, delta) in ratings.iter_mut().zip(deltas) {
+                *rating += delta;
+            }
+        }
+        let ratio = |x: usize, games: usize| {
+            if games == 0 {
+                0.0
+            } else {
+                x as f64 / games as f64
+            }
+        };
+        (0..n)
+            .map(|e| Standing {
+                name: self.entrants[e].name.clone(),
+                games: played[e],
+                wins: wins[e],
+                win_rate: ratio(wins[e], played[e]),
+                average_score: ratio(scores[e], played[e]),
+                rating: ratings[e],
+            })
+            .collect()
+    }
+}
+
+// seats `unseated` in rank order at tables of `size` entrants who have not
+// met, returning false if there's no such seating
+fn seat_new(
+    unseated: &[usize],
+    met: &[Vec<bool>],
+    size: usize,
+    tables: &mut Vec<Vec<usize>>,
+) -> bool {
+    let (&head, rest) = match unseated.split_first() {
+        Some(split) => split,
+        None => return true,
+    };
+    for others in combinations(rest, size - 1, size - 1) {
+        let table: Vec<usize> = iter::once(head).chain(others).collect();
+        let new = table
+            .iter()
+            .enumerate()
+            .all(|(i, &a)| table[..i].iter().all(|&b| !met[a][b]));
+        if !new {
+            continue;
+        }
+        let left: Vec<usize> = rest
+            .iter()
+            .copied()
+            .filter(|e| !table.contains(e))
+            .collect();
+        tables.push(table);
+        if seat_new(&left, met, size, tables) {
+            return true;
+        }
+        tables.pop();
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{Evaluation, GreedyAgent, HeuristicAgent, RandomAgent};
+
+    fn entrants() -> Vec<Entrant> {
+        vec![
+            Entrant::new("random", |_, seed| Box::new(RandomAgent::new(seed))),
+            Entrant::new("greedy", |_, seed| {
+                Box::new(GreedyAgent::new(Evaluation::default(), seed))
+            }),
+            Entrant::new("heuristic", |_, _| Box::new(HeuristicAgent::new())),
+            Entrant::new("random, again", |_, seed| Box::new(RandomAgent::new(seed))),
+        ]
+    }
+
+    #[test]
+    fn round_robin() {
+        let tournament = Tournament::new(entrants()).table_size(3).seed(7);
+        let result = tournament.run().unwrap();
+        // 4 tables of 3, with 3 rotations each
+        assert_eq!(result.games.len(), 12);
+        assert!(result.standings.iter().all(|s| s.games == 9));
+        for rotations in result.games.chunks(3) {
+            assert!(rotations.iter().all(|g| g.seed == rotations[0].seed));
+            assert_eq!(rotations[1].seats[2], rotations[0].seats[0]);
+        }
+        let total: f64 = result.standings.iter().map(|s| s.rating).sum();
+        assert!((total - 4.0 * INITIAL_RATING).abs() < 1e-6);
+
+        // the same with more threads
+        assert_eq!(tournament.threads(3).run().unwrap(), result);
+
+        let csv = result.standings_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], "name,games,wins,win_rate,average_score,rating");
+        assert!(lines[4].starts_with("\"random, again\",9,"));
+        assert_eq!(result.games_csv().lines().count(), 13);
+        let json: serde_json::Value = serde_json::from_str(&result.to_json()).unwrap();
+        assert_eq!(json["standings"][1]["name"], "greedy");
+    }
+
+    #[test]
+    fn swiss() {
+        let result = Tournament::new(entrants())
+            .pairing(Pairing::Swiss { rounds: 3 })
+            .seed(1)
+            .threads(2)
+            .run()
+            .unwrap();
+        // 2 tables of 2 in each round, with 2 rotations each
+        assert_eq!(result.games.len(), 12);
+        assert!(result.standings.iter().all(|s| s.games == 6));
+        assert_eq!(result.games[11].round, 2);
+
+        // with 5 entrants, everyone sits out once and meets everyone else once
+        let result = Tournament::new(entrants())
+            .entrant(Entrant::new("random, thrice", |_, seed| {
+                Box::new(RandomAgent::new(seed))
+            }))
+            .pairing(Pairing::Swiss { rounds: 5 })
+            .seed(2)
+            .threads(2)
+            .run()
+            .unwrap();
+        assert_eq!(result.games.len(), 20);
+        assert!(result.standings.iter().all(|s| s.games == 8));
+        let mut tables: Vec<Vec<usize>> = result
+            .games
+            .chunks(2)
+            .map(|rotations| {
+                let mut seats = rotations[0].seats.clone();
+                seats.sort_unstable();
+                seats
+            })
+            .collect();
+        tables.sort();
+        tables.dedup();
+        assert_eq!(tables.len(), 10);
+
+        assert!(matches!(
+            Tournament::new(entrants()).table_size(5).run(),
+            Err(InnovationError::WrongPlayerNum)
+        ));
+    }
+}
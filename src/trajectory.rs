@@ -0,0 +1,369 @@
+//! Recording self-play games as training data.
+//!
+//! A `TrajectoryRecorder` given to `Match::record` stores a record for
+//! every decision of every seat, in the action space of `codec`: a card
+//! choice of several cards is a decision per picked card and one for
+//! "done", as made by `SequentialDecoder`. The recorder observes the game
+//! too, and keeps only the actions the game accepts.
+//!
+//! Records are written when their game ends, and dropped if it fails, to a
+//! directory holding
+//!
+//! - `manifest.json`, a `Manifest` with the sizes of the encodings and the
+//!   card names they are based on;
+//! - `trajectories-00000.jsonl`, `trajectories-00001.jsonl`, ..., with a
+//!   `Decision` as JSON per line and at most `records_per_shard` lines each.
+//!
+//! A decision looks like
+//!
+//! ```text
+//! {"game":0,"step":3,"player":1,"observation":[1,0,...],"picked":[],
+//!  "legal":[0,5,29],"action":5,"won":false,"score":4}
+//! ```
+//!
+//! where `observation` is the encoding of `encoder` seen by `player`,
+//! `picked` the indices of the cards picked so far in a card choice,
+//! `legal` the legal action indices, which is the mask of `codec`, and
+//! `action` the chosen one. `won` and `score` tell how the game ended for
+//! `player`.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    mem,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    action::Action,
+    card::Card,
+    codec::{ActionCodec, DiscreteAction},
+    encoder::ObservationEncoder,
+    game::{OuterGame, PlayerId},
+    logger::{Item, Observer},
+};
+
+/// The version of the files written by `TrajectoryRecorder`.
+pub const TRAJECTORY_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub observation_size: usize,
+    pub action_size: usize,
+    /// The card list the encodings are based on, in order.
+    pub card_names: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Decision {
+    /// The number of the game among those recorded.
+    pub game: usize,
+    /// The number of the decision in the game.
+    pub step: usize,
+    pub player: PlayerId,
+    pub observation: Vec<u8>,
+    pub picked: Vec<usize>,
+    pub legal: Vec<usize>,
+    pub action: usize,
+    pub won: bool,
+    pub score: usize,
+}
+
+// decisions of a game, before its outcome is known
+struct Pending {
+    player: PlayerId,
+    observation: Vec<u8>,
+    picked: Vec<usize>,
+    legal: Vec<usize>,
+    action: usize,
+}
+
+pub struct TrajectoryRecorder {
+    encoder: ObservationEncoder,
+    codec: ActionCodec,
+    dir: PathBuf,
+    records_per_shard: usize,
+    shard: usize,
+    in_shard: usize,
+    file: Option<BufWriter<File>>,
+    num_games: usize,
+    // the decisions of the action being tried, and of those accepted
+    proposed: Option<(Action, Vec<Pending>)>,
+    accepted: Vec<Pending>,
+    error: Option<io::Error>,
+}
+
+impl TrajectoryRecorder {
+    /// Creates `dir` if needed and writes the manifest for `cards` in it.
+    pub fn create(dir: impl AsRef<Path>, cards: &[&Card]) -> io::Result<TrajectoryRecorder> {
+        let dir = dir.as_ref().to_owned();
+        fs::create_dir_all(&dir)?;
+        let recorder = TrajectoryRecorder {
+            encoder: ObservationEncoder::new(cards),
+            codec: ActionCodec::new(cards),
+            dir,
+            records_per_shard: 100_000,
+            shard: 0,
+            in_shard: 0,
+            file: None,
+            num_games: 0,
+            proposed: None,
+            accepted: Vec::new(),
+            error: None,
+        };
+        let manifest = Manifest {
+            version: TRAJECTORY_VERSION,
+            observation_size: recorder.encoder.size(),
+            action_size: recorder.codec.size(),
+            card_names: recorder.codec.card_names().to_vec(),
+        };
+        fs::write(
+            recorder.dir.join("manifest.json"),
+            serde_json::to_string_pretty(&manifest)?,
+        )?;
+        Ok(recorder)
+    }
+
+    /// The largest number of records in a file, 100000 by default.
+    pub fn records_per_shard(mut self, records_per_shard: usize) -> TrajectoryRecorder {
+        self.records_per_shard = records_per_shard.max(1);
+        self
+    }
+
+    /// The path of the `index`th shard in `dir`.
+    pub fn shard_path(dir: impl AsRef<Path>, index: usize) -> PathBuf {
+        dir.as_ref()
+            .join(format!("trajectories-{:05}.jsonl", index))
+    }
+
+    pub fn num_games(&self) -> usize {
+        self.num_games
+    }
+
+    /// Records that `player` tries `action` in the current state of `game`.
+    ///
+    /// It's kept if the game notifies the action next.
+    pub fn propose(&mut self, game: &OuterGame, player: PlayerId, action: &Action) {
+        let indices = match self.codec.encode(action, game) {
+            Some(indices) => indices,
+            None => {
+                self.proposed = None;
+                return;
+            }
+        };
+        let observation = self.encoder.encode_u8(&game.observe(player));
+        let mut picked_names = Vec::new();
+        let mut picked = Vec::new();
+        let mut decisions = Vec::new();
+        for index in indices {
+            let legal = self
+                .codec
+                .mask(game, &picked_names)
+                .into_iter()
+                .enumerate()
+                .filter(|(_, legal)| *legal)
+                .map(|(i, _)| i)
+                .collect();
+            decisions.push(Pending {
+                player,
+                observation: observation.clone(),
+                picked: picked.clone(),
+                legal,
+                action: index,
+            });
+            if let Some(DiscreteAction::Pick(name)) = self.codec.from_index(index) {
+                picked.push(self.codec.card_index(&name).unwrap());
+                picked_names.push(name);
+            }
+        }
+        self.proposed = Some((action.clone(), decisions));
+    }
+
+    /// Drops the decisions left by a game that didn't end, before a new one.
+    pub fn begin_game(&mut self) {
+        self.proposed = None;
+        self.accepted.clear();
+    }
+
+    /// Writes the decisions of the game with its outcome.
+    pub fn end_game(&mut self, winners: &[PlayerId], scores: &[usize]) {
+        self.proposed = None;
+        let game = self.num_games;
+        self.num_games += 1;
+        for (step, pending) in mem::take(&mut self.accepted).into_iter().enumerate() {
+            let decision = Decision {
+                game,
+                step,
+                player: pending.player,
+                observation: pending.observation,
+                picked: pending.picked,
+                legal: pending.legal,
+                action: pending.action,
+                won: winners.contains(&pending.player),
+                score: scores[pending.player],
+            };
+            if self.error.is_none() {
+                if let Err(error) = self.write(&decision) {
+                    self.error = Some(error);
+                }
+            }
+        }
+    }
+
+    fn write(&mut self, decision: &Decision) -> io::Result<()> {
+        if self.in_shard == self.records_per_shard {
+            self.flush()?;
+            self.file = None;
+            self.shard += 1;
+            self.in_shard = 0;
+        }
+        if self.file.is_none() {
+            let file = File::create(Self::shard_path(&self.dir, self.shard))?;
+            self.file = Some(BufWriter::new(file));
+        }
+        let file = self.file.as_mut().unwrap();
+        serde_json::to_writer(&mut *file, decision)?;
+        writeln!(file)?;
+        self.in_shard += 1;
+        Ok(())
+    }
+
+    /// Writes the buffered records, returning the first error since the
+    /// last flush.
+    pub fn flush(&mut self) -> io::Result<()> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'c> Observer<'c> for TrajectoryRecorder {
+    fn on_notify(&mut self, event: &Item<'c>) {
+        if let Item::Action(_, action) = event {
+            match self.proposed.take() {
+                Some((proposed, decisions)) if proposed == action.no_ref() => {
+                    self.accepted.extend(decisions)
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Reads the decisions of a shard.
+pub fn read_shard(path: impl AsRef<Path>) -> io::Result<Vec<Decision>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .map(|line| Ok(serde_json::from_str(&line?)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        action::NoRefStep,
+        agent::{Agent, Match, RandomAgent},
+        card::default_cards,
+        error::InnovationError,
+        game::GameConfig,
+        observation::Observation,
+    };
+    use std::{cell::RefCell, rc::Rc};
+
+    // plays randomly, then tries an invalid action after `acts` actions
+    struct Quitting {
+        agent: RandomAgent,
+        acts: usize,
+    }
+
+    impl Agent for Quitting {
+        fn act(&mut self, obs: &Observation) -> Action {
+            if self.acts == 0 {
+                return Action::Step(NoRefStep::Achieve(10));
+            }
+            self.acts -= 1;
+            self.agent.act(obs)
+        }
+    }
+
+    #[test]
+    fn record_matches() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let dir = std::env::temp_dir().join(format!("inn-trajectories-{}", std::process::id()));
+        let recorder = TrajectoryRecorder::create(&dir, &cards)
+            .unwrap()
+            .records_per_shard(40);
+        let recorder = Rc::new(RefCell::new(recorder));
+        let agents: Vec<Box<dyn Agent>> =
+            vec![Box::new(RandomAgent::new(1)), Box::new(RandomAgent::new(2))];
+        let config = |seed| GameConfig::standard(cards.clone(), seed);
+        // a game aborted midway leaves nothing to the next
+        let quitting: Vec<Box<dyn Agent>> = (0..2)
+            .map(|id| -> Box<dyn Agent> {
+                Box::new(Quitting {
+                    agent: RandomAgent::new(id),
+                    acts: 5,
+                })
+            })
+            .collect();
+        let aborted = Match::new(quitting)
+            .record(recorder.clone())
+            .play(config(5));
+        assert!(matches!(aborted, Err(InnovationError::InvalidAction)));
+
+        let mut play = Match::new(agents).record(recorder.clone());
+        let mut results = Vec::new();
+        for seed in 0..2 {
+            results.push(play.play(config(seed)).unwrap());
+        }
+        recorder.borrow_mut().flush().unwrap();
+        assert_eq!(recorder.borrow().num_games(), 2);
+
+        let manifest: Manifest =
+            serde_json::from_str(&fs::read_to_string(dir.join("manifest.json")).unwrap()).unwrap();
+        assert_eq!(manifest.card_names.len(), cards.len());
+        let mut decisions = Vec::new();
+        for index in 0.. {
+            let path = TrajectoryRecorder::shard_path(&dir, index);
+            if !path.exists() {
+                break;
+            }
+            let shard = read_shard(&path).unwrap();
+            assert!(shard.len() <= 40);
+            decisions.extend(shard);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(decisions.len() > 40);
+        for decision in &decisions {
+            assert_eq!(decision.observation.len(), manifest.observation_size);
+            assert!(decision.legal.contains(&decision.action));
+            assert!(decision.legal.iter().all(|&i| i < manifest.action_size));
+            let result = &results[decision.game];
+            assert_eq!(decision.won, result.winners.contains(&decision.player));
+            assert_eq!(decision.score, result.scores[decision.player]);
+        }
+        // every action of the games is recorded, each with a decision or more
+        for (game, result) in results.iter().enumerate() {
+            let num_actions = result.logger.history()[0]
+                .items
+                .iter()
+                .filter(|item| matches!(item, Item::Action(..)))
+                .count();
+            let steps: Vec<_> = decisions.iter().filter(|d| d.game == game).collect();
+            assert!(steps.len() >= num_actions);
+            let first_picks = steps.iter().filter(|d| d.picked.is_empty()).count();
+            assert_eq!(first_picks, num_actions);
+            assert!(steps.iter().enumerate().all(|(i, d)| d.step == i));
+        }
+    }
+}
@@ -1,8 +1,8 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
 use crate::{error::InnResult, game::Players, logger::Item};
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Turn {
     step: usize,
     num_players: usize,
@@ -28,6 +28,10 @@ impl Turn {
         self.is_second_step
     }
 
+    pub fn num_players(&self) -> usize {
+        self.num_players
+    }
+
     pub fn player_id(&self) -> usize {
         self.current_player
     }
@@ -117,6 +121,11 @@ impl<'c, 'g> LoggingTurn<'c, 'g> {
     pub fn turn(&self) -> &Turn {
         &self.turn
     }
+
+    /// Sets the turn back to `turn`, without notifying.
+    pub fn restore(&mut self, turn: Turn) {
+        self.turn = turn;
+    }
 }
 
 #[cfg(test)]
@@ -7,6 +7,118 @@ where
     v1.iter().copied().collect::<HashSet<_>>() == HashSet::from(v2)
 }
 
+/// Number of ways to choose between `min_num` and `max_num` items from `n` items.
+///
+/// Saturates at `usize::MAX` instead of overflowing.
+pub fn num_combinations(n: usize, min_num: usize, max_num: usize) -> usize {
+    let mut total: usize = 0;
+    // n choose 0
+    let mut current: usize = 1;
+    for k in 0..=max_num.min(n) {
+        if k >= min_num {
+            total = total.saturating_add(current);
+        }
+        // n choose (k + 1) = n choose k * (n - k) / (k + 1)
+        current = match current.checked_mul(n - k) {
+            Some(v) => v / (k + 1),
+            None => usize::MAX,
+        };
+    }
+    total
+}
+
+/// All subsets of `items` with `min_num` to `max_num` elements, keeping the original order.
+pub fn combinations<T: Clone>(items: &[T], min_num: usize, max_num: usize) -> Vec<Vec<T>> {
+    fn helper<T: Clone>(
+        items: &[T],
+        min_num: usize,
+        max_num: usize,
+        chosen: &mut Vec<T>,
+        res: &mut Vec<Vec<T>>,
+    ) {
+        if chosen.len() >= min_num {
+            res.push(chosen.clone());
+        }
+        if chosen.len() == max_num {
+            return;
+        }
+        for (i, item) in items.iter().enumerate() {
+            chosen.push(item.clone());
+            helper(&items[i + 1..], min_num, max_num, chosen, res);
+            chosen.pop();
+        }
+    }
+    let mut res = Vec::new();
+    helper(items, min_num, max_num, &mut Vec::new(), &mut res);
+    res
+}
+
+/// A line of comma-separated values, quoting the fields with commas, quotes
+/// or line breaks.
+pub fn csv_line<S: AsRef<str>>(fields: &[S]) -> String {
+    fields
+        .iter()
+        .map(|field| {
+            let field = field.as_ref();
+            if field.contains(&[',', '"', '\n', '\r'][..]) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The records of comma-separated values as written by `csv_line`, one per
+/// line. Quoted fields may hold line breaks.
+///
+/// Fails with the line number, from 1, of a quote which is never closed.
+pub fn csv_records(text: &str) -> Result<Vec<Vec<String>>, usize> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut quote_line = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\n' {
+            line += 1;
+        }
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            _ if quoted => field.push(c),
+            '"' => {
+                quoted = true;
+                quote_line = line;
+            }
+            ',' => record.push(std::mem::take(&mut field)),
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(quote_line);
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    Ok(records)
+}
+
 pub trait FromRef<T> {
     fn from_ref(t: &T) -> Self;
 }
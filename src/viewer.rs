@@ -0,0 +1,358 @@
+//! Exporting recorded games as HTML pages for reviewing.
+//!
+//! `export_html` replays a `logger::Game` and writes a single HTML file with
+//! a frame before the first action and after every action. A frame shows
+//! the boards, hands, score piles and achievements of all players, the draw
+//! piles and the available achievements, and narrates the items since the
+//! last frame. The page has its script and style inline, so it needs no
+//! network access.
+
+use serde::Serialize;
+use strum::IntoEnumIterator;
+
+use crate::{
+    card::{Card, Color, Icon, Splay},
+    error::InnResult,
+    game::{GameConfig, OuterGame, PlayerId},
+    logger::{Game, Item, Observer},
+    narrator::Narrator,
+    notation::format_action,
+    render::achievement,
+    replay::Replayer,
+};
+
+#[derive(Serialize)]
+struct StackFrame {
+    color: String,
+    splay: String,
+    /// From the top.
+    cards: Vec<String>,
+    /// The icons of the top card, with "" for its empty position.
+    top_icons: Vec<String>,
+    /// The visible icons of the stack.
+    icons: Vec<(String, usize)>,
+}
+
+#[derive(Serialize)]
+struct PlayerFrame {
+    stacks: Vec<StackFrame>,
+    icons: Vec<(String, usize)>,
+    hand: Vec<String>,
+    score: Vec<String>,
+    total_score: usize,
+    achievements: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Frame {
+    title: String,
+    acting_player: PlayerId,
+    main_pile: [usize; 10],
+    achievements: Vec<String>,
+    players: Vec<PlayerFrame>,
+    events: Vec<String>,
+}
+
+fn card(card: &Card) -> String {
+    format!("{} ({})", card.name(), card.age())
+}
+
+fn icon_counts(count: &counter::Counter<Icon>) -> Vec<(String, usize)> {
+    Icon::iter()
+        .map(|icon| (format!("{:?}", icon), count[&icon]))
+        .collect()
+}
+
+fn frame(game: &OuterGame, title: String, events: Vec<String>) -> Frame {
+    let players = game.players();
+    let pile = players.main_card_pile().borrow();
+    Frame {
+        title,
+        acting_player: game.acting_player(),
+        main_pile: pile.view(),
+        achievements: pile.achievement_views().iter().map(achievement).collect(),
+        players: (0..players.num_players())
+            .map(|id| {
+                let view = players.player_at(id).self_view();
+                PlayerFrame {
+                    stacks: Color::iter()
+                        .map(|color| {
+                            let stack = view.board.get_stack(color);
+                            StackFrame {
+                                color: format!("{:?}", color),
+                                splay: match stack.splay_direction() {
+                                    Splay::NoSplay => String::new(),
+                                    splay => format!("{:?}", splay),
+                                },
+                                cards: stack.iter().map(card).collect(),
+                                top_icons: stack
+                                    .top_card()
+                                    .map(|c| {
+                                        c.icons()
+                                            .iter()
+                                            .map(|&icon| match icon {
+                                                Icon::Empty => String::new(),
+                                                icon => format!("{:?}", icon),
+                                            })
+                                            .collect()
+                                    })
+                                    .unwrap_or_default(),
+                                icons: icon_counts(&stack.icon_count()),
+                            }
+                        })
+                        .collect(),
+                    icons: icon_counts(&view.board.icon_count()),
+                    hand: view.hand.iter().map(|&c| card(c)).collect(),
+                    score: view.score.iter().map(|&c| card(c)).collect(),
+                    total_score: view.score.iter().map(|c| c.age() as usize).sum(),
+                    achievements: view.achievements.iter().map(achievement).collect(),
+                }
+            })
+            .collect(),
+        events,
+    }
+}
+
+// the narrated lines of `items`, continuing the narration so far
+fn narrate(narrator: &mut Narrator<Vec<u8>>, items: &[Item]) -> Vec<String> {
+    for item in items {
+        narrator.on_notify(item);
+    }
+    narrator.flush().expect("Writing to a vector can't fail.");
+    let text =
+        String::from_utf8(std::mem::take(narrator.get_mut())).expect("Narration should be UTF-8.");
+    text.lines().map(str::to_owned).collect()
+}
+
+/// Replays `record` with `config`, as `Replayer::new` does, and writes the
+/// page showing it.
+pub fn export_html<'c>(record: &Game<'c>, config: GameConfig<'c>) -> InnResult<String> {
+    let mut replayer = Replayer::new(record, config)?;
+    let actions: Vec<usize> = record
+        .items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| matches!(item, Item::Action(..)))
+        .map(|(index, _)| index)
+        .collect();
+    let mut narrator = Narrator::new(Vec::new());
+    let first = actions.first().copied().unwrap_or(record.items.len());
+    let mut frames = vec![frame(
+        replayer.game(),
+        "Setup".to_owned(),
+        narrate(&mut narrator, &record.items[..first]),
+    )];
+    for (number, &start) in actions.iter().enumerate() {
+        let acting_player = replayer.game().acting_player();
+        let action = replayer
+            .step()?
+            .expect("Every recorded action is replayed.");
+        let end = actions
+            .get(number + 1)
+            .copied()
+            .unwrap_or(record.items.len());
+        let mut title = format!(
+            "Action {}: Player {} {}",
+            number + 1,
+            acting_player + 1,
+            format_action(&action)
+        );
+        if end == record.items.len() {
+            if let Some(winners) = replayer.game().winners() {
+                let winners: Vec<String> = winners
+                    .iter()
+                    .map(|id| format!("Player {}", id + 1))
+                    .collect();
+                title += &format!(" (game over, won by {})", winners.join(" and "));
+            }
+        }
+        let events = narrate(&mut narrator, &record.items[start..end]);
+        frames.push(frame(replayer.game(), title, events));
+    }
+
+    // no "</script>" may end the data early
+    let data = serde_json::to_string(&frames)
+        .expect("Frames should be serializable.")
+        .replace("</", "<\\/");
+    Ok(TEMPLATE.replace("/*FRAMES*/", &data))
+}
+
+const TEMPLATE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>Innovation replay</title>
+<style>
+body { font-family: sans-serif; margin: 1em; background: #f4f1ea; color: #222; }
+#controls { position: sticky; top: 0; background: #f4f1ea; padding: 0.5em 0; }
+#controls button { min-width: 3em; }
+#position { width: 30em; vertical-align: middle; }
+#title { font-weight: bold; margin-top: 0.5em; }
+#layout { display: flex; gap: 1em; align-items: flex-start; }
+#players { flex: 3; }
+#side { flex: 1; }
+.player { background: #fff; border: 1px solid #ccc; border-radius: 6px; padding: 0.5em; margin-bottom: 1em; }
+.player.acting { border: 2px solid #333; }
+.stacks { display: flex; gap: 0.5em; }
+.stack { flex: 1; border-radius: 4px; padding: 0.3em; color: #fff; min-height: 4em; font-size: 0.9em; }
+.stack.Blue { background: #3f6fb5; }
+.stack.Red { background: #b8403f; }
+.stack.Green { background: #3f8f4f; }
+.stack.Yellow { background: #c9a227; }
+.stack.Purple { background: #7b4ca6; }
+.stack .top { font-weight: bold; }
+.stack .under { opacity: 0.8; }
+.small { font-size: 0.85em; color: #555; }
+.stack .small { color: #eee; }
+#events { background: #fff; border: 1px solid #ccc; border-radius: 6px; padding: 0.5em; white-space: pre-wrap; font-size: 0.9em; }
+</style>
+</head>
+<body>
+<div id="controls">
+<button id="first">|&lt;</button>
+<button id="prev">&lt;</button>
+<button id="next">&gt;</button>
+<button id="last">&gt;|</button>
+<input id="position" type="range" min="0" value="0">
+<span id="counter"></span>
+<div id="title"></div>
+</div>
+<div id="layout">
+<div id="players"></div>
+<div id="side">
+<div id="shared"></div>
+<h3>Events</h3>
+<div id="events"></div>
+</div>
+</div>
+<script id="frames" type="application/json">/*FRAMES*/</script>
+<script>
+"use strict";
+const frames = JSON.parse(document.getElementById("frames").textContent);
+let current = 0;
+
+function element(tag, className, text) {
+  const e = document.createElement(tag);
+  if (className) e.className = className;
+  if (text !== undefined) e.textContent = text;
+  return e;
+}
+
+function icons(counts) {
+  return counts.filter(([, n]) => n > 0).map(([icon, n]) => icon + " " + n).join(", ") || "no icons";
+}
+
+function list(items) {
+  return items.length ? items.join(", ") : "none";
+}
+
+function showPlayer(player, id, acting) {
+  const box = element("div", "player" + (acting ? " acting" : ""));
+  box.appendChild(element("h3", "", "Player " + (id + 1) + (acting ? " (acting)" : "")));
+  const stacks = element("div", "stacks");
+  for (const stack of player.stacks) {
+    const s = element("div", "stack " + stack.color);
+    if (stack.cards.length) {
+      s.appendChild(element("div", "top", stack.cards[0]));
+      s.appendChild(element("div", "small", "[" + stack.top_icons.map(i => i || "-").join(" ") + "]"));
+      for (const card of stack.cards.slice(1)) s.appendChild(element("div", "under", card));
+      const splay = stack.splay ? "splayed " + stack.splay.toLowerCase() + "; " : "";
+      s.appendChild(element("div", "small", splay + icons(stack.icons)));
+    } else {
+      s.appendChild(element("div", "small", stack.color));
+    }
+    stacks.appendChild(s);
+  }
+  box.appendChild(stacks);
+  box.appendChild(element("p", "", "Icons: " + icons(player.icons)));
+  box.appendChild(element("p", "", "Hand: " + list(player.hand)));
+  box.appendChild(element("p", "", "Score (" + player.total_score + "): " + list(player.score)));
+  box.appendChild(element("p", "", "Achievements: " + list(player.achievements)));
+  return box;
+}
+
+function show(index) {
+  current = Math.max(0, Math.min(frames.length - 1, index));
+  const frame = frames[current];
+  document.getElementById("position").value = current;
+  document.getElementById("counter").textContent = current + " / " + (frames.length - 1);
+  document.getElementById("title").textContent = frame.title;
+  const players = document.getElementById("players");
+  players.replaceChildren(...frame.players.map((p, id) => showPlayer(p, id, id === frame.acting_player)));
+  const shared = document.getElementById("shared");
+  shared.replaceChildren(
+    element("h3", "", "Draw piles"),
+    element("p", "", frame.main_pile.map((n, age) => (age + 1) + ": " + n).join(", ")),
+    element("h3", "", "Available achievements"),
+    element("p", "", list(frame.achievements)));
+  document.getElementById("events").textContent = frame.events.join("\n");
+}
+
+document.getElementById("position").max = frames.length - 1;
+document.getElementById("position").addEventListener("input", e => show(Number(e.target.value)));
+document.getElementById("first").addEventListener("click", () => show(0));
+document.getElementById("prev").addEventListener("click", () => show(current - 1));
+document.getElementById("next").addEventListener("click", () => show(current + 1));
+document.getElementById("last").addEventListener("click", () => show(frames.length - 1));
+document.addEventListener("keydown", e => {
+  if (e.key === "ArrowLeft") show(current - 1);
+  if (e.key === "ArrowRight") show(current + 1);
+  if (e.key === "Home") show(0);
+  if (e.key === "End") show(frames.length - 1);
+});
+show(0);
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        agent::{Agent, HeuristicAgent, Match, RandomAgent},
+        card::default_cards,
+    };
+
+    #[test]
+    fn export() {
+        let cards = default_cards::default_cards();
+        let cards: Vec<_> = cards.iter().collect();
+        let config = || GameConfig::standard(cards.clone(), 6);
+        let agents: Vec<Box<dyn Agent>> = vec![
+            Box::new(RandomAgent::new(6)),
+            Box::new(HeuristicAgent::new()),
+        ];
+        let logger = Match::new(agents).play(config()).unwrap().logger;
+        let record = &logger.history()[0];
+        let html = export_html(record, config()).unwrap();
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(!html.contains("http"));
+        let start = html.find("application/json\">").unwrap() + "application/json\">".len();
+        let end = start + html[start..].find("</script>").unwrap();
+        let frames: serde_json::Value = serde_json::from_str(&html[start..end]).unwrap();
+        let frames = frames.as_array().unwrap();
+        let num_actions = record
+            .items
+            .iter()
+            .filter(|item| matches!(item, Item::Action(..)))
+            .count();
+        assert_eq!(frames.len(), num_actions + 1);
+        assert_eq!(frames[0]["title"], "Setup");
+        assert_eq!(frames[0]["events"][0], "Setup");
+        assert_eq!(frames[0]["players"][0]["hand"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            frames[0]["players"][1]["stacks"].as_array().unwrap().len(),
+            5
+        );
+        assert!(frames[1]["title"]
+            .as_str()
+            .unwrap()
+            .starts_with("Action 1: Player 1 pick "));
+        assert!(frames.last().unwrap()["title"]
+            .as_str()
+            .unwrap()
+            .contains("game over"));
+    }
+}